@@ -1,22 +1,50 @@
 use std::panic;
 use std::str::FromStr;
-use std::{ops::Deref, sync::Arc, time::SystemTime};
+use std::{
+    ops::Deref,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use async_trait::async_trait;
 use aws_credential_types::{
-    cache::CredentialsCache, provider::SharedCredentialsProvider, Credentials,
+    cache::CredentialsCache,
+    provider::{ProvideCredentials, SharedCredentialsProvider},
+    Credentials,
 };
+use aws_runtime::auth::sigv4::SigV4OperationSigningConfig;
 use aws_sdk_s3::{
-    config::{AsyncSleep, Config, Region, SharedAsyncSleep, Sleep},
+    config::{timeout::TimeoutConfig, AsyncSleep, Config, Region, SharedAsyncSleep, Sleep},
     primitives::SdkBody,
+    types::ObjectCannedAcl,
     Client,
 };
+use aws_sigv4::http_request::SignableBody;
 use aws_smithy_async::time::{SharedTimeSource, TimeSource};
 use aws_smithy_http::result::ConnectorError;
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::{
+            context::BeforeTransmitInterceptorContextMut, Interceptor, SharedInterceptor,
+        },
+        runtime_components::RuntimeComponents,
+    },
+};
+use aws_smithy_types::config_bag::ConfigBag;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_timer::UNIX_EPOCH;
+use web_sys::RequestCache;
 
-use crate::aws::{error::Error, AmazonS3};
+use crate::aws::{
+    error::Error,
+    express::{self, SessionAuth, Sessions},
+    retry::RetryPolicy,
+    AmazonS3, SseCustomerKey,
+};
+use crate::telemetry::{observe, RequestObserver};
+use crate::utils::str_is_truthy;
+use chrono::Utc;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use snafu::{OptionExt, ResultExt, Snafu};
@@ -36,6 +64,31 @@ enum ConfigError {
     #[snafu(display("URL did not match any known pattern for scheme: {}", url))]
     UrlNotRecognised { url: String },
 
+    #[snafu(display("Unknown fetch cache mode: {}", value))]
+    UnknownFetchCacheMode { value: String },
+
+    #[snafu(display("Unknown canned ACL: {}", value))]
+    UnknownObjectAcl { value: String },
+
+    #[snafu(display(
+        "S3 Express requires a directory bucket (`<name>--<zone id>--x-s3`), not {}",
+        bucket
+    ))]
+    InvalidDirectoryBucket { bucket: String },
+
+    #[snafu(display("S3 Express directory bucket {} requires a region", bucket))]
+    MissingExpressRegion { bucket: String },
+
+    #[snafu(display("Invalid region: '{}'{}", region, hint))]
+    InvalidRegion { region: String, hint: &'static str },
+
+    #[snafu(display("Invalid value for {}: '{}', expected a whole number", key, value))]
+    InvalidNumber { key: String, value: String },
+
+    // NB: never include the key itself
+    #[snafu(display("SSE-C key must be a base64 encoded 256-bit key"))]
+    InvalidSseCustomerKey,
+
     #[snafu(display("Unable parse source url. Url: {}, Error: {}", url, source))]
     UnableToParseUrl {
         source: url::ParseError,
@@ -43,6 +96,39 @@ enum ConfigError {
     },
 }
 
+/// A browser fetch that failed without producing a response
+#[derive(Debug, Snafu)]
+#[snafu(display(
+    "{method} request to {uri} failed ({message}). If the bucket is reachable, its CORS \
+     configuration most likely rejected the request: it must allow this origin, the \
+     {method} method and the headers [{headers}], and expose the ETag header"
+))]
+struct FetchError {
+    method: String,
+    uri: String,
+    headers: String,
+    message: String,
+}
+
+impl FetchError {
+    fn new(parts: &http::request::Parts) -> Self {
+        Self {
+            method: parts.method.to_string(),
+            uri: parts.uri.to_string(),
+            headers: parts.headers.keys().map(|name| name.as_str()).join(", "),
+            message: String::new(),
+        }
+    }
+
+    fn with_cause(mut self, err: JsValue) -> Self {
+        self.message = match err.dyn_ref::<js_sys::Error>() {
+            Some(err) => String::from(err.message()),
+            None => err.as_string().unwrap_or_else(|| format!("{:?}", err)),
+        };
+        self
+    }
+}
+
 impl From<ConfigError> for object_store::Error {
     fn from(source: ConfigError) -> Self {
         match source {
@@ -67,6 +153,22 @@ pub enum AmazonS3ConfigKey {
     SessionToken,
     Bucket,
     Endpoint,
+    FetchCacheMode,
+    SkipPrefixMarker,
+    FetchOwner,
+    UseDualstackEndpoint,
+    ExpectedBucketOwner,
+    ObjectAcl,
+    DecompressByExtension,
+    DryRun,
+    SseCustomerKey,
+    SkipSignature,
+    UnsignedPayload,
+    RetryMaxAttempts,
+    RetryInitialBackoff,
+    RetryMaxBackoff,
+    RetryDeadline,
+    S3Express,
 }
 
 impl AsRef<str> for AmazonS3ConfigKey {
@@ -78,6 +180,22 @@ impl AsRef<str> for AmazonS3ConfigKey {
             Self::Bucket => "aws_bucket",
             Self::Endpoint => "aws_endpoint",
             Self::SessionToken => "aws_session_token",
+            Self::FetchCacheMode => "aws_fetch_cache_mode",
+            Self::SkipPrefixMarker => "aws_skip_prefix_marker",
+            Self::FetchOwner => "aws_fetch_owner",
+            Self::UseDualstackEndpoint => "aws_use_dualstack_endpoint",
+            Self::ExpectedBucketOwner => "aws_expected_bucket_owner",
+            Self::ObjectAcl => "aws_object_acl",
+            Self::DecompressByExtension => "aws_decompress_by_extension",
+            Self::DryRun => "aws_dry_run",
+            Self::SseCustomerKey => "aws_sse_customer_key_base64",
+            Self::SkipSignature => "aws_skip_signature",
+            Self::UnsignedPayload => "aws_unsigned_payload",
+            Self::RetryMaxAttempts => "aws_retry_max_attempts",
+            Self::RetryInitialBackoff => "aws_retry_initial_backoff_ms",
+            Self::RetryMaxBackoff => "aws_retry_max_backoff_ms",
+            Self::RetryDeadline => "aws_retry_deadline_ms",
+            Self::S3Express => "aws_s3_express",
         }
     }
 }
@@ -93,11 +211,107 @@ impl FromStr for AmazonS3ConfigKey {
             "aws_bucket" | "aws_bucket_name" | "bucket_name" | "bucket" => Ok(Self::Bucket),
             "aws_endpoint_url" | "aws_endpoint" | "endpoint_url" | "endpoint" => Ok(Self::Endpoint),
             "aws_session_token" | "aws_token" | "session_token" | "token" => Ok(Self::SessionToken),
+            "aws_fetch_cache_mode" | "fetch_cache_mode" => Ok(Self::FetchCacheMode),
+            "aws_skip_prefix_marker" | "skip_prefix_marker" => Ok(Self::SkipPrefixMarker),
+            "aws_fetch_owner" | "fetch_owner" => Ok(Self::FetchOwner),
+            "aws_use_dualstack_endpoint" | "use_dualstack_endpoint" => {
+                Ok(Self::UseDualstackEndpoint)
+            }
+            "aws_expected_bucket_owner" | "expected_bucket_owner" => Ok(Self::ExpectedBucketOwner),
+            "aws_object_acl" | "object_acl" | "acl" => Ok(Self::ObjectAcl),
+            "aws_decompress_by_extension" | "decompress_by_extension" => {
+                Ok(Self::DecompressByExtension)
+            }
+            "aws_dry_run" | "dry_run" => Ok(Self::DryRun),
+            "aws_sse_customer_key_base64" | "sse_customer_key_base64" => Ok(Self::SseCustomerKey),
+            "aws_skip_signature" | "skip_signature" => Ok(Self::SkipSignature),
+            "aws_unsigned_payload" | "unsigned_payload" => Ok(Self::UnsignedPayload),
+            "aws_retry_max_attempts" | "retry_max_attempts" | "max_attempts" => {
+                Ok(Self::RetryMaxAttempts)
+            }
+            "aws_retry_initial_backoff_ms" | "retry_initial_backoff_ms" => {
+                Ok(Self::RetryInitialBackoff)
+            }
+            "aws_retry_max_backoff_ms" | "retry_max_backoff_ms" => Ok(Self::RetryMaxBackoff),
+            "aws_retry_deadline_ms" | "retry_deadline_ms" => Ok(Self::RetryDeadline),
+            "aws_s3_express" | "s3_express" => Ok(Self::S3Express),
             _ => Err(ConfigError::UnknownConfigurationKey { key: s.into() }.into()),
         }
     }
 }
 
+/// Parse the configuration value of `key` as a whole number
+fn parse_number(key: AmazonS3ConfigKey, value: String) -> Result<u64, ConfigError> {
+    match value.trim().parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(ConfigError::InvalidNumber {
+            key: key.as_ref().to_string(),
+            value,
+        }),
+    }
+}
+
+/// The region of S3-compatible stores that don't have regions, e.g. Cloudflare R2
+const AUTO_REGION: &str = "auto";
+
+/// Whether `region` is shaped like an AWS region, e.g. `us-east-1`,
+/// `us-gov-west-1` or `cn-northwest-1`
+fn is_aws_region(region: &str) -> bool {
+    let parts = region.split('-').collect::<Vec<_>>();
+    match parts.as_slice() {
+        [area, rest @ .., number] if !rest.is_empty() => {
+            area.len() == 2
+                && area.bytes().all(|c| c.is_ascii_lowercase())
+                && rest
+                    .iter()
+                    .all(|part| !part.is_empty() && part.bytes().all(|c| c.is_ascii_lowercase()))
+                && !number.is_empty()
+                && number.bytes().all(|c| c.is_ascii_digit())
+        }
+        _ => false,
+    }
+}
+
+/// Normalise `region`, and check it is plausible for the (custom, if any) endpoint
+///
+/// AWS regions must be shaped like one, while S3-compatible stores' regions
+/// are only checked for being a single, non-empty word (e.g. `auto`, `nyc3`).
+fn validate_region(region: &str, custom_endpoint: bool) -> Result<String, ConfigError> {
+    let normalised = region.trim().to_ascii_lowercase();
+    let fail = |hint| {
+        Err(ConfigError::InvalidRegion {
+            region: region.to_string(),
+            hint,
+        })
+    };
+    if normalised == AUTO_REGION && !custom_endpoint {
+        return fail(", `auto` only applies to S3-compatible stores with a custom endpoint");
+    }
+    if custom_endpoint {
+        let plausible = !normalised.is_empty()
+            && normalised
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_');
+        if !plausible {
+            return fail("");
+        }
+    } else if !is_aws_region(&normalised) {
+        return fail(", expected an AWS region such as `us-east-1`");
+    }
+    Ok(normalised)
+}
+
+/// Whether `bucket` is an S3 Express One Zone directory bucket
+/// (`<name>--<availability zone id>--x-s3`)
+pub(crate) fn is_directory_bucket(bucket: &str) -> bool {
+    bucket.ends_with("--x-s3")
+}
+
+/// Whether `bucket` is an S3 access point ARN rather than a bucket name
+pub(crate) fn is_access_point_arn(bucket: &str) -> bool {
+    bucket.starts_with("arn:") && bucket.contains(":accesspoint/")
+}
+
 #[derive(Default)]
 pub struct AmazonS3Builder {
     pub(crate) bucket: Option<String>,
@@ -107,12 +321,56 @@ pub struct AmazonS3Builder {
     pub(crate) session_token: Option<String>,
     pub(crate) endpoint: Option<String>,
     pub(crate) url: Option<String>,
+    pub(crate) credentials_provider: Option<SharedCredentialsProvider>,
+    pub(crate) force_credential_refresh: bool,
+    pub(crate) observer: Option<RequestObserver>,
+    pub(crate) fetch_cache_mode: Option<String>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) skip_prefix_marker: bool,
+    pub(crate) fetch_owner: bool,
+    pub(crate) use_dualstack_endpoint: bool,
+    pub(crate) verify_put_etag: bool,
+    pub(crate) checksum_validation: bool,
+    pub(crate) expected_bucket_owner: Option<String>,
+    pub(crate) object_acl: Option<String>,
+    pub(crate) decompress_by_extension: bool,
+    pub(crate) dry_run: bool,
+    pub(crate) sse_customer_key: Option<String>,
+    pub(crate) skip_signature: bool,
+    pub(crate) unsigned_payload: bool,
+    pub(crate) retry_max_attempts: Option<String>,
+    pub(crate) retry_initial_backoff: Option<String>,
+    pub(crate) retry_max_backoff: Option<String>,
+    pub(crate) retry_deadline: Option<String>,
+    pub(crate) s3_express: bool,
 }
 
 impl AmazonS3Builder {
     pub fn new() -> AmazonS3Builder {
         Self::default()
     }
+    /// Set the bucket (and region, endpoint where applicable) from a URL
+    ///
+    /// Supported formats:
+    /// - `s3://<bucket>/<path>`
+    /// - `https://s3.<region>.amazonaws.com/<bucket>/<path>` (path-style)
+    /// - `https://<bucket>.s3.<region>.amazonaws.com/<path>` (virtual-hosted)
+    /// - `https://s3.dualstack.<region>.amazonaws.com/<bucket>/<path>` and
+    ///   `https://<bucket>.s3.dualstack.<region>.amazonaws.com/<path>`, which
+    ///   also enable [dualstack endpoints](Self::with_use_dualstack_endpoint)
+    /// - `https://<account>.r2.cloudflarestorage.com/<bucket>/<path>`
+    /// - `https://<name>-<account id>.s3-accesspoint.<region>.amazonaws.com/<path>`
+    /// - `https://<bucket>.s3express-<zone id>.<region>.amazonaws.com/<path>`,
+    ///   which also enables [S3 Express](Self::with_s3_express)
+    ///
+    /// Only the first segment of a path-style URL names the bucket, so the key
+    /// of `https://s3.<region>.amazonaws.com/<bucket>/<bucket>/nested` is
+    /// `<bucket>/nested`, the same key as that of
+    /// `https://<bucket>.s3.<region>.amazonaws.com/<bucket>/nested`. Take care
+    /// when converting URLs between the two styles not to drop or duplicate a
+    /// leading bucket name. Requests themselves are always made path-style.
     pub fn with_url(mut self, url: impl Into<String>) -> Self {
         self.url = Some(url.into());
         self
@@ -127,6 +385,34 @@ impl AmazonS3Builder {
             AmazonS3ConfigKey::Bucket => self.bucket = Some(value.into()),
             AmazonS3ConfigKey::Endpoint => self.endpoint = Some(value.into()),
             AmazonS3ConfigKey::SessionToken => self.session_token = Some(value.into()),
+            AmazonS3ConfigKey::FetchCacheMode => self.fetch_cache_mode = Some(value.into()),
+            AmazonS3ConfigKey::SkipPrefixMarker => {
+                self.skip_prefix_marker = str_is_truthy(&value.into())
+            }
+            AmazonS3ConfigKey::FetchOwner => self.fetch_owner = str_is_truthy(&value.into()),
+            AmazonS3ConfigKey::UseDualstackEndpoint => {
+                self.use_dualstack_endpoint = str_is_truthy(&value.into())
+            }
+            AmazonS3ConfigKey::ExpectedBucketOwner => {
+                self.expected_bucket_owner = Some(value.into())
+            }
+            AmazonS3ConfigKey::ObjectAcl => self.object_acl = Some(value.into()),
+            AmazonS3ConfigKey::DecompressByExtension => {
+                self.decompress_by_extension = str_is_truthy(&value.into())
+            }
+            AmazonS3ConfigKey::DryRun => self.dry_run = str_is_truthy(&value.into()),
+            AmazonS3ConfigKey::SseCustomerKey => self.sse_customer_key = Some(value.into()),
+            AmazonS3ConfigKey::SkipSignature => self.skip_signature = str_is_truthy(&value.into()),
+            AmazonS3ConfigKey::UnsignedPayload => {
+                self.unsigned_payload = str_is_truthy(&value.into())
+            }
+            AmazonS3ConfigKey::RetryMaxAttempts => self.retry_max_attempts = Some(value.into()),
+            AmazonS3ConfigKey::RetryInitialBackoff => {
+                self.retry_initial_backoff = Some(value.into())
+            }
+            AmazonS3ConfigKey::RetryMaxBackoff => self.retry_max_backoff = Some(value.into()),
+            AmazonS3ConfigKey::RetryDeadline => self.retry_deadline = Some(value.into()),
+            AmazonS3ConfigKey::S3Express => self.s3_express = str_is_truthy(&value.into()),
         };
         self
     }
@@ -145,7 +431,7 @@ impl AmazonS3Builder {
                     }
                 }
                 Some((account, "r2", "cloudflarestorage", "com")) => {
-                    self.region = Some("auto".to_string());
+                    self.region = Some(AUTO_REGION.to_string());
                     let endpoint = format!("https://{account}.r2.cloudflarestorage.com");
                     self.endpoint = Some(endpoint);
 
@@ -154,6 +440,40 @@ impl AmazonS3Builder {
                         self.bucket = Some(bucket.into());
                     }
                 }
+                Some((alias, "s3-accesspoint", region, "amazonaws.com")) => {
+                    // `<name>-<account id>.s3-accesspoint.<region>.amazonaws.com`
+                    let (name, account) = alias
+                        .rsplit_once('-')
+                        .context(UrlNotRecognisedSnafu { url })?;
+                    self.region = Some(region.to_string());
+                    self.bucket = Some(format!("arn:aws:s3:{region}:{account}:accesspoint/{name}"));
+                }
+                Some(("s3", "dualstack", region, "amazonaws.com")) => {
+                    self.region = Some(region.to_string());
+                    self.use_dualstack_endpoint = true;
+                    let bucket = parsed.path_segments().into_iter().flatten().next();
+                    if let Some(bucket) = bucket {
+                        self.bucket = Some(bucket.into());
+                    }
+                }
+                Some((bucket, "s3", "dualstack", rest)) => {
+                    let region = rest
+                        .strip_suffix(".amazonaws.com")
+                        .context(UrlNotRecognisedSnafu { url })?;
+                    self.bucket = Some(bucket.to_string());
+                    self.region = Some(region.to_string());
+                    self.use_dualstack_endpoint = true;
+                }
+                Some((bucket, zone, region, "amazonaws.com")) if zone.starts_with("s3express-") => {
+                    // `<bucket>.s3express-<availability zone id>.<region>.amazonaws.com`
+                    self.bucket = Some(bucket.to_string());
+                    self.region = Some(region.to_string());
+                    self.s3_express = true;
+                }
+                Some((bucket, "s3", region, "amazonaws.com")) => {
+                    self.bucket = Some(bucket.to_string());
+                    self.region = Some(region.to_string());
+                }
                 _ => return Err(UrlNotRecognisedSnafu { url }.build().into()),
             },
             scheme => return Err(UnknownUrlSchemeSnafu { scheme }.build().into()),
@@ -165,34 +485,175 @@ impl AmazonS3Builder {
             self.parse_url(&url)?;
         }
         panic::set_hook(Box::new(console_error_panic_hook::hook));
-        let access_key_id = self.access_key_id.ok_or(Error::Unknown)?;
-        let secret_access_key = self.secret_access_key.ok_or(Error::Unknown)?;
-        let session_token = self.session_token;
-        let credentials = Credentials::from_keys(
-            access_key_id.deref(),
-            secret_access_key.deref(),
-            session_token,
+        // object reads shouldn't be served stale from the browser's HTTP cache
+        let fetch_cache_mode = match self.fetch_cache_mode {
+            Some(value) => RequestCache::from_js_value(&JsValue::from_str(&value))
+                .context(UnknownFetchCacheModeSnafu { value })?,
+            None => RequestCache::NoStore,
+        };
+        let object_acl = match self.object_acl {
+            Some(value) if ObjectCannedAcl::values().contains(&value.as_str()) => {
+                Some(ObjectCannedAcl::from(value.as_str()))
+            }
+            Some(value) => return Err(ConfigError::UnknownObjectAcl { value }.into()),
+            None => None,
+        };
+        let mut retry_policy = self.retry_policy.unwrap_or_default();
+        if let Some(value) = self.retry_max_attempts {
+            let max_attempts = parse_number(AmazonS3ConfigKey::RetryMaxAttempts, value)?;
+            retry_policy.max_retries = (max_attempts as usize).saturating_sub(1);
+        }
+        if let Some(value) = self.retry_initial_backoff {
+            let millis = parse_number(AmazonS3ConfigKey::RetryInitialBackoff, value)?;
+            retry_policy.initial_backoff = Duration::from_millis(millis);
+        }
+        if let Some(value) = self.retry_max_backoff {
+            let millis = parse_number(AmazonS3ConfigKey::RetryMaxBackoff, value)?;
+            retry_policy.max_backoff = Duration::from_millis(millis);
+        }
+        if let Some(value) = self.retry_deadline {
+            let millis = parse_number(AmazonS3ConfigKey::RetryDeadline, value)?;
+            retry_policy.deadline = Some(Duration::from_millis(millis));
+        }
+        let sse_customer_key = match self.sse_customer_key {
+            Some(key) => {
+                use md5::{Digest, Md5};
+
+                let decoded = aws_smithy_types::base64::decode(&key)
+                    .ok()
+                    .filter(|decoded| decoded.len() == 32)
+                    .context(InvalidSseCustomerKeySnafu)?;
+                let key_md5 = aws_smithy_types::base64::encode(Md5::digest(decoded));
+                Some(SseCustomerKey { key, key_md5 })
+            }
+            None => None,
+        };
+        let (credentials_provider, use_mock) = match self.credentials_provider {
+            // requests are signed with placeholder credentials, and the signature
+            // then stripped (see `Unsigned`)
+            _ if self.skip_signature => {
+                let credentials = Credentials::from_keys("anonymous", "anonymous", None);
+                (SharedCredentialsProvider::new(credentials), false)
+            }
+            Some(provider) => (provider, false),
+            None => {
+                let access_key_id = self.access_key_id.ok_or(Error::Unknown)?;
+                let secret_access_key = self.secret_access_key.ok_or(Error::Unknown)?;
+                let session_token = self.session_token;
+                let credentials = Credentials::from_keys(
+                    access_key_id.deref(),
+                    secret_access_key.deref(),
+                    session_token,
+                );
+                (
+                    SharedCredentialsProvider::new(credentials),
+                    access_key_id == "access_key",
+                )
+            }
+        };
+        // credentials are deliberately not cached, so that every request (and
+        // in particular a forced refresh) asks the provider for credentials
+        let bucket = self.bucket.ok_or(Error::Unknown)?;
+        // scripted responses may be answered with any credentials provider
+        #[cfg(test)]
+        let use_mock = use_mock || bucket == "mock-scripted";
+        let region = self
+            .region
+            .map(|region| validate_region(&region, self.endpoint.is_some()))
+            .transpose()?;
+        // endpoints are validated but otherwise passed through verbatim, so that
+        // non-standard ports (e.g. MinIO/localstack on http://localhost:9000)
+        // survive. Requests always use path-style addressing, so the bucket
+        // never displaces the host:port.
+        let mut endpoint = match self.endpoint {
+            Some(endpoint) => {
+                url::Url::parse(&endpoint).context(UnableToParseUrlSnafu { url: &endpoint })?;
+                Some(endpoint.trim_end_matches('/').to_string())
+            }
+            None => None,
+        };
+        let connector = Adapter::new(
+            use_mock.then(|| MockResponse::for_bucket(&bucket)),
+            self.observer,
+            fetch_cache_mode,
         );
+        let time_source = SharedTimeSource::new(BrowserNow);
+        // directory buckets are sent requests at their zonal endpoint, signed
+        // with the credentials of a session rather than those configured
+        let express = match self.s3_express || is_directory_bucket(&bucket) {
+            true => {
+                let region = region
+                    .clone()
+                    .context(MissingExpressRegionSnafu { bucket: &bucket })?;
+                let zonal_endpoint = express::zonal_endpoint(&bucket, &region)
+                    .context(InvalidDirectoryBucketSnafu { bucket: &bucket })?;
+                let endpoint = endpoint.get_or_insert(zonal_endpoint);
+                Some(Arc::new(Sessions::new(
+                    bucket.clone(),
+                    region,
+                    endpoint,
+                    credentials_provider.clone(),
+                    connector.clone(),
+                    time_source.clone(),
+                )))
+            }
+            false => None,
+        };
+        // the SDK resolves access point ARNs to their own (virtual-hosted) endpoints
         let mut builder = Config::builder()
-            .force_path_style(true)
-            .region(self.region.map(Region::new))
-            .credentials_provider(SharedCredentialsProvider::new(credentials))
+            .force_path_style(!is_access_point_arn(&bucket))
+            .region(region.map(Region::new))
+            .credentials_provider(credentials_provider)
             .credentials_cache(CredentialsCache::no_caching())
+            .use_dual_stack(self.use_dualstack_endpoint)
             .sleep_impl(SharedAsyncSleep::new(BrowserSleep))
-            .time_source(SharedTimeSource::new(BrowserNow))
-            .http_connector(Adapter::new(access_key_id == "access_key"));
-        builder.set_endpoint_url(self.endpoint);
+            .time_source(time_source)
+            .http_connector(connector);
+        builder.set_endpoint_url(endpoint);
+        if let Some(sessions) = &express {
+            builder.push_interceptor(SharedInterceptor::new(SessionAuth(sessions.clone())));
+        }
+        if self.skip_signature {
+            builder.push_interceptor(SharedInterceptor::new(Unsigned));
+        }
+        if self.unsigned_payload {
+            builder.push_interceptor(SharedInterceptor::new(UnsignedPayload));
+        }
+        let mut timeouts = TimeoutConfig::builder();
+        timeouts
+            .set_operation_attempt_timeout(self.timeout)
+            .set_connect_timeout(self.connect_timeout);
+        builder.set_timeout_config(Some(timeouts.build()));
         let sdk_config = builder.build();
         Ok(AmazonS3 {
             client: Arc::new(Client::from_conf(sdk_config)),
-            bucket: self.bucket.ok_or(Error::Unknown)?,
+            bucket,
+            force_credential_refresh: self.force_credential_refresh,
+            retry_policy,
+            skip_prefix_marker: self.skip_prefix_marker,
+            fetch_owner: self.fetch_owner,
+            verify_put_etag: self.verify_put_etag,
+            checksum_validation: self.checksum_validation,
+            expected_bucket_owner: self.expected_bucket_owner,
+            object_acl,
+            decompress_by_extension: self.decompress_by_extension,
+            dry_run: self.dry_run,
+            sse_customer_key,
+            express,
         })
     }
+    /// Set the bucket name, or an access point ARN
+    /// (`arn:aws:s3:<region>:<account id>:accesspoint/<name>`)
     pub fn bucket(mut self, value: impl Into<String>) -> Self {
         self.bucket = Some(value.into());
         self
     }
 
+    /// Set the region, e.g. `us-east-1`, or `auto` for stores without regions
+    /// such as Cloudflare R2
+    ///
+    /// Regions are case-insensitive. Without a custom [endpoint](Self::endpoint)
+    /// the region must be a valid AWS region, `build` fails otherwise.
     pub fn region(mut self, value: impl Into<String>) -> Self {
         self.region = Some(value.into());
         self
@@ -213,6 +674,237 @@ impl AmazonS3Builder {
         self.endpoint = Some(value.into());
         self
     }
+
+    /// Use `provider` to source (and refresh) credentials, instead of static keys
+    pub fn with_credentials_provider(
+        mut self,
+        provider: impl ProvideCredentials + 'static,
+    ) -> Self {
+        self.credentials_provider = Some(SharedCredentialsProvider::new(provider));
+        self
+    }
+
+    /// Send unsigned (anonymous) requests, e.g. to read public buckets, in which
+    /// case no credentials are required
+    ///
+    /// Any configured credentials are ignored. S3 rejects unsigned requests
+    /// to buckets (and objects) that don't allow public access.
+    pub fn with_skip_signature(mut self, skip_signature: bool) -> Self {
+        self.skip_signature = skip_signature;
+        self
+    }
+
+    /// Sign requests with `x-amz-content-sha256: UNSIGNED-PAYLOAD`, rather than
+    /// the SHA256 of the body
+    ///
+    /// Bodies are hashed by default, which S3 requires of some buckets (e.g.
+    /// those whose policies deny unsigned payloads). Skipping the hash saves
+    /// reading large bodies twice, the payload then being covered by TLS alone.
+    pub fn with_unsigned_payload(mut self, unsigned_payload: bool) -> Self {
+        self.unsigned_payload = unsigned_payload;
+        self
+    }
+
+    /// Re-issue a request once, with freshly provided credentials, if S3
+    /// rejects it with `ExpiredToken`/`InvalidToken`
+    ///
+    /// Only useful alongside [`Self::with_credentials_provider`], static
+    /// credentials will simply fail again.
+    pub fn with_force_credential_refresh(mut self, force: bool) -> Self {
+        self.force_credential_refresh = force;
+        self
+    }
+
+    /// Set the `cache` mode of the browser fetch requests reading objects
+    /// (`get` and `head`), one of `default`, `no-store`, `reload`, `no-cache`,
+    /// `force-cache` or `only-if-cached`
+    ///
+    /// Defaults to `no-store`, other requests always use `default`. This is
+    /// applied via the fetch `RequestInit` rather than a `Cache-Control` request
+    /// header, which would trigger CORS preflights.
+    pub fn with_fetch_cache_mode(mut self, mode: impl Into<String>) -> Self {
+        self.fetch_cache_mode = Some(mode.into());
+        self
+    }
+
+    /// Make up to `max_attempts` attempts (including the first) at each request,
+    /// waiting `initial_backoff` before the first retry, doubling thereafter
+    ///
+    /// Browsers throttle rapid bursts of retries, so avoid very short backoffs.
+    /// Which failures are retried is left to the [`RetryPolicy`].
+    pub fn with_retry(mut self, max_attempts: usize, initial_backoff: Duration) -> Self {
+        let retry_policy = self.retry_policy.get_or_insert_with(Default::default);
+        retry_policy.max_retries = max_attempts.saturating_sub(1);
+        retry_policy.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Cap the (exponentially growing) delay between retries at `max_backoff`
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.retry_policy
+            .get_or_insert_with(Default::default)
+            .max_backoff = max_backoff;
+        self
+    }
+
+    /// Give up on a request once `deadline` has passed, across all attempts,
+    /// even with retries remaining
+    ///
+    /// Each attempt is otherwise bounded only by [`with_timeout`](Self::with_timeout),
+    /// so a request can take up to that timeout for every retry, plus backoffs.
+    pub fn with_retry_deadline(mut self, deadline: Duration) -> Self {
+        self.retry_policy
+            .get_or_insert_with(Default::default)
+            .deadline = Some(deadline);
+        self
+    }
+
+    /// Set which failed requests are retried, see [`RetryPolicy`]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Fail each request attempt (rather than the whole, retried, operation)
+    /// that hasn't completed within `timeout`, including reading the response
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Fail requests whose connection isn't established within `timeout`,
+    /// independently of [`with_timeout`](Self::with_timeout)
+    ///
+    /// Browser fetch doesn't expose the connection phase of a request, so the
+    /// fetch connector used in the browser can't enforce this: it only applies
+    /// to connectors that can distinguish connecting from awaiting a response.
+    /// In the browser, bound hung connections with `with_timeout` instead.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Omit the object whose key equals the listed prefix (e.g. the zero-byte
+    /// `folder/` marker some tools create) from `list` and `list_with_delimiter`
+    pub fn with_skip_prefix_marker(mut self, skip: bool) -> Self {
+        self.skip_prefix_marker = skip;
+        self
+    }
+
+    /// Request the owner of each object listed by
+    /// [`list_page`](AmazonS3::list_page) (`fetch-owner=true`), reported in
+    /// [`ListPage::owners`](crate::aws::ListPage::owners)
+    pub fn with_fetch_owner(mut self, fetch_owner: bool) -> Self {
+        self.fetch_owner = fetch_owner;
+        self
+    }
+
+    /// Treat the bucket as an S3 Express One Zone directory bucket
+    /// (`<name>--<zone id>--x-s3`), enabled by default for such bucket names
+    ///
+    /// Requests are then sent to the bucket's zonal endpoint (unless an
+    /// endpoint is configured), and signed with the credentials of a session
+    /// created with `CreateSession`, and replaced before it expires, rather
+    /// than with the configured credentials. A region is required.
+    pub fn with_s3_express(mut self, s3_express: bool) -> Self {
+        self.s3_express = s3_express;
+        self
+    }
+
+    /// Send requests to S3's dualstack (IPv4 and IPv6) endpoints, for networks
+    /// requiring IPv6
+    pub fn with_use_dualstack_endpoint(mut self, use_dualstack_endpoint: bool) -> Self {
+        self.use_dualstack_endpoint = use_dualstack_endpoint;
+        self
+    }
+
+    /// Assert that the bucket is owned by the given AWS account id, sending
+    /// `x-amz-expected-bucket-owner` with every request
+    ///
+    /// Requests to a bucket owned by another account fail with `AccessDenied`,
+    /// guarding against misdirected reads and writes (e.g. following a bucket
+    /// being deleted and its name claimed by another account).
+    pub fn with_expected_bucket_owner(mut self, account_id: impl Into<String>) -> Self {
+        self.expected_bucket_owner = Some(account_id.into());
+        self
+    }
+
+    /// Apply a canned ACL (e.g. `public-read`, `bucket-owner-full-control`) to
+    /// objects written by `put` and `copy`
+    ///
+    /// Buckets with ACLs disabled (the default "bucket owner enforced" object
+    /// ownership setting) reject such writes with `AccessControlListNotSupported`.
+    pub fn with_object_acl(mut self, acl: impl Into<String>) -> Self {
+        self.object_acl = Some(acl.into());
+        self
+    }
+
+    /// Verify that the ETag S3 returns for a `put` is the MD5 of the payload,
+    /// failing the `put` otherwise
+    ///
+    /// S3's ETag is only the MD5 of the content for single part uploads stored
+    /// unencrypted or with SSE-S3. Objects encrypted with SSE-KMS or SSE-C, and
+    /// multipart uploads (whose ETags carry a `-<part count>` suffix), aren't
+    /// verified.
+    pub fn with_verify_put_etag(mut self, verify: bool) -> Self {
+        self.verify_put_etag = verify;
+        self
+    }
+
+    /// Send the SHA256 checksum of each part of a multipart upload, which S3
+    /// verifies on receipt, and verify the composite checksum S3 reports for
+    /// the completed object against those of the parts
+    ///
+    /// Guards against parts corrupted in transit, at the cost of hashing every
+    /// part. Uploads fail with [`Error::ChecksumMismatch`](crate::aws::error::Error::ChecksumMismatch)
+    /// should a checksum not match.
+    pub fn with_checksum_validation(mut self, validate: bool) -> Self {
+        self.checksum_validation = validate;
+        self
+    }
+
+    /// Transparently decompress objects keyed `*.gz` (gzip) or `*.zst` (zstd)
+    /// that are stored without a `Content-Encoding`
+    ///
+    /// Only applies to `get`s of whole objects, ranges of a compressed object
+    /// can't be decoded in isolation. The result's `meta` and `range` still
+    /// describe the stored, compressed, object. Off by default, as consumers of
+    /// such objects usually expect the compressed bytes.
+    pub fn with_decompress_by_extension(mut self, decompress: bool) -> Self {
+        self.decompress_by_extension = decompress;
+        self
+    }
+
+    /// Don't perform `put`, `copy`, `rename` or `delete` requests, instead
+    /// failing them with an error describing the action they would have taken
+    ///
+    /// The bucket's accessibility (credentials, existence and, if configured,
+    /// [ownership](Self::with_expected_bucket_owner)) is checked first, as is
+    /// the existence of a copy's source, so that the dry run error is only
+    /// returned for actions that got past those preflights. Write permissions
+    /// themselves can't be checked without writing.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Encrypt objects with a customer-provided key (SSE-C), the base64
+    /// encoding of a 256-bit AES key
+    ///
+    /// The key is sent with every `get`, `head`, `put` and multipart upload,
+    /// and as both the source and destination key of copies, so all objects
+    /// accessed must be encrypted with it. S3 doesn't store the key: objects
+    /// are unreadable without it. The key is redacted from `Debug` output.
+    pub fn with_sse_customer_key(mut self, key: impl Into<String>) -> Self {
+        self.sse_customer_key = Some(key.into());
+        self
+    }
+
+    /// Invoke `observer` once per HTTP request issued, including retries
+    pub fn with_observer(mut self, observer: RequestObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -241,6 +933,7 @@ trait MakeRequestBrowser {
     async fn send(
         parts: http::request::Parts,
         body: SdkBody,
+        cache: RequestCache,
     ) -> Result<http::Response<SdkBody>, JsValue>;
 }
 
@@ -251,6 +944,7 @@ impl MakeRequestBrowser for BrowserHttpClient {
     async fn send(
         parts: http::request::Parts,
         body: SdkBody,
+        cache: RequestCache,
     ) -> Result<http::Response<SdkBody>, JsValue> {
         use js_sys::{Array, ArrayBuffer, Reflect, Uint8Array};
         use wasm_bindgen_futures::JsFuture;
@@ -258,9 +952,13 @@ impl MakeRequestBrowser for BrowserHttpClient {
         let mut opts = web_sys::RequestInit::new();
         opts.method(parts.method.as_str());
         opts.mode(web_sys::RequestMode::Cors);
+        opts.cache(cache);
 
         let body_pinned = std::pin::Pin::new(body.bytes().unwrap());
-        if body_pinned.len() > 0 {
+        // writes always carry a body, even an empty one, so that zero-byte
+        // objects (e.g. directory markers) are sent with Content-Length: 0
+        let is_write = matches!(parts.method, http::Method::PUT | http::Method::POST);
+        if is_write || !body_pinned.is_empty() {
             let uint_8_array = unsafe { Uint8Array::view(&body_pinned) };
             opts.body(Some(&uint_8_array));
         }
@@ -313,6 +1011,7 @@ impl MakeRequestBrowser for MockedHttpClient {
     async fn send(
         _parts: http::request::Parts,
         _body: SdkBody,
+        _cache: RequestCache,
     ) -> Result<http::Response<SdkBody>, JsValue> {
         let body = "{
             \"Functions\": [
@@ -331,14 +1030,673 @@ impl MakeRequestBrowser for MockedHttpClient {
     }
 }
 
+/// The canned response of the mocked client, selected by the bucket name so
+/// that parsers' handling of malformed responses can be exercised
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum MockResponse {
+    Default,
+    /// A `ListObjectsV2` response cut off mid-way through an entry
+    TruncatedListing,
+    /// A server error whose XML error envelope is cut off
+    MalformedError,
+    /// Conditional reads, answered as S3 would for an object since changed to
+    /// `abcd` (ETag `"v2"`)
+    ConditionalGet,
+    /// `DeleteObjects` requests, failing keys under `denied/` as `AccessDenied`
+    BulkDelete,
+    /// Objects whose conditional puts are answered as S3 would, their ETags
+    /// being the MD5 of their content
+    ConditionalPut,
+    /// A listing of [`MockedHttpClient::LISTED_KEYS`], two keys to a page
+    Listing,
+    /// Multipart uploads, whose completions report the number of parts in
+    /// their ETag (`"mock-multipart-<n>"`)
+    Multipart,
+    /// Multipart uploads whose parts are corrupted in transit, a byte flipped
+    /// before they're checksummed
+    CorruptMultipart,
+    /// The responses given to [`script::respond_with`], in order
+    #[cfg(test)]
+    Scripted,
+}
+
+impl MockResponse {
+    fn for_bucket(bucket: &str) -> Self {
+        match bucket {
+            "mock-truncated-listing" => Self::TruncatedListing,
+            "mock-malformed-error" => Self::MalformedError,
+            "mock-conditional-get" => Self::ConditionalGet,
+            "mock-bulk-delete" => Self::BulkDelete,
+            "mock-conditional-put" => Self::ConditionalPut,
+            "mock-listing" => Self::Listing,
+            "mock-multipart" => Self::Multipart,
+            "mock-corrupt-multipart" => Self::CorruptMultipart,
+            #[cfg(test)]
+            "mock-scripted" => Self::Scripted,
+            #[cfg(test)]
+            _ if bucket.ends_with(":accesspoint/mock-scripted") => Self::Scripted,
+            _ => Self::Default,
+        }
+    }
+}
+
+impl MockedHttpClient {
+    fn respond(
+        mock: MockResponse,
+        parts: http::request::Parts,
+        body: SdkBody,
+        cache: RequestCache,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<http::Response<SdkBody>, JsValue>>>,
+    > {
+        #[cfg(test)]
+        script::record(&parts, &body);
+        #[cfg(test)]
+        if let MockResponse::Scripted = mock {
+            let response = script::next_response();
+            return Box::pin(async move { response });
+        }
+        if let Some(offset) = parts.headers.get("x-amz-write-offset-bytes") {
+            let offset = offset.to_str().ok().and_then(|offset| offset.parse().ok());
+            let appended = body.bytes().map_or(0, <[u8]>::len) as u64;
+            let response = Self::append(parts.uri.path(), offset, appended);
+            return Box::pin(async move { Ok(response) });
+        }
+        if let MockResponse::ConditionalGet = mock {
+            let response = Self::conditional_get(&parts);
+            return Box::pin(async move { Ok(response) });
+        }
+        if let MockResponse::BulkDelete = mock {
+            let body = body.bytes().unwrap_or_default();
+            if let Some(response) = Self::bulk_delete(&parts, body) {
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+        if let MockResponse::ConditionalPut = mock {
+            let body = body.bytes().unwrap_or_default();
+            if let Some(response) = Self::conditional_put(&parts, body) {
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+        if let MockResponse::Listing = mock {
+            if let Some(response) = Self::listing(&parts) {
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+        if let MockResponse::Multipart | MockResponse::CorruptMultipart = mock {
+            let mut received = body.bytes().unwrap_or_default().to_vec();
+            if let MockResponse::CorruptMultipart = mock {
+                match received.first_mut() {
+                    Some(byte) => *byte ^= 1,
+                    None => received.push(0),
+                }
+            }
+            if let Some(response) = Self::multipart(&parts, &received) {
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+        let (status, body) = match mock {
+            // directory buckets' sessions, valid for long enough for any test
+            _ if parts.uri.query() == Some("session") => (
+                200,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <CreateSessionResult><Credentials>\
+                 <SessionToken>mock-session-token</SessionToken>\
+                 <SecretAccessKey>mock-session-secret</SecretAccessKey>\
+                 <AccessKeyId>mock-session-key</AccessKeyId>\
+                 <Expiration>2100-01-01T00:00:00Z</Expiration>\
+                 </Credentials></CreateSessionResult>",
+            ),
+            MockResponse::Default
+            | MockResponse::ConditionalGet
+            | MockResponse::BulkDelete
+            | MockResponse::ConditionalPut
+            | MockResponse::Listing
+            | MockResponse::Multipart
+            | MockResponse::CorruptMultipart => return Self::send(parts, body, cache),
+            #[cfg(test)]
+            MockResponse::Scripted => unreachable!("answered above"),
+            MockResponse::TruncatedListing => (
+                200,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+                 <Name>mock-truncated-listing</Name><KeyCount>2</KeyCount>\
+                 <MaxKeys>1000</MaxKeys><IsTruncated>false</IsTruncated>\
+                 <Contents><Key>a.txt</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified>\
+                 <ETag>&quot;900150983cd24fb0d6963f7d28e17f72&quot;</ETag><Size>3</Size>\
+                 <StorageClass>STANDARD</StorageClass></Contents><Contents><Key>b.t",
+            ),
+            MockResponse::MalformedError => (
+                500,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <Error><Code>InternalError</Code><Message>We encountered an inter",
+            ),
+        };
+        Box::pin(async move {
+            Ok(http::Response::builder()
+                .status(status)
+                .body(SdkBody::from(body))
+                .unwrap())
+        })
+    }
+}
+
+/// The requests the mocked client receives, and the responses it gives to
+/// those of the `mock-scripted` bucket, for tests to inspect and script
+///
+/// Both are per thread, as each test is.
+#[cfg(test)]
+pub(crate) mod script {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    use aws_sdk_s3::primitives::SdkBody;
+    use bytes::Bytes;
+
+    thread_local! {
+        /// Responses to come, `None` for a rejected fetch
+        static RESPONSES: RefCell<VecDeque<Option<http::Response<Bytes>>>> = Default::default();
+        static REQUESTS: RefCell<Vec<http::Request<Bytes>>> = Default::default();
+    }
+
+    /// Answer the next requests to the `mock-scripted` bucket with `responses`
+    pub(crate) fn respond_with(responses: impl IntoIterator<Item = http::Response<Bytes>>) {
+        RESPONSES.with(|queue| queue.borrow_mut().extend(responses.into_iter().map(Some)));
+    }
+
+    /// Reject the next request after those already scripted, as browsers do a
+    /// fetch blocked by CORS
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn reject() {
+        RESPONSES.with(|queue| queue.borrow_mut().push_back(None));
+    }
+
+    /// A response of `status` with `headers` and `body`
+    pub(crate) fn response(
+        status: u16,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> http::Response<Bytes> {
+        let mut builder = http::Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder
+            .body(Bytes::copy_from_slice(body.as_bytes()))
+            .unwrap()
+    }
+
+    /// An S3 error response of `status` with the error `code`
+    pub(crate) fn error(status: u16, code: &str) -> http::Response<Bytes> {
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <Error><Code>{code}</Code><Message>{code}</Message></Error>"
+        );
+        response(status, &[], &body)
+    }
+
+    /// The requests received since the last call
+    pub(crate) fn take_requests() -> Vec<http::Request<Bytes>> {
+        REQUESTS.with(|requests| requests.take())
+    }
+
+    pub(super) fn record(parts: &http::request::Parts, body: &SdkBody) {
+        let mut request = http::Request::builder()
+            .method(parts.method.clone())
+            .uri(parts.uri.clone());
+        for (name, value) in &parts.headers {
+            request = request.header(name, value);
+        }
+        let body = Bytes::copy_from_slice(body.bytes().unwrap_or_default());
+        REQUESTS.with(|requests| requests.borrow_mut().push(request.body(body).unwrap()));
+    }
+
+    /// The next scripted response, a 500 once they run out
+    pub(super) fn next_response() -> Result<http::Response<SdkBody>, wasm_bindgen::JsValue> {
+        let response = RESPONSES
+            .with(|queue| queue.borrow_mut().pop_front())
+            .unwrap_or_else(|| Some(error(500, "UnscriptedRequest")));
+        match response {
+            Some(response) => Ok(response.map(SdkBody::from)),
+            None => Err(js_sys::TypeError::new("Failed to fetch").into()),
+        }
+    }
+}
+
+impl MockedHttpClient {
+    /// Answer a `GetObject` or `HeadObject` of `abcd` (ETag `"v2"`, last
+    /// modified at the start of 2024), checking its conditional headers as S3
+    /// would, and returning the requested range only if its `If-Range` matches
+    fn conditional_get(parts: &http::request::Parts) -> http::Response<SdkBody> {
+        const BODY: &str = "abcd";
+        const E_TAG: &str = "\"v2\"";
+        const LAST_MODIFIED: &str = "Mon, 01 Jan 2024 00:00:00 GMT";
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let date = |name: &str| header(name).and_then(crate::utils::parse_http_date);
+        let last_modified = crate::utils::parse_http_date(LAST_MODIFIED).unwrap();
+        // If-Match and If-None-Match take precedence over the dates
+        let failed = match header("if-match") {
+            Some(e_tag) => e_tag != E_TAG,
+            None => date("if-unmodified-since").map_or(false, |date| last_modified > date),
+        };
+        let not_modified = match header("if-none-match") {
+            Some(e_tag) => e_tag == E_TAG,
+            None => date("if-modified-since").map_or(false, |date| last_modified <= date),
+        };
+        let range = header("range")
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.split_once('-'))
+            .and_then(|(first, last)| Some(first.parse::<usize>().ok()?..last.parse().ok()?));
+        let builder = http::Response::builder()
+            .header("etag", E_TAG)
+            .header("last-modified", LAST_MODIFIED);
+        let (builder, body) = match (failed, not_modified, range, header("if-range")) {
+            (true, _, _, _) => (builder.status(412), ""),
+            (_, true, _, _) => (builder.status(304), ""),
+            (_, _, Some(range), Some(E_TAG)) => (
+                builder.status(206).header(
+                    "content-range",
+                    format!("bytes {}-{}/4", range.start, range.end),
+                ),
+                &BODY[range.start..range.end + 1],
+            ),
+            _ => (builder.status(200), BODY),
+        };
+        // responses to HEAD have the length of the body they'd have had
+        let builder = builder.header("content-length", body.len());
+        let body = match parts.method {
+            http::Method::HEAD => "",
+            _ => body,
+        };
+        builder.body(SdkBody::from(body)).unwrap()
+    }
+
+    /// Answer a quiet `DeleteObjects`, reporting the keys under `denied/` as
+    /// failed, `None` for any other request
+    fn bulk_delete(parts: &http::request::Parts, body: &[u8]) -> Option<http::Response<SdkBody>> {
+        if parts.method != http::Method::POST || !Self::query(parts).contains_key("delete") {
+            return None;
+        }
+        let body = std::str::from_utf8(body).ok()?;
+        let errors = body
+            .split("<Key>")
+            .skip(1)
+            .filter_map(|rest| Some(rest.split_once("</Key>")?.0))
+            .filter(|key| key.starts_with("denied/"))
+            .map(|key| {
+                format!(
+                    "<Error><Key>{key}</Key><Code>AccessDenied</Code>\
+                     <Message>Access Denied</Message></Error>"
+                )
+            })
+            .collect::<String>();
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <DeleteResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">{errors}</DeleteResult>"
+        );
+        Some(
+            http::Response::builder()
+                .status(200)
+                .body(SdkBody::from(body))
+                .unwrap(),
+        )
+    }
+
+    /// Answer a `PutObject`, tracking the ETags of the objects put so that
+    /// `If-None-Match: *` and `If-Match` are checked against them, `None` for
+    /// any other request
+    fn conditional_put(
+        parts: &http::request::Parts,
+        body: &[u8],
+    ) -> Option<http::Response<SdkBody>> {
+        use md5::{Digest, Md5};
+
+        thread_local! {
+            static E_TAGS: std::cell::RefCell<std::collections::HashMap<String, String>> =
+                Default::default();
+        }
+        let query = Self::query(parts);
+        if parts.method != http::Method::PUT || query.get("x-id")? != "PutObject" {
+            return None;
+        }
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let path = parts.uri.path().to_string();
+        let current = E_TAGS.with(|e_tags| e_tags.borrow().get(&path).cloned());
+        let satisfied = match (header("if-none-match"), header("if-match")) {
+            (Some("*"), _) => current.is_none(),
+            (_, Some(e_tag)) => current.as_deref() == Some(e_tag),
+            _ => true,
+        };
+        let response = match satisfied {
+            true => {
+                let md5 = Md5::digest(body)
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                let e_tag = format!("\"{md5}\"");
+                E_TAGS.with(|e_tags| e_tags.borrow_mut().insert(path, e_tag.clone()));
+                http::Response::builder()
+                    .status(200)
+                    .header("etag", e_tag)
+                    .body(SdkBody::empty())
+            }
+            false => http::Response::builder().status(412).body(SdkBody::from(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <Error><Code>PreconditionFailed</Code>\
+                 <Message>At least one of the pre-conditions you specified did not hold</Message>\
+                 </Error>",
+            )),
+        };
+        Some(response.unwrap())
+    }
+
+    /// The keys of the `mock-listing` bucket
+    const LISTED_KEYS: [&'static str; 5] = ["a/b", "a/c", "d", "e/f/g", "h"];
+
+    /// The decoded query parameters of a request
+    fn query(parts: &http::request::Parts) -> std::collections::HashMap<String, String> {
+        let query = parts.uri.query().unwrap_or_default();
+        url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect()
+    }
+
+    /// Answer a `ListObjectsV2` of [`Self::LISTED_KEYS`] two entries to a page,
+    /// the continuation token being the index of the page's first entry, `None`
+    /// for any other request
+    ///
+    /// With a delimiter, keys are rolled up into common prefixes as S3 would,
+    /// each counting as a single entry.
+    fn listing(parts: &http::request::Parts) -> Option<http::Response<SdkBody>> {
+        const PAGE_SIZE: usize = 2;
+        let query = Self::query(parts);
+        if parts.method != http::Method::GET || !query.contains_key("list-type") {
+            return None;
+        }
+        let prefix = query.get("prefix").map_or("", String::as_str);
+        let start = match query.get("continuation-token") {
+            Some(token) => token.parse().ok()?,
+            None => 0,
+        };
+        let mut entries = Vec::<String>::new();
+        for key in Self::LISTED_KEYS
+            .iter()
+            .filter(|key| key.starts_with(prefix))
+        {
+            let entry = match query.get("delimiter") {
+                Some(delimiter) => match key[prefix.len()..].find(delimiter.as_str()) {
+                    Some(end) => &key[..prefix.len() + end + delimiter.len()],
+                    None => key,
+                },
+                None => key,
+            };
+            if entries.last().map(String::as_str) != Some(entry) {
+                entries.push(entry.to_string());
+            }
+        }
+        let page = entries
+            .iter()
+            .skip(start)
+            .take(PAGE_SIZE)
+            .collect::<Vec<_>>();
+        let next = start + PAGE_SIZE;
+        let is_truncated = next < entries.len();
+        let mut body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+             <Name>mock-listing</Name><Prefix>{prefix}</Prefix><KeyCount>{}</KeyCount>\
+             <MaxKeys>{PAGE_SIZE}</MaxKeys><IsTruncated>{is_truncated}</IsTruncated>",
+            page.len(),
+        );
+        if is_truncated {
+            body.push_str(&format!(
+                "<NextContinuationToken>{next}</NextContinuationToken>"
+            ));
+        }
+        for entry in page {
+            // keys never end with the delimiter, only common prefixes do
+            let entry = match query.get("delimiter") {
+                Some(delimiter) if entry.ends_with(delimiter.as_str()) => {
+                    format!("<CommonPrefixes><Prefix>{entry}</Prefix></CommonPrefixes>")
+                }
+                _ => format!(
+                    "<Contents><Key>{entry}</Key>\
+                     <LastModified>2024-01-01T00:00:00.000Z</LastModified>\
+                     <ETag>&quot;{entry}&quot;</ETag><Size>{}</Size></Contents>",
+                    entry.len()
+                ),
+            };
+            body.push_str(&entry);
+        }
+        body.push_str("</ListBucketResult>");
+        Some(
+            http::Response::builder()
+                .status(200)
+                .body(SdkBody::from(body))
+                .unwrap(),
+        )
+    }
+
+    /// Answer the requests of a multipart upload, tracking the SHA256 checksums
+    /// of the parts received, `None` for any other request
+    ///
+    /// Parts are answered with their checksum where one was sent, and
+    /// completions with the composite checksum of the parts received.
+    fn multipart(parts: &http::request::Parts, body: &[u8]) -> Option<http::Response<SdkBody>> {
+        use aws_smithy_types::base64;
+        use sha2::{Digest, Sha256};
+
+        /// The part numbers and checksums received, by upload ID
+        type Uploads = std::collections::HashMap<String, Vec<(i32, Vec<u8>)>>;
+        thread_local! {
+            static UPLOADS: std::cell::RefCell<Uploads> = Default::default();
+        }
+        let query = Self::query(parts);
+        let builder = http::Response::builder().status(200);
+        let response = match (&parts.method, query.get("uploadId")) {
+            (&http::Method::POST, None) if query.contains_key("uploads") => {
+                let upload_id = UPLOADS.with(|uploads| {
+                    let mut uploads = uploads.borrow_mut();
+                    let upload_id = format!("mock-upload-{}", uploads.len());
+                    uploads.insert(upload_id.clone(), Vec::new());
+                    upload_id
+                });
+                builder.body(SdkBody::from(format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                     <InitiateMultipartUploadResult><UploadId>{upload_id}</UploadId>\
+                     </InitiateMultipartUploadResult>"
+                )))
+            }
+            (&http::Method::PUT, Some(upload_id)) => {
+                let part_number: i32 = query.get("partNumber")?.parse().ok()?;
+                let checksum = Sha256::digest(body).to_vec();
+                UPLOADS.with(|uploads| {
+                    let mut uploads = uploads.borrow_mut();
+                    let parts = uploads.get_mut(upload_id)?;
+                    parts.retain(|(n, _)| *n != part_number);
+                    parts.push((part_number, checksum.clone()));
+                    Some(())
+                })?;
+                let mut builder = builder.header("etag", format!("\"part-{part_number}\""));
+                if parts.headers.contains_key("x-amz-checksum-sha256") {
+                    builder = builder.header("x-amz-checksum-sha256", base64::encode(checksum));
+                }
+                builder.body(SdkBody::empty())
+            }
+            (&http::Method::POST, Some(upload_id)) => {
+                let mut parts = UPLOADS.with(|uploads| uploads.borrow_mut().remove(upload_id))?;
+                parts.sort_by_key(|(part_number, _)| *part_number);
+                let composite = parts
+                    .iter()
+                    .fold(Sha256::new(), |hasher, (_, checksum)| {
+                        hasher.chain_update(checksum)
+                    })
+                    .finalize();
+                builder.body(SdkBody::from(format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                     <CompleteMultipartUploadResult><ETag>&quot;mock-multipart-{}&quot;</ETag>\
+                     <ChecksumSHA256>{}-{}</ChecksumSHA256></CompleteMultipartUploadResult>",
+                    parts.len(),
+                    base64::encode(composite),
+                    parts.len(),
+                )))
+            }
+            (&http::Method::DELETE, Some(upload_id)) => {
+                UPLOADS.with(|uploads| uploads.borrow_mut().remove(upload_id));
+                builder.status(204).body(SdkBody::empty())
+            }
+            _ => return None,
+        };
+        Some(response.unwrap())
+    }
+
+    /// Append `appended` bytes at `offset` to the object at `path`, tracking
+    /// the sizes of objects appended to, so that stale offsets are rejected
+    /// as S3 would
+    fn append(path: &str, offset: Option<u64>, appended: u64) -> http::Response<SdkBody> {
+        thread_local! {
+            static SIZES: std::cell::RefCell<std::collections::HashMap<String, u64>> =
+                Default::default();
+        }
+        let accepted = SIZES.with(|sizes| {
+            let mut sizes = sizes.borrow_mut();
+            let size = sizes.entry(path.to_string()).or_default();
+            match offset == Some(*size) {
+                true => {
+                    *size += appended;
+                    true
+                }
+                false => false,
+            }
+        });
+        let (status, body) = match accepted {
+            true => (200, ""),
+            false => (
+                400,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <Error><Code>InvalidWriteOffset</Code>\
+                 <Message>The write offset is not the size of the object</Message></Error>",
+            ),
+        };
+        http::Response::builder()
+            .status(status)
+            .body(SdkBody::from(body))
+            .unwrap()
+    }
+}
+
 #[derive(Debug, Clone)]
-struct Adapter {
-    use_mock: bool,
+pub(crate) struct Adapter {
+    mock: Option<MockResponse>,
+    observer: Option<RequestObserver>,
+    fetch_cache_mode: RequestCache,
 }
 
 impl Adapter {
-    fn new(use_mock: bool) -> Self {
-        Self { use_mock }
+    pub(crate) fn new(
+        mock: Option<MockResponse>,
+        observer: Option<RequestObserver>,
+        fetch_cache_mode: RequestCache,
+    ) -> Self {
+        Self {
+            mock,
+            observer,
+            fetch_cache_mode,
+        }
+    }
+}
+
+/// Strips the signature (and any session token) from signed requests, making
+/// them anonymous
+///
+/// This SDK version always signs requests, so unsigned requests are signed with
+/// placeholder credentials, then unsigned just before they're sent.
+#[derive(Debug)]
+struct Unsigned;
+
+impl Interceptor for Unsigned {
+    fn name(&self) -> &'static str {
+        "Unsigned"
+    }
+
+    fn modify_before_transmit(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let headers = context.request_mut().headers_mut();
+        headers.remove(http::header::AUTHORIZATION);
+        headers.remove("x-amz-security-token");
+        Ok(())
+    }
+}
+
+/// Signs requests with `UNSIGNED-PAYLOAD` in place of the SHA256 of the body
+///
+/// The signer hashes bodies held in memory, which every body sent by this crate
+/// is, so the payload is overridden in the operation's signing config.
+#[derive(Debug)]
+struct UnsignedPayload;
+
+impl Interceptor for UnsignedPayload {
+    fn name(&self) -> &'static str {
+        "UnsignedPayload"
+    }
+
+    fn modify_before_signing(
+        &self,
+        _context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(signing_config) = cfg.load::<SigV4OperationSigningConfig>() {
+            let mut signing_config = signing_config.clone();
+            signing_config.signing_options.payload_override = Some(SignableBody::UnsignedPayload);
+            cfg.interceptor_state().store_put(signing_config);
+        }
+        Ok(())
+    }
+}
+
+/// Name the S3 operation a raw request corresponds to, for telemetry
+fn request_operation(parts: &http::request::Parts) -> &'static str {
+    let query = parts.uri.query().unwrap_or_default();
+    match parts.method {
+        http::Method::GET if query.contains("list-type=2") => "list",
+        http::Method::GET => "get",
+        http::Method::HEAD => "head",
+        http::Method::PUT if parts.headers.contains_key("x-amz-copy-source") => "copy",
+        http::Method::PUT => "put",
+        http::Method::DELETE => "delete",
+        http::Method::POST => "post",
+        _ => "other",
+    }
+}
+
+/// The fetch `cache` mode of a raw request: `configured` for object reads
+/// (`GetObject`, tagged `x-id=GetObject`, and `HeadObject`), the browser's
+/// default for listings, sessions and writes
+fn request_cache_mode(parts: &http::request::Parts, configured: RequestCache) -> RequestCache {
+    let query = parts.uri.query().unwrap_or_default();
+    let object_read = match parts.method {
+        http::Method::HEAD => true,
+        http::Method::GET => query.split('&').any(|pair| pair == "x-id=GetObject"),
+        _ => false,
+    };
+    match object_read {
+        true => configured,
+        false => RequestCache::Default,
     }
 }
 
@@ -361,25 +1719,275 @@ impl tower::Service<http::Request<SdkBody>> for Adapter {
 
     fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
         let (parts, body) = req.into_parts();
-        let uri = parts.uri.to_string();
+        let operation = request_operation(&parts);
+        let request_bytes = body.bytes().map(<[u8]>::len).unwrap_or(0);
 
         let (tx, rx) = tokio::sync::oneshot::channel();
-        let use_mock = self.use_mock;
-        wasm_bindgen_futures::spawn_local(async move {
-            let fut = if use_mock {
-                MockedHttpClient::send(parts, body)
-            } else {
-                BrowserHttpClient::send(parts, body)
+        let mock = self.mock;
+        let observer = self.observer.clone();
+        let cache = request_cache_mode(&parts, self.fetch_cache_mode);
+        let request = async move {
+            let started = Utc::now();
+            let failure = FetchError::new(&parts);
+            let fut = match mock {
+                Some(mock) => MockedHttpClient::respond(mock, parts, body, cache),
+                None => BrowserHttpClient::send(parts, body, cache),
             };
-            let _ = tx.send(
-                fut.await
-                    .unwrap_or_else(|_| panic!("failure while making request to: {}", uri)),
+            // NB: browsers deliberately don't distinguish CORS rejections from
+            // network failures, both surface as an opaque TypeError
+            let response = fut.await.map_err(|err| failure.with_cause(err));
+            let response_bytes = match &response {
+                Ok(response) => response.body().bytes().map(<[u8]>::len).unwrap_or(0),
+                Err(_) => 0,
+            };
+            observe(
+                observer.as_ref(),
+                operation,
+                request_bytes + response_bytes,
+                started,
             );
-        });
+            let _ = tx.send(response);
+        };
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(request);
+        // outside the browser only the mocked client (for native tests) can
+        // answer, which it does without awaiting anything
+        #[cfg(not(target_arch = "wasm32"))]
+        futures::executor::block_on(request);
 
         Box::pin(async move {
             let response = rx.await.map_err(|e| ConnectorError::user(Box::new(e)))?;
-            Ok(response)
+            response.map_err(|e| ConnectorError::io(Box::new(e)))
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_error() {
+        let (parts, _) = http::Request::builder()
+            .method("PUT")
+            .uri("https://bucket.s3.us-east-1.amazonaws.com/a.txt")
+            .header("x-amz-content-sha256", "UNSIGNED-PAYLOAD")
+            .header("x-amz-date", "20240101T000000Z")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let message = FetchError::new(&parts).to_string();
+        assert!(message
+            .starts_with("PUT request to https://bucket.s3.us-east-1.amazonaws.com/a.txt failed"));
+        // naming what the bucket's CORS configuration must allow
+        assert!(message.contains("CORS configuration"), "{message}");
+        assert!(
+            message.contains("the PUT method and the headers [x-amz-content-sha256, x-amz-date]"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_validate_region() {
+        for (region, expected) in [
+            ("us-east-1", "us-east-1"),
+            ("us-gov-west-1", "us-gov-west-1"),
+            ("cn-northwest-1", "cn-northwest-1"),
+            ("ap-southeast-2", "ap-southeast-2"),
+            (" EU-West-2 ", "eu-west-2"),
+        ] {
+            assert_eq!(
+                validate_region(region, false).unwrap(),
+                expected,
+                "{region}"
+            );
+        }
+        for region in [
+            "",
+            "auto",
+            "us",
+            "us-east",
+            "useast-1",
+            "us-east-a",
+            "us--1",
+            "u-east-1",
+            "us_east_1",
+            "us-east-1 x",
+        ] {
+            assert!(validate_region(region, false).is_err(), "{region}");
+        }
+
+        for (region, expected) in [
+            ("auto", "auto"),
+            ("AUTO", "auto"),
+            ("nyc3", "nyc3"),
+            ("us-east-1", "us-east-1"),
+            ("my_region", "my_region"),
+        ] {
+            assert_eq!(validate_region(region, true).unwrap(), expected, "{region}");
+        }
+        for region in ["", " ", "us east", "us/east", "région"] {
+            assert!(validate_region(region, true).is_err(), "{region}");
+        }
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let parse = |url: &str| {
+            let mut builder = AmazonS3Builder::new();
+            builder.parse_url(url).unwrap();
+            (builder.bucket.unwrap(), builder.region)
+        };
+        let expected = |bucket: &str| (bucket.to_string(), Some("eu-west-1".to_string()));
+
+        assert_eq!(
+            parse("https://s3.eu-west-1.amazonaws.com/data/data/nested"),
+            expected("data")
+        );
+        assert_eq!(
+            parse("https://data.s3.eu-west-1.amazonaws.com/data/nested"),
+            expected("data")
+        );
+        // bucket names beginning with "s3" aren't mistaken for path-style hosts
+        assert_eq!(
+            parse("https://s3data.s3.eu-west-1.amazonaws.com/nested"),
+            expected("s3data")
+        );
+        assert_eq!(parse("s3://s3data/nested"), ("s3data".to_string(), None));
+        for url in [
+            "https://s3.dualstack.eu-west-1.amazonaws.com/data/nested",
+            "https://data.s3.dualstack.eu-west-1.amazonaws.com/nested",
+        ] {
+            let mut builder = AmazonS3Builder::new();
+            builder.parse_url(url).unwrap();
+            assert_eq!(builder.bucket.as_deref(), Some("data"), "{url}");
+            assert_eq!(builder.region.as_deref(), Some("eu-west-1"), "{url}");
+            assert!(builder.use_dualstack_endpoint, "{url}");
+        }
+        assert_eq!(
+            parse("https://reports-123456789012.s3-accesspoint.eu-west-1.amazonaws.com/a.csv"),
+            expected("arn:aws:s3:eu-west-1:123456789012:accesspoint/reports")
+        );
+
+        let mut builder = AmazonS3Builder::new();
+        assert!(builder.parse_url("https://example.com/data").is_err());
+        assert!(builder.parse_url("gs://data/nested").is_err());
+    }
+
+    #[test]
+    fn test_request_cache_mode() {
+        let parts = |method: http::Method, uri: &str| {
+            let request = http::Request::builder().method(method).uri(uri);
+            request.body(()).unwrap().into_parts().0
+        };
+        let configured = RequestCache::Reload;
+        for (method, uri, expected) in [
+            (
+                http::Method::GET,
+                "https://b.s3.amazonaws.com/a.txt?x-id=GetObject",
+                configured,
+            ),
+            (
+                http::Method::GET,
+                "https://b.s3.amazonaws.com/a.txt?versionId=1&x-id=GetObject",
+                configured,
+            ),
+            (
+                http::Method::HEAD,
+                "https://b.s3.amazonaws.com/a.txt",
+                configured,
+            ),
+            (
+                http::Method::GET,
+                "https://b.s3.amazonaws.com/?list-type=2&prefix=a",
+                RequestCache::Default,
+            ),
+            (
+                http::Method::GET,
+                "https://b.s3express-usw2-az1.us-west-2.amazonaws.com/?session",
+                RequestCache::Default,
+            ),
+            (
+                http::Method::PUT,
+                "https://b.s3.amazonaws.com/a.txt?x-id=PutObject",
+                RequestCache::Default,
+            ),
+        ] {
+            assert_eq!(
+                request_cache_mode(&parts(method, uri), configured),
+                expected,
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_s3_express() {
+        let builder = || {
+            AmazonS3Builder::new()
+                .access_key_id("key")
+                .secret_access_key("secret")
+        };
+
+        let store = builder()
+            .bucket("logs--usw2-az1--x-s3")
+            .region("us-west-2")
+            .build()
+            .unwrap();
+        assert!(store.express.is_some());
+        let store = builder()
+            .with_url("https://logs--usw2-az1--x-s3.s3express-usw2-az1.us-west-2.amazonaws.com/a")
+            .build()
+            .unwrap();
+        assert!(store.express.is_some());
+        let store = builder()
+            .bucket("logs")
+            .region("us-west-2")
+            .build()
+            .unwrap();
+        assert!(store.express.is_none());
+
+        // directory buckets are zonal, so need a region
+        assert!(builder().bucket("logs--usw2-az1--x-s3").build().is_err());
+        // and S3 Express needs the zone of a directory bucket name
+        assert!(builder()
+            .bucket("logs")
+            .region("us-west-2")
+            .with_config("s3_express".parse().unwrap(), "true")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let builder = || {
+            AmazonS3Builder::new()
+                .access_key_id("key")
+                .secret_access_key("secret")
+                .region("us-west-2")
+                .bucket("bucket")
+        };
+
+        let store = builder()
+            .with_timeout(Duration::from_secs(30))
+            .with_connect_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let timeouts = store.client.config().timeout_config().unwrap();
+        assert_eq!(timeouts.connect_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(
+            timeouts.operation_attempt_timeout(),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(timeouts.operation_timeout(), None);
+
+        // each is independent of the other
+        let store = builder()
+            .with_connect_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let timeouts = store.client.config().timeout_config().unwrap();
+        assert_eq!(timeouts.connect_timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.operation_attempt_timeout(), None);
+    }
+}
@@ -1,13 +1,16 @@
 use std::convert::Infallible;
 
 use aws_sdk_s3::{
-    error::SdkError,
+    error::{ProvideErrorMetadata, SdkError},
     operation::{
         abort_multipart_upload::AbortMultipartUploadError,
         complete_multipart_upload::CompleteMultipartUploadError, copy_object::CopyObjectError,
         create_multipart_upload::CreateMultipartUploadError, delete_object::DeleteObjectError,
-        get_object::GetObjectError, head_object::HeadObjectError,
+        delete_object_tagging::DeleteObjectTaggingError, delete_objects::DeleteObjectsError,
+        get_object::GetObjectError, get_object_tagging::GetObjectTaggingError,
+        head_bucket::HeadBucketError, head_object::HeadObjectError,
         list_objects_v2::ListObjectsV2Error, put_object::PutObjectError,
+        put_object_tagging::PutObjectTaggingError, restore_object::RestoreObjectError,
         upload_part::UploadPartError,
     },
     primitives::SdkBody,
@@ -20,6 +23,8 @@ pub enum Error {
     S3Infallible(#[from] SdkError<Infallible, http::response::Response<SdkBody>>),
     #[error("S3 head object error")]
     S3Head(#[from] SdkError<HeadObjectError, http::response::Response<SdkBody>>),
+    #[error("S3 head bucket error")]
+    S3HeadBucket(#[from] SdkError<HeadBucketError, http::response::Response<SdkBody>>),
     #[error("S3 uploadpart object error")]
     S3UploadPart(#[from] SdkError<UploadPartError, http::response::Response<SdkBody>>),
     #[error("S3 create multipart error")]
@@ -42,21 +47,246 @@ pub enum Error {
     S3CopyObject(#[from] SdkError<CopyObjectError, http::response::Response<SdkBody>>),
     #[error("S3 delete object error")]
     S3DeleteObject(#[from] SdkError<DeleteObjectError, http::response::Response<SdkBody>>),
+    #[error("S3 delete objects error")]
+    S3DeleteObjects(#[from] SdkError<DeleteObjectsError, http::response::Response<SdkBody>>),
     #[error("S3 list objects error")]
     S3ListObjects(#[from] SdkError<ListObjectsV2Error, http::response::Response<SdkBody>>),
+    #[error("S3 restore object error")]
+    S3RestoreObject(#[from] SdkError<RestoreObjectError, http::response::Response<SdkBody>>),
+    #[error("S3 put object tagging error")]
+    S3PutObjectTagging(#[from] SdkError<PutObjectTaggingError, http::response::Response<SdkBody>>),
+    #[error("S3 get object tagging error")]
+    S3GetObjectTagging(#[from] SdkError<GetObjectTaggingError, http::response::Response<SdkBody>>),
+    #[error("S3 delete object tagging error")]
+    S3DeleteObjectTagging(
+        #[from] SdkError<DeleteObjectTaggingError, http::response::Response<SdkBody>>,
+    ),
     #[error("S3 conversion error")]
     S3Conversion(#[from] aws_smithy_types::date_time::ConversionError),
     #[error("Parse int error")]
     ParseInt(#[from] std::num::ParseIntError),
+    #[error("S3 credentials have expired ({code}), provide fresh credentials or configure a refreshable credentials provider")]
+    CredentialsExpired { code: String, source: Box<Error> },
+    #[error("access denied, check the credentials' permissions and, if an expected bucket owner is configured, that the bucket belongs to that account")]
+    AccessDenied { source: Box<Error> },
+    #[error("bucket {bucket} does not exist")]
+    NoSuchBucket { bucket: String },
+    #[error("S3 is unreachable, check the endpoint and network connectivity")]
+    Unreachable { source: Box<Error> },
+    #[error("the bucket has ACLs disabled, remove the configured object ACL or enable ACLs on the bucket")]
+    AclNotSupported { source: Box<Error> },
+    #[error("object is archived, restore it with AmazonS3::restore_object before reading it")]
+    ObjectNotRestored { source: Box<Error> },
+    #[error("S3 returned ETag {actual} for the uploaded object, expected {expected} (the MD5 of the payload)")]
+    ETagMismatch { expected: String, actual: String },
+    #[error("S3 returned SHA256 checksum {actual} for {what}, expected {expected}")]
+    ChecksumMismatch {
+        what: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("payload of {size} bytes exceeds the S3 single-part upload limit, use put_multipart instead")]
+    EntityTooLarge { size: u64 },
+    #[error("copied {from} to {to}, but failed to delete the source, which is left intact")]
+    RenameSourceNotDeleted {
+        from: String,
+        to: String,
+        source: object_store::Error,
+    },
+    #[error("part {part_number} of {part_count} was not uploaded, await every part before completing the upload")]
+    MissingPart { part_number: i32, part_count: i32 },
+    #[error("S3 response is missing or has an invalid {field}")]
+    MissingField { field: &'static str },
+    #[error("{key} is stored with Content-Encoding {encoding}, so ranges of it are of the encoded bytes, which can't be decoded on their own. Fetch the whole object instead")]
+    EncodedRange { key: String, encoding: String },
+    #[error("invalid Content-Range header: {content_range}")]
+    InvalidContentRange { content_range: String },
+    #[error("conditional updates require the ETag of the object being replaced")]
+    MissingETag,
+    #[error("invalid ETag: {e_tag}")]
+    InvalidETag { e_tag: String },
+    #[error("failed to delete {key}: {code} {message}")]
+    DeleteFailed {
+        key: String,
+        code: String,
+        message: String,
+    },
+    #[error("failed to create an S3 Express session: {message}")]
+    CreateSession { message: String },
+    #[error("S3 request did not complete within its {deadline:?} deadline")]
+    DeadlineExceeded { deadline: std::time::Duration },
+    #[error("dry run, would have {action}")]
+    DryRun { action: String },
     #[error("unknown object store error")]
     Unknown,
 }
 
+/// S3 error codes indicating the request was signed with expired credentials
+const EXPIRED_CREDENTIALS_CODES: [&str; 3] =
+    ["ExpiredToken", "InvalidToken", "TokenRefreshRequired"];
+
+pub(crate) fn is_expired_credentials(err: &impl ProvideErrorMetadata) -> bool {
+    matches!(err.code(), Some(code) if EXPIRED_CREDENTIALS_CODES.contains(&code))
+}
+
+impl Error {
+    /// The S3 error code of the underlying service error, if any
+    pub(crate) fn code(&self) -> Option<&str> {
+        match self {
+            Self::S3Head(err) => err.code(),
+            Self::S3HeadBucket(err) => err.code(),
+            Self::S3UploadPart(err) => err.code(),
+            Self::S3CreateMultipart(err) => err.code(),
+            Self::S3CompleteMultipart(err) => err.code(),
+            Self::S3AbortMultipart(err) => err.code(),
+            Self::S3GetObject(err) => err.code(),
+            Self::S3PutObject(err) => err.code(),
+            Self::S3CopyObject(err) => err.code(),
+            Self::S3DeleteObject(err) => err.code(),
+            Self::S3DeleteObjects(err) => err.code(),
+            Self::S3ListObjects(err) => err.code(),
+            Self::S3RestoreObject(err) => err.code(),
+            Self::S3PutObjectTagging(err) => err.code(),
+            Self::S3GetObjectTagging(err) => err.code(),
+            Self::S3DeleteObjectTagging(err) => err.code(),
+            _ => None,
+        }
+    }
+
+    /// Whether S3 reported the object as missing: `NoSuchKey` from `GetObject`
+    /// and the other object operations, or a 404 (`HeadObject` responses have no
+    /// body to carry a code)
+    fn is_not_found(&self) -> bool {
+        match self {
+            Self::S3GetObject(SdkError::ServiceError(err)) => err.err().is_no_such_key(),
+            Self::S3Head(SdkError::ServiceError(err)) => err.err().is_not_found(),
+            _ => self.code() == Some("NoSuchKey"),
+        }
+    }
+
+    /// The status of an error response to a `GetObject` or `HeadObject`
+    fn object_status(&self) -> Option<u16> {
+        match self {
+            Self::S3GetObject(SdkError::ServiceError(err)) => Some(err.raw().status().as_u16()),
+            Self::S3Head(SdkError::ServiceError(err)) => Some(err.raw().status().as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Convert into an [`object_store::Error`] for a request to `path`, which
+    /// is [`NotFound`](object_store::Error::NotFound) if the object is missing,
+    /// [`NotModified`](object_store::Error::NotModified) if it's unchanged, or
+    /// [`Precondition`](object_store::Error::Precondition) if it failed an
+    /// `If-Match`/`If-Unmodified-Since` condition
+    pub(crate) fn with_path(self, path: &object_store::path::Path) -> object_store::Error {
+        let path = path.to_string();
+        if self.is_not_found() {
+            return object_store::Error::NotFound {
+                path,
+                source: Box::new(self),
+            };
+        }
+        match self.object_status() {
+            Some(304) => object_store::Error::NotModified {
+                path,
+                source: Box::new(self),
+            },
+            Some(412) => object_store::Error::Precondition {
+                path,
+                source: Box::new(self),
+            },
+            _ => self.into(),
+        }
+    }
+}
+
 impl From<Error> for object_store::Error {
     fn from(value: Error) -> Self {
+        let value = match value.code() {
+            Some(code) if EXPIRED_CREDENTIALS_CODES.contains(&code) => Error::CredentialsExpired {
+                code: code.to_string(),
+                source: Box::new(value),
+            },
+            Some("InvalidObjectState") => Error::ObjectNotRestored {
+                source: Box::new(value),
+            },
+            Some("AccessControlListNotSupported") => Error::AclNotSupported {
+                source: Box::new(value),
+            },
+            Some("AccessDenied") => Error::AccessDenied {
+                source: Box::new(value),
+            },
+            _ => value,
+        };
         object_store::Error::Generic {
             store: "S3",
             source: Box::new(value),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::types::error::{NoSuchKey, NotFound};
+    use aws_smithy_types::error::ErrorMetadata;
+
+    fn raw(status: u16) -> http::Response<SdkBody> {
+        http::Response::builder()
+            .status(status)
+            .body(SdkBody::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_with_path_not_found() {
+        let path = object_store::path::Path::from("a.txt");
+        let not_found = |err: Error| {
+            matches!(
+                err.with_path(&path),
+                object_store::Error::NotFound { path, .. } if path == "a.txt"
+            )
+        };
+
+        let missing = GetObjectError::NoSuchKey(NoSuchKey::builder().build());
+        assert!(not_found(SdkError::service_error(missing, raw(404)).into()));
+        let missing = HeadObjectError::NotFound(NotFound::builder().build());
+        assert!(not_found(SdkError::service_error(missing, raw(404)).into()));
+
+        let missing =
+            GetObjectTaggingError::generic(ErrorMetadata::builder().code("NoSuchKey").build());
+        assert!(not_found(SdkError::service_error(missing, raw(404)).into()));
+
+        let denied = GetObjectError::generic(ErrorMetadata::builder().code("AccessDenied").build());
+        let err = Error::from(SdkError::service_error(denied, raw(403))).with_path(&path);
+        assert!(matches!(err, object_store::Error::Generic { .. }), "{err}");
+        let timeout = SdkError::<HeadObjectError, _>::timeout_error("timed out");
+        assert!(!not_found(timeout.into()));
+    }
+
+    #[test]
+    fn test_with_path_conditions() {
+        let path = object_store::path::Path::from("a.txt");
+        let unhandled = |status| {
+            let err = HeadObjectError::generic(ErrorMetadata::builder().build());
+            Error::from(SdkError::service_error(err, raw(status))).with_path(&path)
+        };
+        assert!(matches!(
+            unhandled(304),
+            object_store::Error::NotModified { .. }
+        ));
+        assert!(matches!(
+            unhandled(412),
+            object_store::Error::Precondition { .. }
+        ));
+        assert!(matches!(
+            unhandled(500),
+            object_store::Error::Generic { .. }
+        ));
+
+        let err =
+            GetObjectError::generic(ErrorMetadata::builder().code("PreconditionFailed").build());
+        let err = Error::from(SdkError::service_error(err, raw(412))).with_path(&path);
+        assert!(matches!(err, object_store::Error::Precondition { path, .. } if path == "a.txt"));
+    }
+}
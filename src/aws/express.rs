@@ -0,0 +1,473 @@
+//! S3 Express One Zone session authentication
+//!
+//! Directory buckets authorize requests with short-lived session credentials,
+//! issued by `CreateSession`, rather than with the caller's own credentials.
+//! This SDK version predates S3 Express, so sessions are created here, and each
+//! request re-signed with the current session just before it's sent.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_sdk_s3::primitives::SdkBody;
+use aws_sigv4::http_request::{
+    sign, PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest, SigningParams,
+    SigningSettings, UriPathNormalizationMode,
+};
+use aws_smithy_async::time::SharedTimeSource;
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::{context::BeforeTransmitInterceptorContextMut, Interceptor},
+        runtime_components::RuntimeComponents,
+    },
+};
+use aws_smithy_types::config_bag::ConfigBag;
+use http::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use std::sync::Arc;
+use tower::Service;
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+use crate::aws::{builder::Adapter, error::Error};
+
+/// The header carrying the session token, in place of `x-amz-security-token`
+const SESSION_TOKEN: HeaderName = HeaderName::from_static("x-amz-s3session-token");
+const SECURITY_TOKEN: HeaderName = HeaderName::from_static("x-amz-security-token");
+const DATE: HeaderName = HeaderName::from_static("x-amz-date");
+const CONTENT_SHA256: HeaderName = HeaderName::from_static("x-amz-content-sha256");
+/// The service name requests to directory buckets are signed for
+const SIGNING_NAME: &str = "s3express";
+/// Sessions are replaced this long before they expire, S3 issuing them for
+/// five minutes
+const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(60);
+
+/// The availability zone id of a directory bucket name
+/// (`<name>--<availability zone id>--x-s3`), e.g. `usw2-az1`
+pub(crate) fn zone_id(bucket: &str) -> Option<&str> {
+    let (_, zone_id) = bucket.strip_suffix("--x-s3")?.rsplit_once("--")?;
+    Some(zone_id).filter(|zone_id| !zone_id.is_empty())
+}
+
+/// The (virtual-hosted) zonal endpoint of directory bucket `bucket`
+pub(crate) fn zonal_endpoint(bucket: &str, region: &str) -> Option<String> {
+    let zone_id = zone_id(bucket)?;
+    Some(format!(
+        "https://{bucket}.s3express-{zone_id}.{region}.amazonaws.com"
+    ))
+}
+
+/// The credentials of a session, as issued by `CreateSession`
+#[derive(Clone, PartialEq)]
+pub(crate) struct Session {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expiration: SystemTime,
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("access_key_id", &self.access_key_id)
+            .field("expiration", &self.expiration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Session {
+    fn is_fresh(&self, now: SystemTime) -> bool {
+        self.expiration > now + REFRESH_BEFORE_EXPIRY
+    }
+}
+
+/// Parse the credentials of a `CreateSessionResult` `body`
+fn parse_create_session(body: &str) -> object_store::Result<Session> {
+    let invalid = |message: &str| -> object_store::Error {
+        Error::CreateSession {
+            message: message.to_string(),
+        }
+        .into()
+    };
+    let (mut access_key_id, mut secret_access_key, mut session_token, mut expiration) =
+        (None, None, None, None);
+    let mut element = "";
+    for token in Tokenizer::from(body) {
+        match token.map_err(|err| invalid(&format!("invalid response: {err}")))? {
+            Token::ElementStart { local, .. } => element = local.as_str(),
+            Token::ElementEnd {
+                end: ElementEnd::Empty | ElementEnd::Close(..),
+                ..
+            } => element = "",
+            Token::Text { text } => {
+                let field = match element {
+                    "AccessKeyId" => &mut access_key_id,
+                    "SecretAccessKey" => &mut secret_access_key,
+                    "SessionToken" => &mut session_token,
+                    "Expiration" => &mut expiration,
+                    _ => continue,
+                };
+                *field = Some(text.as_str().trim().to_string());
+            }
+            _ => {}
+        }
+    }
+    let expiration = expiration
+        .and_then(|expiration| chrono::DateTime::parse_from_rfc3339(&expiration).ok())
+        .ok_or_else(|| invalid("response is missing or has an invalid Expiration"))?;
+    Ok(Session {
+        access_key_id: access_key_id.ok_or_else(|| invalid("response is missing AccessKeyId"))?,
+        secret_access_key: secret_access_key
+            .ok_or_else(|| invalid("response is missing SecretAccessKey"))?,
+        session_token: session_token.ok_or_else(|| invalid("response is missing SessionToken"))?,
+        expiration: expiration.into(),
+    })
+}
+
+/// The S3 request signing settings, S3 neither normalizing nor re-encoding paths
+fn signing_settings() -> SigningSettings {
+    let mut settings = SigningSettings::default();
+    settings.percent_encoding_mode = PercentEncodingMode::Single;
+    settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+    settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+    settings
+}
+
+/// Sign `request`, to directory bucket `bucket`, with `session`
+///
+/// The SDK addresses buckets path-style, so the bucket is first dropped from
+/// the path of requests to its zonal endpoint, whose host already names it.
+pub(crate) fn sign_request(
+    request: &mut http::Request<SdkBody>,
+    bucket: &str,
+    region: &str,
+    session: &Session,
+    time: SystemTime,
+) -> Result<(), BoxError> {
+    let uri = request.uri();
+    let virtual_hosted = matches!(
+        uri.host().and_then(|host| host.strip_prefix(bucket)),
+        Some(rest) if rest.starts_with('.')
+    );
+    if virtual_hosted {
+        let path = match uri
+            .path()
+            .strip_prefix('/')
+            .and_then(|p| p.strip_prefix(bucket))
+        {
+            Some("") => Some("/"),
+            Some(rest) if rest.starts_with('/') => Some(rest),
+            _ => None,
+        };
+        if let Some(path) = path {
+            let path_and_query = match uri.query() {
+                Some(query) => format!("{path}?{query}"),
+                None => path.to_string(),
+            };
+            let mut parts = uri.clone().into_parts();
+            parts.path_and_query = Some(path_and_query.parse()?);
+            *request.uri_mut() = http::Uri::from_parts(parts)?;
+        }
+    }
+
+    let headers = request.headers_mut();
+    for name in [AUTHORIZATION, SECURITY_TOKEN, DATE] {
+        headers.remove(name);
+    }
+    headers.insert(
+        SESSION_TOKEN,
+        HeaderValue::from_str(&session.session_token)?,
+    );
+    let body = match headers.get(CONTENT_SHA256).map(HeaderValue::to_str) {
+        Some(Ok(hash)) if hash != "UNSIGNED-PAYLOAD" => SignableBody::Precomputed(hash.into()),
+        _ => SignableBody::UnsignedPayload,
+    };
+
+    let params = SigningParams::builder()
+        .access_key(&session.access_key_id)
+        .secret_key(&session.secret_access_key)
+        .region(region)
+        .service_name(SIGNING_NAME)
+        .time(time)
+        .settings(signing_settings())
+        .build()?;
+    let signable = SignableRequest::new(request.method(), request.uri(), request.headers(), body);
+    let (instructions, _) = sign(signable, &params)?.into_parts();
+    instructions.apply_to_request(request);
+    Ok(())
+}
+
+/// The session of a directory bucket, created (and replaced before it expires)
+/// on demand
+#[derive(Debug)]
+pub(crate) struct Sessions {
+    bucket: String,
+    region: String,
+    /// The URL of the bucket's `CreateSession` requests
+    url: String,
+    credentials: SharedCredentialsProvider,
+    connector: Adapter,
+    time_source: SharedTimeSource,
+    session: Mutex<Option<Session>>,
+}
+
+impl Sessions {
+    pub(crate) fn new(
+        bucket: String,
+        region: String,
+        endpoint: &str,
+        credentials: SharedCredentialsProvider,
+        connector: Adapter,
+        time_source: SharedTimeSource,
+    ) -> Self {
+        let endpoint = endpoint.trim_end_matches('/');
+        // zonal endpoints name the bucket in the host, custom ones are path-style
+        let url = match endpoint.contains(&format!("://{bucket}.")) {
+            true => format!("{endpoint}/?session"),
+            false => format!("{endpoint}/{bucket}?session"),
+        };
+        Self {
+            bucket,
+            region,
+            url,
+            credentials,
+            connector,
+            time_source,
+            session: Mutex::new(None),
+        }
+    }
+
+    /// The current session, if any, which may have expired
+    fn current(&self) -> Option<Session> {
+        self.session.lock().unwrap().clone()
+    }
+
+    /// Create a session, unless the current one has yet to near its expiry
+    ///
+    /// Concurrent requests without a fresh session may each create one, the
+    /// last created being kept, any of them being valid for the bucket.
+    pub(crate) async fn ensure(&self) -> object_store::Result<()> {
+        let now = self.time_source.now();
+        if matches!(self.current(), Some(session) if session.is_fresh(now)) {
+            return Ok(());
+        }
+        let session = self.create().await?;
+        *self.session.lock().unwrap() = Some(session);
+        Ok(())
+    }
+
+    /// Discard the current session, e.g. once S3 reports it expired
+    pub(crate) fn invalidate(&self) {
+        self.session.lock().unwrap().take();
+    }
+
+    /// Issue a `CreateSession` request, signed with the configured credentials
+    async fn create(&self) -> object_store::Result<Session> {
+        let failed =
+            |message: String| -> object_store::Error { Error::CreateSession { message }.into() };
+        let credentials = self
+            .credentials
+            .provide_credentials()
+            .await
+            .map_err(|err| failed(format!("no credentials: {err}")))?;
+        let mut request = http::Request::get(&self.url)
+            .body(SdkBody::empty())
+            .map_err(|err| failed(err.to_string()))?;
+        let mut params = SigningParams::builder()
+            .access_key(credentials.access_key_id())
+            .secret_key(credentials.secret_access_key())
+            .region(&self.region)
+            .service_name(SIGNING_NAME)
+            .time(self.time_source.now())
+            .settings(signing_settings());
+        params.set_security_token(credentials.session_token());
+        let params = params.build().map_err(|err| failed(err.to_string()))?;
+        let signable = SignableRequest::new(
+            request.method(),
+            request.uri(),
+            request.headers(),
+            SignableBody::Bytes(&[]),
+        );
+        let (instructions, _) = sign(signable, &params)
+            .map_err(|err| failed(err.to_string()))?
+            .into_parts();
+        instructions.apply_to_request(&mut request);
+
+        let response = self
+            .connector
+            .clone()
+            .call(request)
+            .await
+            .map_err(|err| failed(err.to_string()))?;
+        let body = response
+            .body()
+            .bytes()
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default();
+        match response.status().is_success() {
+            true => parse_create_session(&body),
+            false => Err(failed(format!(
+                "{} for bucket {}: {}",
+                response.status(),
+                self.bucket,
+                body
+            ))),
+        }
+    }
+}
+
+/// Re-signs requests with the current session of a directory bucket, which
+/// [`Sessions::ensure`] must have created beforehand
+#[derive(Debug)]
+pub(crate) struct SessionAuth(pub(crate) Arc<Sessions>);
+
+impl Interceptor for SessionAuth {
+    fn name(&self) -> &'static str {
+        "SessionAuth"
+    }
+
+    fn modify_before_transmit(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Self(sessions) = self;
+        let session = sessions
+            .current()
+            .ok_or("no S3 Express session has been created")?;
+        sign_request(
+            context.request_mut(),
+            &sessions.bucket,
+            &sessions.region,
+            &session,
+            sessions.time_source.now(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            access_key_id: "ASIASESSION".to_string(),
+            secret_access_key: "session-secret".to_string(),
+            session_token: "session-token".to_string(),
+            expiration: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_300),
+        }
+    }
+
+    #[test]
+    fn test_zone_id() {
+        assert_eq!(zone_id("logs--usw2-az1--x-s3"), Some("usw2-az1"));
+        assert_eq!(zone_id("my--logs--use1-az4--x-s3"), Some("use1-az4"));
+        for bucket in ["logs", "logs--x-s3", "--x-s3", "logs----x-s3"] {
+            assert_eq!(zone_id(bucket), None, "{bucket}");
+        }
+        assert_eq!(
+            zonal_endpoint("logs--usw2-az1--x-s3", "us-west-2").as_deref(),
+            Some("https://logs--usw2-az1--x-s3.s3express-usw2-az1.us-west-2.amazonaws.com")
+        );
+    }
+
+    #[test]
+    fn test_parse_create_session() {
+        let body = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+            <CreateSessionResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+            <Credentials><SessionToken>session-token</SessionToken>\
+            <SecretAccessKey>session-secret</SecretAccessKey>\
+            <AccessKeyId>ASIASESSION</AccessKeyId>\
+            <Expiration>2023-11-14T22:18:20Z</Expiration></Credentials>\
+            </CreateSessionResult>";
+        assert_eq!(parse_create_session(body).unwrap(), session());
+
+        let truncated = &body[..body.find("<Expiration>").unwrap()];
+        assert!(parse_create_session(truncated).is_err());
+        let missing_token = body.replace("<SessionToken>session-token</SessionToken>", "");
+        assert!(parse_create_session(&missing_token).is_err());
+    }
+
+    #[test]
+    fn test_session_freshness() {
+        let session = session();
+        let expiration = session.expiration;
+        assert!(session.is_fresh(expiration - Duration::from_secs(240)));
+        assert!(!session.is_fresh(expiration - Duration::from_secs(30)));
+        assert!(!session.is_fresh(expiration + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_sign_request() {
+        let bucket = "logs--usw2-az1--x-s3";
+        let host = "logs--usw2-az1--x-s3.s3express-usw2-az1.us-west-2.amazonaws.com";
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for (uri, expected) in [
+            (
+                format!("https://{host}/{bucket}/a/b.txt"),
+                format!("https://{host}/a/b.txt"),
+            ),
+            (
+                format!("https://{host}/{bucket}?list-type=2&prefix=a"),
+                format!("https://{host}/?list-type=2&prefix=a"),
+            ),
+            // custom (path-style) endpoints keep the bucket in the path
+            (
+                format!("http://localhost:9000/{bucket}/a.txt"),
+                format!("http://localhost:9000/{bucket}/a.txt"),
+            ),
+        ] {
+            let mut request = http::Request::get(&uri)
+                .header(AUTHORIZATION, "AWS4-HMAC-SHA256 Credential=AKIABASE/...")
+                .header(SECURITY_TOKEN, "base-token")
+                .header(DATE, "20000101T000000Z")
+                .header(CONTENT_SHA256, "UNSIGNED-PAYLOAD")
+                .body(SdkBody::empty())
+                .unwrap();
+            sign_request(&mut request, bucket, "us-west-2", &session(), time).unwrap();
+
+            assert_eq!(request.uri().to_string(), expected);
+            let headers = request.headers();
+            assert_eq!(headers[&SESSION_TOKEN], "session-token");
+            assert_eq!(headers[&DATE], "20231114T221320Z");
+            assert!(!headers.contains_key(&SECURITY_TOKEN));
+            let authorization = headers[&AUTHORIZATION].to_str().unwrap();
+            assert!(
+                authorization.starts_with(
+                    "AWS4-HMAC-SHA256 Credential=ASIASESSION/20231114/us-west-2/s3express/aws4_request"
+                ),
+                "{authorization}"
+            );
+            assert!(
+                authorization.contains("x-amz-s3session-token"),
+                "{authorization}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_session_urls() {
+        let sessions = |endpoint: &str| {
+            Sessions::new(
+                "logs--usw2-az1--x-s3".to_string(),
+                "us-west-2".to_string(),
+                endpoint,
+                SharedCredentialsProvider::new(aws_credential_types::Credentials::from_keys(
+                    "key", "secret", None,
+                )),
+                Adapter::new(None, None, web_sys::RequestCache::NoStore),
+                SharedTimeSource::default(),
+            )
+        };
+        assert_eq!(
+            sessions("https://logs--usw2-az1--x-s3.s3express-usw2-az1.us-west-2.amazonaws.com").url,
+            "https://logs--usw2-az1--x-s3.s3express-usw2-az1.us-west-2.amazonaws.com/?session"
+        );
+        assert_eq!(
+            sessions("http://localhost:9000/").url,
+            "http://localhost:9000/logs--usw2-az1--x-s3?session"
+        );
+    }
+}
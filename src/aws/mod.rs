@@ -1,86 +1,878 @@
-use std::{fmt::Display, num::ParseIntError, ops::Range, sync::Arc};
+use std::{collections::HashMap, fmt::Display, future::Future, ops::Range, pin::Pin, sync::Arc};
+
+use async_compression::futures::bufread::{GzipDecoder, ZstdDecoder};
 
 use async_trait::async_trait;
-use aws_sdk_s3::Client;
+use aws_sdk_s3::{
+    error::{ProvideErrorMetadata, SdkError},
+    operation::{
+        get_object::GetObjectOutput, head_object::HeadObjectOutput, put_object::PutObjectOutput,
+    },
+    types::{
+        ChecksumAlgorithm, Delete, Error as S3Error, GlacierJobParameters, MetadataDirective,
+        ObjectCannedAcl, ObjectIdentifier, RestoreRequest, Tag, Tagging, TaggingDirective, Tier,
+    },
+    Client,
+};
+use aws_smithy_runtime_api::{
+    box_error::BoxError,
+    client::{
+        interceptors::{context::BeforeTransmitInterceptorContextMut, Interceptor},
+        runtime_components::RuntimeComponents,
+    },
+};
+use aws_smithy_types::config_bag::ConfigBag;
+use aws_smithy_types::DateTime as SmithyDateTime;
 use builder::AmazonS3Builder;
+use bytes::Bytes;
 use chrono::{DateTime, TimeZone, Utc};
 use error::Error;
 use futures::{
+    future::{self, Either},
+    io::{AsyncRead, AsyncReadExt},
     stream::{self, BoxStream},
-    TryFutureExt, TryStreamExt,
+    StreamExt, TryStreamExt,
 };
-use object_store::Attributes;
+use http::header::{HeaderName, HeaderValue, IF_MATCH, IF_NONE_MATCH, IF_RANGE};
+use object_store::{Attribute, Attributes};
 use object_store::{
-    GetRange, GetResultPayload, ListResult, ObjectMeta, ObjectStore, PutOptions, PutResult,
+    GetRange, GetResultPayload, ListResult, ObjectMeta, ObjectStore, PutMode, PutOptions,
+    PutResult, TagSet,
 };
+use retry::RetryPolicy;
+
+use crate::utils::{expires_attribute_value, parse_http_date, IfRange, EXPIRES_ATTRIBUTE};
 
 pub mod builder;
 mod error;
+mod express;
 mod multipart;
+pub mod restore;
+pub mod retry;
+
+type HttpResponse = http::Response<aws_sdk_s3::primitives::SdkBody>;
 const STORE: &str = "S3";
+/// The largest object S3 accepts in a single `PutObject` request (5GiB)
+const MAX_PUT_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// The header of the offset an append is made at, which must be the object's size
+const WRITE_OFFSET: HeaderName = HeaderName::from_static("x-amz-write-offset-bytes");
+/// The most keys S3 deletes in a single `DeleteObjects` request
+const MAX_DELETE_BATCH: usize = 1000;
+/// The [`Attribute::Metadata`] key of the `x-amz-mp-parts-count` header, the
+/// number of parts an object was uploaded in. Only reported by
+/// [`AmazonS3::get_part`], S3 omits it from other requests.
+pub const PARTS_COUNT_ATTRIBUTE: &str = "x-amz-mp-parts-count";
+/// The [`Attribute::Metadata`] key of the `x-amz-replication-status` header, the
+/// cross-region replication status of an object (`PENDING`, `COMPLETED`, `FAILED`,
+/// or `REPLICA` for a replica). Reported on `get`/`head` of replicated objects.
+pub const REPLICATION_STATUS_ATTRIBUTE: &str = "x-amz-replication-status";
+/// The [`Attribute::Metadata`] key of the `x-amz-server-side-encryption` header,
+/// the algorithm an object is encrypted at rest with (e.g. `AES256`, `aws:kms`)
+pub const SSE_ATTRIBUTE: &str = "x-amz-server-side-encryption";
+/// The [`Attribute::Metadata`] key of the KMS key an object is encrypted with,
+/// when encrypted with `aws:kms`
+pub const SSE_KMS_KEY_ID_ATTRIBUTE: &str = "x-amz-server-side-encryption-aws-kms-key-id";
+/// The [`Attribute::Metadata`] key of the `x-amz-tagging-count` header, the
+/// number of tags an object has. Only reported by `get`, S3 omits it from `head`.
+pub const TAGGING_COUNT_ATTRIBUTE: &str = "x-amz-tagging-count";
+/// The [`Attribute::Metadata`] key of the `x-amz-website-redirect-location`
+/// header, which redirects requests for an object served by S3 static website
+/// hosting. Set on `put`, and reported on `get`/`head`.
+pub const WEBSITE_REDIRECT_ATTRIBUTE: &str = "x-amz-website-redirect-location";
 
-#[derive(Debug)]
+/// The size of the buffer decompressed objects are read in to
+const DECOMPRESS_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Decode `payload` according to the extension of `location`, if it's one of
+/// `.gz` or `.zst`
+fn decompress_by_extension(
+    location: &object_store::path::Path,
+    payload: BoxStream<'static, object_store::Result<Bytes>>,
+) -> BoxStream<'static, object_store::Result<Bytes>> {
+    let extension = match location.extension() {
+        Some(extension @ ("gz" | "zst")) => extension,
+        _ => return payload,
+    };
+    let reader = payload
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))
+        .into_async_read();
+    let decoder: Pin<Box<dyn AsyncRead + Send>> = if extension == "gz" {
+        let mut decoder = GzipDecoder::new(reader);
+        // concatenated gzip members (e.g. appended logs) form a single stream
+        decoder.multiple_members(true);
+        Box::pin(decoder)
+    } else {
+        Box::pin(ZstdDecoder::new(reader))
+    };
+    Box::pin(stream::try_unfold(decoder, |mut decoder| async move {
+        let mut buf = vec![0; DECOMPRESS_CHUNK_SIZE];
+        let read = decoder
+            .read(&mut buf)
+            .await
+            .map_err(|err| object_store::Error::Generic {
+                store: STORE,
+                source: Box::new(err),
+            })?;
+        buf.truncate(read);
+        Ok((read > 0).then(|| (Bytes::from(buf), decoder)))
+    }))
+}
+
+/// The only algorithm S3 supports for customer-provided keys
+const SSE_CUSTOMER_ALGORITHM: &str = "AES256";
+
+/// A customer-provided (SSE-C) encryption key, see
+/// [`AmazonS3Builder::with_sse_customer_key`]
+///
+/// The key material is redacted from `Debug`, so never ends up in logs.
+#[derive(Clone)]
+pub(crate) struct SseCustomerKey {
+    /// The base64 encoded 256-bit key
+    pub(crate) key: String,
+    /// The base64 encoded MD5 digest of the (decoded) key
+    pub(crate) key_md5: String,
+}
+
+impl std::fmt::Debug for SseCustomerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SseCustomerKey(<redacted>)")
+    }
+}
+
+/// Set the SSE-C key headers on a request, if a customer-provided key is configured
+macro_rules! sse_customer_key {
+    ($request:expr, $key:expr) => {{
+        let key: &Option<SseCustomerKey> = $key;
+        $request
+            .set_sse_customer_algorithm(key.as_ref().map(|_| SSE_CUSTOMER_ALGORITHM.to_string()))
+            .set_sse_customer_key(key.as_ref().map(|key| key.key.clone()))
+            .set_sse_customer_key_md5(key.as_ref().map(|key| key.key_md5.clone()))
+    }};
+}
+
+/// Set the SSE-C key headers for the source object of a `CopyObject` request
+macro_rules! copy_source_sse_customer_key {
+    ($request:expr, $key:expr) => {{
+        let key: &Option<SseCustomerKey> = $key;
+        $request
+            .set_copy_source_sse_customer_algorithm(
+                key.as_ref().map(|_| SSE_CUSTOMER_ALGORITHM.to_string()),
+            )
+            .set_copy_source_sse_customer_key(key.as_ref().map(|key| key.key.clone()))
+            .set_copy_source_sse_customer_key_md5(key.as_ref().map(|key| key.key_md5.clone()))
+    }};
+}
+
+/// Set the headers described by [`Attributes`] on a `PutObject` or `CopyObject` request
+macro_rules! apply_attributes {
+    ($request:expr, $attributes:expr) => {{
+        let mut request = $request;
+        for (attribute, value) in $attributes.iter() {
+            let value = value.to_string();
+            request = match attribute {
+                Attribute::ContentDisposition => request.content_disposition(value),
+                Attribute::ContentEncoding => request.content_encoding(value),
+                Attribute::ContentLanguage => request.content_language(value),
+                Attribute::ContentType => request.content_type(value),
+                Attribute::CacheControl => request.cache_control(value),
+                Attribute::Metadata(key) if key == WEBSITE_REDIRECT_ATTRIBUTE => {
+                    request.website_redirect_location(value)
+                }
+                Attribute::Metadata(key) if key == EXPIRES_ATTRIBUTE => {
+                    match parse_http_date(&value) {
+                        Some(date) => request.expires(SmithyDateTime::from_secs(date.timestamp())),
+                        None => request,
+                    }
+                }
+                // reported by S3, rather than set by requests
+                Attribute::Metadata(key) if key.starts_with("x-amz-") => request,
+                Attribute::Metadata(key) => request.metadata(key.to_string(), value),
+                _ => request,
+            };
+        }
+        request
+    }};
+}
+
+/// A single page of a listing, see [`AmazonS3::list_page`]
+#[derive(Debug, Clone)]
+pub struct ListPage {
+    pub objects: Vec<ObjectMeta>,
+    /// The number of keys S3 returned in this page, including any prefix
+    /// marker omitted from `objects`
+    pub key_count: usize,
+    /// Whether further pages follow
+    pub is_truncated: bool,
+    /// The token to pass to [`AmazonS3::list_page`] for the next page, if any
+    pub next_continuation_token: Option<String>,
+    /// The owners of `objects`, by location, if requested with
+    /// [`AmazonS3Builder::with_fetch_owner`]
+    pub owners: HashMap<object_store::path::Path, ObjectOwner>,
+}
+
+/// The owner of a listed object
+#[derive(Debug, Clone)]
+pub struct ObjectOwner {
+    /// The canonical ID of the owning account
+    pub id: Option<String>,
+    /// The owner's display name, which S3 only reports in some regions
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Clone)]
 pub struct AmazonS3 {
     client: Arc<Client>,
     bucket: String,
+    force_credential_refresh: bool,
+    retry_policy: RetryPolicy,
+    skip_prefix_marker: bool,
+    fetch_owner: bool,
+    verify_put_etag: bool,
+    checksum_validation: bool,
+    expected_bucket_owner: Option<String>,
+    object_acl: Option<ObjectCannedAcl>,
+    decompress_by_extension: bool,
+    dry_run: bool,
+    sse_customer_key: Option<SseCustomerKey>,
+    /// The sessions of an S3 Express One Zone directory bucket
+    express: Option<Arc<express::Sessions>>,
 }
 
 impl AmazonS3 {
     pub fn builder() -> AmazonS3Builder {
         AmazonS3Builder::default()
     }
-}
 
-#[async_trait]
-impl ObjectStore for AmazonS3 {
-    async fn copy(
+    /// Get part `part_number` (1-based) of an object uploaded with multipart upload
+    ///
+    /// The result's [`PARTS_COUNT_ATTRIBUTE`] reports the object's total number of
+    /// parts, so that readers can issue further part-aligned reads. Objects
+    /// uploaded in a single request consist of exactly one part.
+    pub async fn get_part(
+        &self,
+        location: &object_store::path::Path,
+        part_number: i32,
+    ) -> object_store::Result<object_store::GetResult> {
+        let request = self
+            .client
+            .get_object()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(location.to_string())
+            .part_number(part_number);
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let response = self
+            .send(|| request.clone().send())
+            .await
+            .map_err(|err| Error::from(err).with_path(location))?;
+        get_result(location, response)
+    }
+
+    /// Verify the store is reachable and its credentials grant access to the
+    /// bucket, by way of a `HeadBucket` request
+    ///
+    /// Fails with a distinct error for a missing bucket, denied access (e.g.
+    /// invalid credentials) and network failures.
+    pub async fn check_access(&self) -> object_store::Result<()> {
+        let request = self
+            .client
+            .head_bucket()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone());
+        self.send(|| request.clone().send()).await.map_err(|err| {
+            // HEAD responses carry no error body, so no error code either
+            match (&err, err.raw_response().map(|res| res.status().as_u16())) {
+                (_, Some(404)) => Error::NoSuchBucket {
+                    bucket: self.bucket.clone(),
+                },
+                (_, Some(401 | 403)) => Error::AccessDenied {
+                    source: Box::new(Error::from(err)),
+                },
+                (SdkError::DispatchFailure(_) | SdkError::TimeoutError(_), _) => {
+                    Error::Unreachable {
+                        source: Box::new(Error::from(err)),
+                    }
+                }
+                _ => Error::from(err),
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Append `payload` to the object at `location`, whose size must be
+    /// `offset`, returning the object's new size
+    ///
+    /// An `offset` of 0 creates the object, should it not exist. Only S3 Express
+    /// One Zone directory buckets support appends (`x-amz-write-offset-bytes`),
+    /// other buckets fail with [`NotSupported`](object_store::Error::NotSupported).
+    /// Fails with [`Precondition`](object_store::Error::Precondition) when
+    /// `offset` isn't the object's size, e.g. after a concurrent append, in
+    /// which case re-read the size with `head` before appending again.
+    pub async fn append(
+        &self,
+        location: &object_store::path::Path,
+        offset: u64,
+        payload: object_store::PutPayload,
+    ) -> object_store::Result<u64> {
+        if self.express.is_none() {
+            return Err(object_store::Error::NotSupported {
+                source: "appends require an S3 Express One Zone directory bucket".into(),
+            });
+        }
+        let buf = bytes::Bytes::from(payload);
+        let size = buf.len() as u64;
+        if self.dry_run {
+            let action = format!("append {size} bytes to {location} at offset {offset}");
+            self.dry_run_action(action, None).await?;
+        }
+        // PutObject has no field for the offset, so it's added as a header
+        let client = self.with_interceptor(Precondition(WRITE_OFFSET, HeaderValue::from(offset)));
+        // the request body isn't `Clone`, so the request is rebuilt per attempt
+        self.send(|| {
+            client
+                .put_object()
+                .bucket(self.bucket.clone())
+                .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+                .key(location.to_string())
+                .body(buf.clone().into())
+                .send()
+        })
+        .await
+        .map_err(|err| {
+            let status = err.raw_response().map(|res| res.status().as_u16());
+            match (err.code(), status) {
+                (Some("InvalidWriteOffset"), _) | (_, Some(412)) => {
+                    object_store::Error::Precondition {
+                        path: location.to_string(),
+                        source: Box::new(Error::from(err)),
+                    }
+                }
+                _ => Error::from(err).into(),
+            }
+        })?;
+        Ok(offset + size)
+    }
+
+    /// The `x-amz-copy-source` of the object at `from`, or of its version
+    /// `version_id`, if any
+    fn copy_source(&self, from: &object_store::path::Path, version_id: Option<&str>) -> String {
+        // access point copy sources take the form `<arn>/object/<key>`
+        let source = match builder::is_access_point_arn(&self.bucket) {
+            true => format!("{}/object/{}", self.bucket, from),
+            false => format!("{}/{}", self.bucket, from),
+        };
+        match version_id {
+            Some(version_id) => format!("{source}?versionId={version_id}"),
+            None => source,
+        }
+    }
+
+    /// Copy version `version_id` of the object at `from` to `to`
+    ///
+    /// In a versioned bucket this restores an older version, by making a copy
+    /// of it the current version of `to`.
+    pub async fn copy_version(
         &self,
         from: &object_store::path::Path,
+        version_id: &str,
         to: &object_store::path::Path,
     ) -> object_store::Result<()> {
-        let mut source_bucket_and_object: String = "".to_owned();
-        source_bucket_and_object.push_str(&self.bucket);
-        source_bucket_and_object.push('/');
-        source_bucket_and_object.push_str(from.as_ref());
-        self.client
+        if self.dry_run {
+            let action = format!("copied version {version_id} of {from} to {to}");
+            return self.dry_run_action(action, Some(from)).await;
+        }
+        let request = self
+            .client
             .copy_object()
-            .copy_source(source_bucket_and_object)
+            .copy_source(self.copy_source(from, Some(version_id)))
+            .set_expected_source_bucket_owner(self.expected_bucket_owner.clone())
+            .set_acl(self.object_acl.clone())
             .bucket(self.bucket.clone())
-            .key(to.to_string())
-            .send()
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(to.to_string());
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let request = copy_source_sse_customer_key!(request, &self.sse_customer_key);
+        self.send(|| request.clone().send())
             .await
             .map_err(Error::from)?;
         Ok(())
     }
-    async fn copy_if_not_exists(
+
+    /// Permanently delete version `version_id` of the object at `location`
+    ///
+    /// Unlike `delete`, which in a versioned bucket only adds a delete marker,
+    /// this removes the version itself, leaving other versions intact. Deleting
+    /// the current version makes the previous one current.
+    pub async fn delete_version(
         &self,
-        _from: &object_store::path::Path,
-        _to: &object_store::path::Path,
+        location: &object_store::path::Path,
+        version_id: &str,
     ) -> object_store::Result<()> {
-        Err(object_store::Error::NotSupported {
-            source: Box::new(Error::Unknown),
-        })
-    }
-    async fn delete(&self, location: &object_store::path::Path) -> object_store::Result<()> {
-        self.client
+        if self.dry_run {
+            let action = format!("deleted version {version_id} of {location}");
+            return self.dry_run_action(action, None).await;
+        }
+        let request = self
+            .client
             .delete_object()
             .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
             .key(location.to_string())
-            .send()
+            .version_id(version_id);
+        self.send(|| request.clone().send())
             .await
             .map_err(Error::from)?;
         Ok(())
     }
-    async fn get_opts(
+
+    /// Copy `from` to `to`, replacing the source's metadata with `attributes`
+    /// rather than copying it (`x-amz-metadata-directive: REPLACE`)
+    ///
+    /// Copying an object onto itself this way updates its metadata in place,
+    /// e.g. to correct a `Content-Type`. [`Attribute::Metadata`] entries become
+    /// user metadata (`x-amz-meta-*`), other than the `x-amz-*` keys this module
+    /// defines, such as [`WEBSITE_REDIRECT_ATTRIBUTE`].
+    pub async fn copy_with_metadata(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+        attributes: &Attributes,
+    ) -> object_store::Result<()> {
+        if self.dry_run {
+            let action = format!("copied {from} to {to}, replacing its metadata");
+            return self.dry_run_action(action, Some(from)).await;
+        }
+        let request = self
+            .client
+            .copy_object()
+            .copy_source(self.copy_source(from, None))
+            .set_expected_source_bucket_owner(self.expected_bucket_owner.clone())
+            .set_acl(self.object_acl.clone())
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(to.to_string())
+            .metadata_directive(MetadataDirective::Replace);
+        let request = apply_attributes!(request, attributes);
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let request = copy_source_sse_customer_key!(request, &self.sse_customer_key);
+        self.send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    /// Copy `from` to `to`, tagging the copy with `tags` rather than the source's
+    /// tags (`x-amz-tagging-directive: REPLACE`)
+    ///
+    /// `copy` preserves the source's tags. An empty `tags` leaves the copy untagged.
+    pub async fn copy_with_tags(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+        tags: &TagSet,
+    ) -> object_store::Result<()> {
+        if self.dry_run {
+            let action = format!("copied {from} to {to}, replacing its tags");
+            return self.dry_run_action(action, Some(from)).await;
+        }
+        let request = self
+            .client
+            .copy_object()
+            .copy_source(self.copy_source(from, None))
+            .set_expected_source_bucket_owner(self.expected_bucket_owner.clone())
+            .set_acl(self.object_acl.clone())
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(to.to_string())
+            .tagging_directive(TaggingDirective::Replace)
+            .tagging(tags.encoded());
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let request = copy_source_sse_customer_key!(request, &self.sse_customer_key);
+        self.send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    /// Replace the tags of the object at `location` with `tags`
+    pub async fn put_tags(
         &self,
         location: &object_store::path::Path,
-        options: object_store::GetOptions,
-    ) -> object_store::Result<object_store::GetResult> {
+        tags: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> object_store::Result<()> {
+        let tag_set: Vec<_> = tags
+            .into_iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect();
+        if self.dry_run {
+            let action = format!("set {} tags on {location}", tag_set.len());
+            return self.dry_run_action(action, Some(location)).await;
+        }
         let request = self
             .client
-            .get_object()
+            .put_object_tagging()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(location.to_string())
+            .tagging(Tagging::builder().set_tag_set(Some(tag_set)).build());
+        self.send(|| request.clone().send())
+            .await
+            .map_err(|err| Error::from(err).with_path(location))?;
+        Ok(())
+    }
+
+    /// The tags of the object at `location`, as key - value pairs
+    pub async fn get_tags(
+        &self,
+        location: &object_store::path::Path,
+    ) -> object_store::Result<Vec<(String, String)>> {
+        let request = self
+            .client
+            .get_object_tagging()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(location.to_string());
+        let response = self
+            .send(|| request.clone().send())
+            .await
+            .map_err(|err| Error::from(err).with_path(location))?;
+        Ok(response
+            .tag_set
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| (tag.key.unwrap_or_default(), tag.value.unwrap_or_default()))
+            .collect())
+    }
+
+    /// Remove all tags from the object at `location`
+    pub async fn delete_tags(
+        &self,
+        location: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        if self.dry_run {
+            let action = format!("removed the tags of {location}");
+            return self.dry_run_action(action, Some(location)).await;
+        }
+        let request = self
+            .client
+            .delete_object_tagging()
             .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
             .key(location.to_string());
+        self.send(|| request.clone().send())
+            .await
+            .map_err(|err| Error::from(err).with_path(location))?;
+        Ok(())
+    }
+
+    /// List a single page (of up to 1000 objects) under `prefix`, continuing
+    /// from `continuation_token`, if any
+    ///
+    /// Unlike `list`, reports the progress of the listing, e.g. for showing
+    /// "loaded N so far" whilst listing large prefixes.
+    pub async fn list_page(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+        continuation_token: Option<String>,
+    ) -> object_store::Result<ListPage> {
+        let request = self
+            .client
+            .list_objects_v2()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .set_prefix(prefix.map(|prefix| prefix.to_string()))
+            .set_fetch_owner(self.fetch_owner.then_some(true))
+            .set_continuation_token(continuation_token);
+        let response = self
+            .send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        let mut objects = Vec::new();
+        let mut owners = HashMap::new();
+        for mut object in response.contents.unwrap_or_default() {
+            let owner = object.owner.take();
+            let meta = listed_meta(object)?;
+            if self.is_prefix_marker(prefix, &meta.location) {
+                continue;
+            }
+            if let Some(owner) = owner {
+                let owner = ObjectOwner {
+                    id: owner.id,
+                    display_name: owner.display_name,
+                };
+                owners.insert(meta.location.clone(), owner);
+            }
+            objects.push(meta);
+        }
+        Ok(ListPage {
+            objects,
+            key_count: response.key_count.max(0) as usize,
+            is_truncated: response.is_truncated,
+            next_continuation_token: response.next_continuation_token,
+            owners,
+        })
+    }
+
+    /// Check the bucket (and `source`, if any) is accessible, then fail with
+    /// [`Error::DryRun`] describing `action`, which is not performed
+    async fn dry_run_action(
+        &self,
+        action: String,
+        source: Option<&object_store::path::Path>,
+    ) -> object_store::Result<()> {
+        self.check_access().await?;
+        if let Some(source) = source {
+            self.head(source).await?;
+        }
+        Err(Error::DryRun { action }.into())
+    }
+
+    /// Whether `location` is the marker object (`folder/`, or `folder`) of
+    /// `prefix`, to omit from listings under it, if configured
+    fn is_prefix_marker(
+        &self,
+        prefix: Option<&object_store::path::Path>,
+        location: &object_store::path::Path,
+    ) -> bool {
+        match prefix {
+            Some(prefix) if self.skip_prefix_marker => {
+                let key = location.as_ref();
+                key.strip_suffix(object_store::path::DELIMITER)
+                    .unwrap_or(key)
+                    == prefix.as_ref()
+            }
+            _ => false,
+        }
+    }
+
+    /// Request a temporary copy of an archived (e.g. Glacier) object be made
+    /// available for `days` days, retrieved at the given `tier` (e.g. `"Standard"`,
+    /// `"Bulk"` or `"Expedited"`)
+    ///
+    /// Restores complete asynchronously, poll their progress via the
+    /// [`restore::RESTORE_ATTRIBUTE`] attribute of a `head` request made with
+    /// [`get_opts`](ObjectStore::get_opts).
+    pub async fn restore_object(
+        &self,
+        location: &object_store::path::Path,
+        days: i32,
+        tier: impl Into<Tier>,
+    ) -> object_store::Result<()> {
+        let tier = tier.into();
+        if self.dry_run {
+            let action = format!(
+                "restored {location} for {days} days at tier {}",
+                tier.as_str()
+            );
+            return self.dry_run_action(action, Some(location)).await;
+        }
+        let restore_request = RestoreRequest::builder()
+            .days(days)
+            .glacier_job_parameters(GlacierJobParameters::builder().tier(tier).build())
+            .build();
+        let request = self
+            .client
+            .restore_object()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(location.to_string())
+            .restore_request(restore_request);
+        self.send(|| request.clone().send())
+            .await
+            .map_err(|err| Error::from(err).with_path(location))?;
+        Ok(())
+    }
+
+    /// Delete `locations` (at most [`MAX_DELETE_BATCH`]) in one `DeleteObjects`
+    /// request, returning the outcome for each location
+    ///
+    /// Fails as a whole only if the request itself fails.
+    async fn delete_batch(
+        &self,
+        locations: Vec<object_store::path::Path>,
+    ) -> object_store::Result<Vec<object_store::Result<object_store::path::Path>>> {
+        if self.dry_run {
+            let mut results = Vec::with_capacity(locations.len());
+            for location in locations {
+                results.push(self.delete(&location).await.map(|_| location));
+            }
+            return Ok(results);
+        }
+        let objects = locations
+            .iter()
+            .map(|location| {
+                ObjectIdentifier::builder()
+                    .key(location.to_string())
+                    .build()
+            })
+            .collect();
+        // quiet mode only reports the keys that failed
+        let delete = Delete::builder()
+            .set_objects(Some(objects))
+            .quiet(true)
+            .build();
+        let request = self
+            .client
+            .delete_objects()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .delete(delete);
+        let response = self
+            .send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        let mut failures: HashMap<String, S3Error> = response
+            .errors
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|error| Some((error.key.clone()?, error)))
+            .collect();
+        Ok(locations
+            .into_iter()
+            .map(|location| match failures.remove(location.as_ref()) {
+                Some(error) => Err(delete_error(location, error)),
+                None => Ok(location),
+            })
+            .collect())
+    }
+
+    /// A copy of the client which also runs `interceptor` on every request
+    fn with_interceptor(&self, interceptor: impl Interceptor + 'static) -> Client {
+        let config = self.client.config().to_builder().interceptor(interceptor);
+        Client::from_conf(config.build())
+    }
+
+    /// Sends the request produced by `send`, re-issuing it according to the
+    /// [`RetryPolicy`], and once more if S3 reports the credentials as expired
+    /// and a forced refresh is enabled
+    ///
+    /// Credentials aren't cached, so the re-issued request fetches fresh
+    /// credentials from the configured provider. Requests to directory buckets
+    /// are preceded by a `CreateSession` whenever the session nears its expiry,
+    /// and re-issued once with a new session should S3 report it expired.
+    ///
+    /// Should the policy set a deadline, the request fails with a timeout
+    /// error once it passes, including when a retry's backoff would outlast it.
+    async fn send<T, E, F, Fut>(&self, send: F) -> Result<T, SdkError<E, HttpResponse>>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, SdkError<E, HttpResponse>>>,
+        E: ProvideErrorMetadata,
+    {
+        // measured with the wall clock, as telemetry is
+        let started = Utc::now();
+        let elapsed = || (Utc::now() - started).to_std().unwrap_or_default();
+        let deadline = self.retry_policy.deadline;
+        let remaining = || deadline.map(|deadline| deadline.saturating_sub(elapsed()));
+        // only ever called with a deadline set
+        let exceeded = || {
+            SdkError::timeout_error(Error::DeadlineExceeded {
+                deadline: deadline.unwrap_or_default(),
+            })
+        };
+        let mut retries = 0;
+        let mut refreshed = false;
+        loop {
+            if let Some(sessions) = &self.express {
+                sessions
+                    .ensure()
+                    .await
+                    .map_err(SdkError::construction_failure)?;
+            }
+            let res = match remaining() {
+                Some(remaining) => {
+                    let attempt = Box::pin(send());
+                    match future::select(attempt, wasm_timer::Delay::new(remaining)).await {
+                        Either::Left((res, _)) => res,
+                        Either::Right(_) => return Err(exceeded()),
+                    }
+                }
+                None => send().await,
+            };
+            match res {
+                Err(err)
+                    if (self.force_credential_refresh || self.express.is_some())
+                        && !refreshed
+                        && error::is_expired_credentials(&err) =>
+                {
+                    if let Some(sessions) = &self.express {
+                        sessions.invalidate();
+                    }
+                    refreshed = true;
+                }
+                Err(err)
+                    if retries < self.retry_policy.max_retries
+                        && self.retry_policy.is_retriable(&err) =>
+                {
+                    let backoff = self.retry_policy.backoff(retries);
+                    if matches!(remaining(), Some(remaining) if remaining <= backoff) {
+                        return Err(exceeded());
+                    }
+                    let _ = wasm_timer::Delay::new(backoff).await;
+                    retries += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// The error for a key `DeleteObjects` failed to delete
+fn delete_error(location: object_store::path::Path, error: S3Error) -> object_store::Error {
+    let path = location.to_string();
+    let code = error.code.unwrap_or_default();
+    let kind = code.clone();
+    let source = Box::new(Error::DeleteFailed {
+        key: path.clone(),
+        code,
+        message: error.message.unwrap_or_default(),
+    });
+    match kind.as_str() {
+        "AccessDenied" => object_store::Error::PermissionDenied { path, source },
+        "NoSuchKey" => object_store::Error::NotFound { path, source },
+        _ => object_store::Error::Generic {
+            store: STORE,
+            source,
+        },
+    }
+}
+
+/// Sets a precondition header (e.g. `If-Match`, `If-Range`, or the offset of an
+/// append) on each (re)signed request, for operations whose input lacks a field
+/// for it
+#[derive(Debug)]
+struct Precondition(HeaderName, HeaderValue);
+
+impl Interceptor for Precondition {
+    fn name(&self) -> &'static str {
+        "Precondition"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Self(name, value) = self;
+        context
+            .request_mut()
+            .headers_mut()
+            .insert(name.clone(), value.clone());
+        Ok(())
+    }
+}
+
+/// Apply the preconditions, version and range of a [`GetOptions`](object_store::GetOptions)
+/// to a `GetObject` or `HeadObject` request
+macro_rules! conditional_request {
+    ($request:expr, $options:expr) => {{
+        let request = $request;
+        let options = $options;
+        let request = match options.version {
+            Some(version) => request.version_id(version),
+            None => request,
+        };
         let request = match options.if_match {
             Some(if_match) => request.if_match(if_match),
             None => request,
@@ -107,7 +899,7 @@ impl ObjectStore for AmazonS3 {
                         .signed_duration_since::<Utc>(DateTime::from_timestamp(0, 0).unwrap())
                         .num_milliseconds(),
                 );
-                request.if_modified_since(date_time)
+                request.if_unmodified_since(date_time)
             }
             None => request,
         };
@@ -125,176 +917,509 @@ impl ObjectStore for AmazonS3 {
         } else {
             request
         };
-        let response = request.send().await.map_err(Error::from)?;
-        let last_modified = Utc
-            .timestamp_millis_opt(
-                response
-                    .last_modified()
-                    .ok_or(Error::Unknown)?
-                    .to_millis()
-                    .map_err(Error::from)?,
-            )
-            .unwrap();
-        let size = response.content_length() as usize;
-        // TODO: restore the original error handling in the case that
-        // the content_range *is* present
-        let range = response
-            .content_range
-            // .ok_or(Error::Unknown)?
-            .unwrap_or(format!("0-{}", size))
-            .trim_start_matches("bytes=")
-            .split('-')
-            .map(|x| x.parse::<usize>())
-            .collect::<Result<Vec<_>, ParseIntError>>()
-            .map_err(Error::from)?;
-        Ok(object_store::GetResult {
-            payload: GetResultPayload::Stream(Box::pin(response.body.map_err(|err| {
-                object_store::Error::Generic {
-                    store: "aws_smithy",
-                    source: Box::new(err),
-                }
-            }))),
-            meta: ObjectMeta {
-                location: location.to_string().into(),
-                last_modified,
-                size,
-                e_tag: response.e_tag,
-                version: None,
-            },
-            range: Range {
-                start: range[0],
-                end: range[1],
-            },
-            attributes: Attributes::new(),
-        })
+        request
+    }};
+}
+
+/// [`Attributes`] reported on both `GetObject` and `HeadObject` responses
+macro_rules! object_attributes {
+    ($output:expr) => {{
+        let mut attributes = Attributes::new();
+        if let Some(expires) = $output.expires() {
+            if let Some(date) = Utc
+                .timestamp_opt(expires.secs(), expires.subsec_nanos())
+                .single()
+            {
+                attributes.insert(
+                    Attribute::Metadata(EXPIRES_ATTRIBUTE.into()),
+                    expires_attribute_value(date).into(),
+                );
+            }
+        }
+        if let Some(language) = $output.content_language() {
+            attributes.insert(Attribute::ContentLanguage, language.to_string().into());
+        }
+        if let Some(restore) = $output.restore() {
+            attributes.insert(
+                Attribute::Metadata(restore::RESTORE_ATTRIBUTE.into()),
+                restore.to_string().into(),
+            );
+        }
+        if let Some(location) = $output.website_redirect_location() {
+            attributes.insert(
+                Attribute::Metadata(WEBSITE_REDIRECT_ATTRIBUTE.into()),
+                location.to_string().into(),
+            );
+        }
+        if let Some(sse) = $output.server_side_encryption() {
+            attributes.insert(
+                Attribute::Metadata(SSE_ATTRIBUTE.into()),
+                sse.as_str().to_string().into(),
+            );
+        }
+        if let Some(key_id) = $output.ssekms_key_id() {
+            attributes.insert(
+                Attribute::Metadata(SSE_KMS_KEY_ID_ATTRIBUTE.into()),
+                key_id.to_string().into(),
+            );
+        }
+        if let Some(status) = $output.replication_status() {
+            attributes.insert(
+                Attribute::Metadata(REPLICATION_STATUS_ATTRIBUTE.into()),
+                status.as_str().to_string().into(),
+            );
+        }
+        if $output.parts_count() > 0 {
+            attributes.insert(
+                Attribute::Metadata(PARTS_COUNT_ATTRIBUTE.into()),
+                $output.parts_count().to_string().into(),
+            );
+        }
+        // user metadata (`x-amz-meta-*`), keyed without the prefix
+        for (key, value) in $output.metadata().into_iter().flatten() {
+            attributes.insert(
+                Attribute::Metadata(key.clone().into()),
+                value.clone().into(),
+            );
+        }
+        attributes
+    }};
+}
+
+/// Check the ETag of a `PutObject` response is the MD5 of `payload`, where S3
+/// computes it as such
+fn verify_put_etag(payload: &[u8], output: &PutObjectOutput) -> object_store::Result<()> {
+    use md5::{Digest, Md5};
+
+    let kms_encrypted = matches!(
+        output.server_side_encryption(),
+        Some(sse) if sse.as_str().starts_with("aws:kms")
+    );
+    let actual = match output.e_tag() {
+        Some(e_tag) if !kms_encrypted && output.sse_customer_algorithm().is_none() => e_tag,
+        _ => return Ok(()),
+    };
+    let expected = Md5::digest(payload)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    match actual.trim_matches('"') == expected {
+        true => Ok(()),
+        false => Err(Error::ETagMismatch {
+            expected: format!("\"{}\"", expected),
+            actual: actual.to_string(),
+        }
+        .into()),
     }
-    async fn head(
+}
+
+/// Convert the S3 timestamp `field` of a response to a UTC `DateTime`
+fn to_utc(
+    date: Option<&SmithyDateTime>,
+    field: &'static str,
+) -> object_store::Result<DateTime<Utc>> {
+    let millis = date
+        .ok_or(Error::MissingField { field })?
+        .to_millis()
+        .map_err(Error::from)?;
+    Ok(DateTime::from_timestamp_millis(millis).ok_or(Error::MissingField { field })?)
+}
+
+/// The [`Path`](object_store::path::Path) of the S3 object `key`
+///
+/// Keys are kept exactly, so that listed locations address the same keys when
+/// passed back to the store: converting via `From` would percent-encode e.g. a
+/// `%` already in the key, and `Path::parse` drops the trailing slash of
+/// `folder/` markers and refuses the empty segments of `a//b`. Only keys with
+/// a leading slash, or segments `Path` can't hold at all, are converted.
+fn key_path(key: String) -> object_store::path::Path {
+    use object_store::path::{Path, PathPart, DELIMITER};
+
+    if key.starts_with(DELIMITER) {
+        return key.into();
+    }
+    let parts = key
+        .split(DELIMITER)
+        .map(PathPart::parse)
+        .collect::<Result<Vec<_>, _>>();
+    match parts {
+        // `child` joins parts as they are, empty ones included
+        Ok(parts) => parts
+            .into_iter()
+            .fold(Path::default(), |path, part| path.child(part)),
+        Err(_) => key.into(),
+    }
+}
+
+/// The metadata of an entry of a `ListObjectsV2` response
+fn listed_meta(object: aws_sdk_s3::types::Object) -> object_store::Result<ObjectMeta> {
+    Ok(ObjectMeta {
+        last_modified: to_utc(object.last_modified(), "LastModified")?,
+        location: key_path(object.key.ok_or(Error::MissingField { field: "Key" })?),
+        size: object.size as usize,
+        e_tag: object.e_tag,
+        version: None,
+    })
+}
+
+fn head_meta(
+    location: &object_store::path::Path,
+    output: &HeadObjectOutput,
+) -> object_store::Result<ObjectMeta> {
+    Ok(ObjectMeta {
+        location: location.clone(),
+        last_modified: to_utc(output.last_modified(), "LastModified")?,
+        size: output.content_length() as usize,
+        e_tag: output.e_tag().map(|x| x.to_string()),
+        version: output.version_id().map(|x| x.to_string()),
+    })
+}
+
+/// Parse a `Content-Range` header (`bytes <first>-<last>/<total>`), returning
+/// the (exclusive) range returned, and the object's total size where known
+fn parse_content_range(content_range: &str) -> Option<(Range<usize>, Option<usize>)> {
+    let (range, total) = content_range.strip_prefix("bytes ")?.split_once('/')?;
+    let (first, last) = range.split_once('-')?;
+    let (first, last) = (first.parse::<usize>().ok()?, last.parse::<usize>().ok()?);
+    let total = match total {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    (first <= last).then(|| (first..last + 1, total))
+}
+
+fn get_result(
+    location: &object_store::path::Path,
+    response: GetObjectOutput,
+) -> object_store::Result<object_store::GetResult> {
+    let last_modified = to_utc(response.last_modified(), "LastModified")?;
+    let size = response.content_length() as usize;
+    let mut attributes = object_attributes!(response);
+    if response.tag_count() > 0 {
+        attributes.insert(
+            Attribute::Metadata(TAGGING_COUNT_ATTRIBUTE.into()),
+            response.tag_count().to_string().into(),
+        );
+    }
+    // ranged responses' Content-Length is that of the range, the size of the
+    // object as a whole is only reported by Content-Range
+    let (range, size) = match response.content_range() {
+        Some(content_range) => {
+            let (range, total) =
+                parse_content_range(content_range).ok_or_else(|| Error::InvalidContentRange {
+                    content_range: content_range.to_string(),
+                })?;
+            (range, total.unwrap_or(size))
+        }
+        None => (0..size, size),
+    };
+    Ok(object_store::GetResult {
+        payload: GetResultPayload::Stream(Box::pin(response.body.map_err(|err| {
+            object_store::Error::Generic {
+                store: "aws_smithy",
+                source: Box::new(err),
+            }
+        }))),
+        meta: ObjectMeta {
+            location: location.to_string().into(),
+            last_modified,
+            size,
+            e_tag: response.e_tag,
+            version: response.version_id,
+        },
+        range,
+        attributes,
+    })
+}
+
+impl AmazonS3 {
+    /// Get (a range of) the object at `location` with a `GetObject` sent by
+    /// `client`, which may add headers the input lacks fields for
+    async fn get_object(
         &self,
+        client: &Client,
         location: &object_store::path::Path,
-    ) -> object_store::Result<object_store::ObjectMeta> {
-        let output = self
-            .client
+        options: object_store::GetOptions,
+    ) -> object_store::Result<object_store::GetResult> {
+        let request = client
+            .get_object()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(location.to_string());
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let ranged = options.range.is_some();
+        let request = conditional_request!(request, options);
+        let response = self
+            .send(|| request.clone().send())
+            .await
+            .map_err(|err| Error::from(err).with_path(location))?;
+        // the range is of the encoded bytes, which can't be decoded on their own
+        if let Some(encoding) = response
+            .content_encoding()
+            .filter(|encoding| ranged && !encoding.eq_ignore_ascii_case("identity"))
+        {
+            return Err(object_store::Error::NotSupported {
+                source: Box::new(Error::EncodedRange {
+                    key: location.to_string(),
+                    encoding: encoding.to_string(),
+                }),
+            });
+        }
+        let decompress =
+            self.decompress_by_extension && !ranged && response.content_encoding().is_none();
+        let mut result = get_result(location, response)?;
+        if decompress {
+            if let GetResultPayload::Stream(payload) = result.payload {
+                result.payload =
+                    GetResultPayload::Stream(decompress_by_extension(location, payload));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Get `range` of `location` if the object still matches `if_range`, or the
+    /// whole object if it has since changed
+    ///
+    /// The result's `range` covers the whole object in the latter case, so that
+    /// resuming a download of an object that changed in the meantime restarts
+    /// it, in a single request.
+    pub async fn get_if_range(
+        &self,
+        location: &object_store::path::Path,
+        range: GetRange,
+        if_range: IfRange,
+    ) -> object_store::Result<object_store::GetResult> {
+        let value = HeaderValue::from_str(&if_range.header_value()).map_err(|_| {
+            object_store::Error::Generic {
+                store: "S3",
+                source: format!("invalid If-Range {if_range:?}").into(),
+            }
+        })?;
+        // GetObject has no field for If-Range, so it's added as a header
+        let client = self.with_interceptor(Precondition(IF_RANGE, value));
+        let options = object_store::GetOptions {
+            range: Some(range),
+            ..Default::default()
+        };
+        self.get_object(&client, location, options).await
+    }
+}
+
+#[async_trait]
+impl ObjectStore for AmazonS3 {
+    async fn copy(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        if self.dry_run {
+            let action = format!("copied {from} to {to}");
+            return self.dry_run_action(action, Some(from)).await;
+        }
+        let request = self
+            .client
+            .copy_object()
+            .copy_source(self.copy_source(from, None))
+            .set_expected_source_bucket_owner(self.expected_bucket_owner.clone())
+            .set_acl(self.object_acl.clone())
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(to.to_string());
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let request = copy_source_sse_customer_key!(request, &self.sse_customer_key);
+        self.send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+    async fn copy_if_not_exists(
+        &self,
+        _from: &object_store::path::Path,
+        _to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        Err(object_store::Error::NotSupported {
+            source: Box::new(Error::Unknown),
+        })
+    }
+    /// Copies `from` to `to`, then deletes `from`
+    ///
+    /// S3 has no atomic rename: the source is only deleted once the copy has
+    /// succeeded and the destination is visible. Should that delete fail, both
+    /// objects exist and the returned error says as much.
+    async fn rename(
+        &self,
+        from: &object_store::path::Path,
+        to: &object_store::path::Path,
+    ) -> object_store::Result<()> {
+        if self.dry_run {
+            let action = format!("renamed {from} to {to}");
+            return self.dry_run_action(action, Some(from)).await;
+        }
+        self.copy(from, to).await?;
+        self.head(to).await?;
+        self.delete(from)
+            .await
+            .map_err(|source| Error::RenameSourceNotDeleted {
+                from: from.to_string(),
+                to: to.to_string(),
+                source,
+            })?;
+        Ok(())
+    }
+    /// Deletes `locations` in batches of up to 1000 keys per `DeleteObjects`
+    /// request, reporting the outcome for each key
+    ///
+    /// A batch whose request fails yields a single error in place of its keys.
+    fn delete_stream<'a>(
+        &'a self,
+        locations: BoxStream<'a, object_store::Result<object_store::path::Path>>,
+    ) -> BoxStream<'a, object_store::Result<object_store::path::Path>> {
+        locations
+            .try_chunks(MAX_DELETE_BATCH)
+            .map_err(|err| err.1)
+            .and_then(move |locations| async move {
+                Ok(stream::iter(self.delete_batch(locations).await?))
+            })
+            .try_flatten()
+            .boxed()
+    }
+    async fn delete(&self, location: &object_store::path::Path) -> object_store::Result<()> {
+        if self.dry_run {
+            return self
+                .dry_run_action(format!("deleted {location}"), None)
+                .await;
+        }
+        let request = self
+            .client
+            .delete_object()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(location.to_string());
+        self.send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+    async fn get_opts(
+        &self,
+        location: &object_store::path::Path,
+        options: object_store::GetOptions,
+    ) -> object_store::Result<object_store::GetResult> {
+        if options.head {
+            let request = self
+                .client
+                .head_object()
+                .bucket(self.bucket.clone())
+                .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+                .key(location.to_string());
+            let request = sse_customer_key!(request, &self.sse_customer_key);
+            let request = conditional_request!(request, options);
+            let output = self
+                .send(|| request.clone().send())
+                .await
+                .map_err(|err| Error::from(err).with_path(location))?;
+            let meta = head_meta(location, &output)?;
+            return Ok(object_store::GetResult {
+                payload: GetResultPayload::Stream(Box::pin(stream::empty())),
+                range: 0..meta.size,
+                meta,
+                attributes: object_attributes!(output),
+            });
+        }
+        self.get_object(&self.client, location, options).await
+    }
+    async fn head(
+        &self,
+        location: &object_store::path::Path,
+    ) -> object_store::Result<object_store::ObjectMeta> {
+        let request = self
+            .client
             .head_object()
             .set_bucket(Some(self.bucket.clone()))
-            .set_key(Some(location.to_string()))
-            .send()
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .set_key(Some(location.to_string()));
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let output = self
+            .send(|| request.clone().send())
             .await
-            .map_err(Error::from)?;
-        let last_modified = DateTime::from_timestamp_millis(
-            output
-                .last_modified()
-                .ok_or(Error::Unknown)?
-                .to_millis()
-                .map_err(Error::from)?,
-        )
-        .unwrap();
-        let meta = ObjectMeta {
-            location: location.clone(),
-            last_modified,
-            size: output.content_length() as usize,
-            e_tag: output.e_tag().map(|x| x.to_string()),
-            version: None,
-        };
-        Ok(meta)
+            .map_err(|err| Error::from(err).with_path(location))?;
+        head_meta(location, &output)
     }
     fn list(
         &self,
         prefix: Option<&object_store::path::Path>,
     ) -> BoxStream<'_, object_store::Result<object_store::ObjectMeta>> {
-        let request = self.client.list_objects_v2().bucket(self.bucket.clone());
-        let request = match prefix {
-            Some(prefix) => request.prefix(prefix.to_string()),
-            None => request,
-        };
-        Box::pin(
-            request
-                .send()
-                .map_err(|_| object_store::Error::from(Error::Unknown))
-                .and_then(|response| async {
-                    match response.contents {
-                        Some(contents) => {
-                            Ok(Box::pin(stream::iter(contents.into_iter().map(|object| {
-                                let last_modified = DateTime::from_timestamp_millis(
-                                    object
-                                        .last_modified()
-                                        .ok_or(Error::Unknown)?
-                                        .to_millis()
-                                        .map_err(Error::from)?,
-                                )
-                                .unwrap();
-                                Ok(ObjectMeta {
-                                    location: object
-                                        .key
-                                        .ok_or(object_store::Error::Generic {
-                                            store: "aws",
-                                            source: Box::new(Error::Unknown),
-                                        })?
-                                        .into(),
-                                    last_modified,
-                                    size: object.size as usize,
-                                    e_tag: object.e_tag,
-                                    version: None,
-                                })
-                            }))) as BoxStream<_>)
-                        }
-                        None => Ok(Box::pin(stream::empty()) as BoxStream<_>),
-                    }
-                })
-                .try_flatten_stream()
-                .into_stream(),
-        )
+        let prefix = prefix.cloned();
+        // `None` once the last page has been listed, otherwise the continuation
+        // token of the next page (`None` for the first). Pages are only
+        // requested once the previous page has been consumed.
+        let pages = stream::try_unfold(Some(None), move |token: Option<Option<String>>| {
+            let prefix = prefix.clone();
+            async move {
+                let token = match token {
+                    Some(token) => token,
+                    None => return Ok::<_, object_store::Error>(None),
+                };
+                let page = self.list_page(prefix.as_ref(), token).await?;
+                let next = match page.is_truncated {
+                    true => page.next_continuation_token.map(Some),
+                    false => None,
+                };
+                let objects = page.objects.into_iter().map(Ok::<_, object_store::Error>);
+                Ok(Some((stream::iter(objects), next)))
+            }
+        });
+        Box::pin(pages.try_flatten())
     }
 
     async fn list_with_delimiter(
         &self,
         prefix: Option<&object_store::path::Path>,
     ) -> object_store::Result<object_store::ListResult> {
-        let request = self.client.list_objects_v2().bucket(self.bucket.clone());
-        let request = match prefix {
-            Some(prefix) => request.prefix(prefix.to_string()),
-            None => request,
-        };
-        let response = request.send().await.map_err(Error::from)?;
-        let objects = match response.contents {
-            Some(contents) => contents
-                .into_iter()
-                .map(|object| {
-                    let last_modified = DateTime::from_timestamp_millis(
-                        object
-                            .last_modified()
-                            .ok_or(Error::Unknown)?
-                            .to_millis()
-                            .map_err(Error::from)?,
-                    )
-                    .unwrap();
-                    Ok(ObjectMeta {
-                        location: object
-                            .key
-                            .ok_or(object_store::Error::Generic {
-                                store: "aws",
-                                source: Box::new(Error::Unknown),
-                            })?
-                            .into(),
-                        last_modified,
-                        size: object.size as usize,
-                        e_tag: object.e_tag,
-                        version: None,
-                    })
-                })
-                .collect::<Result<Vec<_>, object_store::Error>>()?,
-            None => Vec::new(),
-        };
+        // S3 only rolls keys up into common prefixes with a delimiter, and the
+        // prefix must end with it to list the "directory" itself
+        let request = self
+            .client
+            .list_objects_v2()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .delimiter(object_store::path::DELIMITER)
+            .set_prefix(prefix.map(|prefix| format!("{prefix}{}", object_store::path::DELIMITER)));
+        let mut objects = Vec::new();
+        let mut common_prefixes = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let request = request
+                .clone()
+                .set_continuation_token(continuation_token.take());
+            let response = self
+                .send(|| request.clone().send())
+                .await
+                .map_err(Error::from)?;
+            for object in response.contents.unwrap_or_default() {
+                let meta = listed_meta(object)?;
+                if !self.is_prefix_marker(prefix, &meta.location) {
+                    objects.push(meta);
+                }
+            }
+            for common_prefix in response.common_prefixes.unwrap_or_default() {
+                let mut common_prefix = common_prefix
+                    .prefix
+                    .ok_or(Error::MissingField { field: "Prefix" })?;
+                // common prefixes end with the delimiter, paths don't
+                if common_prefix.ends_with(object_store::path::DELIMITER) {
+                    common_prefix.pop();
+                }
+                common_prefixes.push(key_path(common_prefix));
+            }
+            match response.next_continuation_token {
+                Some(token) if response.is_truncated => continuation_token = Some(token),
+                _ => break,
+            }
+        }
         Ok(ListResult {
             objects,
-            common_prefixes: response
-                .common_prefixes
-                .and_then(|prefixes| {
-                    prefixes
-                        .into_iter()
-                        .map(|x| x.prefix.map(|y| y.into()))
-                        .collect::<Option<Vec<_>>>()
-                })
-                .unwrap_or(Vec::new()),
+            common_prefixes,
         })
     }
     async fn put_opts(
@@ -304,16 +1429,71 @@ impl ObjectStore for AmazonS3 {
         opts: PutOptions,
     ) -> object_store::Result<PutResult> {
         let buf = bytes::Bytes::from(payload);
+        let size = buf.len() as u64;
+        if size > MAX_PUT_SIZE {
+            return Err(Error::EntityTooLarge { size }.into());
+        }
+        if self.dry_run {
+            let action = format!("put {size} bytes to {location}");
+            self.dry_run_action(action, None).await?;
+        }
+        let tagging = opts.tags.encoded();
+        // PutObject has no conditional fields, so conditional puts are sent by
+        // a client that adds the header
+        let client = match &opts.mode {
+            PutMode::Overwrite => (*self.client).clone(),
+            PutMode::Create => {
+                self.with_interceptor(Precondition(IF_NONE_MATCH, HeaderValue::from_static("*")))
+            }
+            PutMode::Update(version) => {
+                let e_tag = version.e_tag.as_deref().ok_or(Error::MissingETag)?;
+                let e_tag = HeaderValue::from_str(e_tag).map_err(|_| Error::InvalidETag {
+                    e_tag: e_tag.to_string(),
+                })?;
+                self.with_interceptor(Precondition(IF_MATCH, e_tag))
+            }
+        };
+        // the request body isn't `Clone`, so the request is rebuilt per attempt
         let result = self
-            .client
-            .put_object()
-            .bucket(self.bucket.clone())
-            .key(location.to_string())
-            .body(buf.into())
-            .tagging(opts.tags.encoded())
-            .send()
+            .send(|| {
+                let request = client
+                    .put_object()
+                    .bucket(self.bucket.clone())
+                    .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+                    .key(location.to_string())
+                    .body(buf.clone().into())
+                    .tagging(tagging)
+                    .set_acl(self.object_acl.clone());
+                let request = sse_customer_key!(request, &self.sse_customer_key);
+                apply_attributes!(request, opts.attributes).send()
+            })
             .await
-            .map_err(Error::from)?;
+            .map_err(|err| {
+                let status = err.raw_response().map(|res| res.status().as_u16());
+                let path = location.to_string();
+                match (err.code(), status, &opts.mode) {
+                    (Some("EntityTooLarge"), _, _) | (_, Some(413), _) => {
+                        Error::EntityTooLarge { size }.into()
+                    }
+                    (_, Some(412), PutMode::Create) => object_store::Error::AlreadyExists {
+                        path,
+                        source: Box::new(Error::from(err)),
+                    },
+                    // 409s are concurrent conditional writes to the same key
+                    (_, Some(412), _) | (_, Some(409), PutMode::Create | PutMode::Update(_)) => {
+                        object_store::Error::Precondition {
+                            path,
+                            source: Box::new(Error::from(err)),
+                        }
+                    }
+                    _ => Error::from(err).into(),
+                }
+            })?;
+        if self.verify_put_etag {
+            verify_put_etag(&buf, &result)?;
+        }
+        // S3 only returns a version id for buckets with versioning enabled,
+        // for unversioned (or suspended) buckets this is None
         Ok(PutResult {
             e_tag: result.e_tag,
             version: result.version_id,
@@ -321,38 +1501,47 @@ impl ObjectStore for AmazonS3 {
     }
     async fn put_multipart(
         &self,
-        _location: &object_store::path::Path,
+        location: &object_store::path::Path,
     ) -> object_store::Result<Box<dyn object_store::MultipartUpload>> {
-        Err(object_store::Error::NotImplemented)
-
-        // let response = self
-        //     .client
-        //     .create_multipart_upload()
-        //     .bucket(self.bucket.clone())
-        //     .key(location.to_string())
-        //     .send()
-        //     .await
-        //     .map_err(Error::from)?;
-
-        // let multipart_upload = Box::new(WriteMultiPart::new(
-        //     MultiPartUpload {
-        //         bucket: self.bucket.clone(),
-        //         location: location.to_string(),
-        //         upload_id: response.upload_id.clone().ok_or(Error::Unknown)?,
-        //         client: self.client.clone(),
-        //     },
-        //     16,
-        // ));
-
-        // Ok((response.upload_id.ok_or(Error::Unknown)?, multipart_upload))
+        self.put_multipart_opts(location, Default::default()).await
     }
 
     async fn put_multipart_opts(
         &self,
-        _location: &object_store::path::Path,
-        _opts: object_store::PutMultipartOpts,
+        location: &object_store::path::Path,
+        opts: object_store::PutMultipartOpts,
     ) -> object_store::Result<Box<dyn object_store::MultipartUpload>> {
-        Err(object_store::Error::NotImplemented)
+        if self.dry_run {
+            let action = format!("started a multipart upload to {location}");
+            self.dry_run_action(action, None).await?;
+        }
+        let request = self
+            .client
+            .create_multipart_upload()
+            .bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .key(location.to_string())
+            .tagging(opts.tags.encoded())
+            .set_acl(self.object_acl.clone())
+            .set_checksum_algorithm(
+                self.checksum_validation
+                    .then_some(ChecksumAlgorithm::Sha256),
+            );
+        let request = apply_attributes!(request, opts.attributes);
+        let request = sse_customer_key!(request, &self.sse_customer_key);
+        let response = self
+            .send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        Ok(Box::new(multipart::MultiPartUpload {
+            store: self.clone(),
+            location: location.to_string(),
+            upload_id: response
+                .upload_id
+                .ok_or(Error::MissingField { field: "UploadId" })?,
+            part_count: 0,
+            parts: Default::default(),
+        }))
     }
 }
 
@@ -361,3 +1550,1605 @@ impl Display for AmazonS3 {
         write!(f, "{:?}", self.client.config())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 0-9/100"),
+            Some((0..10, Some(100)))
+        );
+        assert_eq!(
+            parse_content_range("bytes 90-99/100"),
+            Some((90..100, Some(100)))
+        );
+        assert_eq!(parse_content_range("bytes 0-9/*"), Some((0..10, None)));
+        // an unsatisfied range reports the size alone, there's no range returned
+        assert_eq!(parse_content_range("bytes */100"), None);
+
+        for malformed in [
+            "",
+            "bytes",
+            "bytes 0-9",
+            "0-9/100",
+            "items 0-9/100",
+            "bytes 9-0/100",
+            "bytes -9/100",
+            "bytes 0-/100",
+            "bytes a-b/100",
+            "bytes 0-9/abc",
+        ] {
+            assert_eq!(parse_content_range(malformed), None, "{malformed}");
+        }
+    }
+
+    /// A builder of a store of `bucket` whose requests are answered by the
+    /// mocked client, without retries
+    fn mock_builder(bucket: &str) -> AmazonS3Builder {
+        AmazonS3Builder::new()
+            .access_key_id("access_key")
+            .secret_access_key("secret_key")
+            .region("us-west-2")
+            .bucket(bucket)
+            .with_retry(1, std::time::Duration::ZERO)
+    }
+
+    /// A store of `bucket` whose requests are answered by the mocked client,
+    /// without retries
+    fn mock_store(bucket: &str) -> AmazonS3 {
+        mock_builder(bucket).build().unwrap()
+    }
+
+    /// The store built by `builder`, and the operations of the requests it sends
+    fn observe(builder: AmazonS3Builder) -> (AmazonS3, Arc<Mutex<Vec<&'static str>>>) {
+        let operations = Arc::new(Mutex::new(Vec::new()));
+        let observed = operations.clone();
+        let store = builder
+            .with_observer(crate::telemetry::RequestObserver::new(move |event| {
+                observed.lock().unwrap().push(event.operation)
+            }))
+            .build()
+            .unwrap();
+        (store, operations)
+    }
+
+    /// A mocked store of `bucket`, and the operations of the requests it sends
+    fn observed_store(bucket: &str) -> (AmazonS3, Arc<Mutex<Vec<&'static str>>>) {
+        observe(mock_builder(bucket))
+    }
+
+    #[test]
+    fn test_listed_meta() {
+        use aws_sdk_s3::types::Object;
+
+        let last_modified = SmithyDateTime::from_secs(784_111_777);
+        let object = Object::builder()
+            .key("a/b.txt")
+            .size(3)
+            .e_tag("\"900150983cd24fb0d6963f7d28e17f72\"")
+            .last_modified(last_modified)
+            .build();
+        let meta = listed_meta(object).unwrap();
+        assert_eq!(meta.location.as_ref(), "a/b.txt");
+        assert_eq!(meta.size, 3);
+        assert_eq!(meta.last_modified.to_rfc3339(), "1994-11-06T08:49:37+00:00");
+
+        // keys are kept exactly, even where they aren't canonical paths
+        for key in ["folder/", "a//b", "a//", "100%.txt", "a b/c"] {
+            let object = Object::builder()
+                .key(key)
+                .last_modified(last_modified)
+                .build();
+            assert_eq!(listed_meta(object).unwrap().location.as_ref(), key);
+        }
+
+        // entries of truncated listings lack fields, which are errors
+        let missing_key = Object::builder().last_modified(last_modified).build();
+        let err = listed_meta(missing_key).unwrap_err();
+        assert!(err.to_string().contains("Key"), "{err}");
+        let missing_date = Object::builder().key("a/b.txt").build();
+        let err = listed_meta(missing_date).unwrap_err();
+        assert!(err.to_string().contains("LastModified"), "{err}");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_truncated_listing() {
+        let store = mock_store("mock-truncated-listing");
+        let listed = store.list(None).collect::<Vec<_>>().await;
+        assert!(listed.iter().any(Result::is_err), "{listed:?}");
+        assert!(store.list_with_delimiter(None).await.is_err());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_malformed_error() {
+        let store = mock_store("mock-malformed-error");
+        let location = object_store::path::Path::from("a.txt");
+        assert!(store.get(&location).await.is_err());
+        assert!(store.head(&location).await.is_err());
+        assert!(store.list_with_delimiter(None).await.is_err());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_s3_express_session() {
+        // the session is created ahead of the request, which is then signed with it
+        let store = mock_store("logs--usw2-az1--x-s3");
+        store.check_access().await.unwrap();
+        store.check_access().await.unwrap();
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_append() {
+        let store = mock_store("logs--usw2-az1--x-s3");
+        let location = object_store::path::Path::from("append/log.txt");
+        let append = |offset, data: &'static str| store.append(&location, offset, data.into());
+
+        assert_eq!(append(0, "abc").await.unwrap(), 3);
+        assert_eq!(append(3, "de").await.unwrap(), 5);
+        // a stale offset, as after a concurrent append
+        let err = append(3, "f").await.unwrap_err();
+        assert!(
+            matches!(err, object_store::Error::Precondition { .. }),
+            "{err}"
+        );
+        assert_eq!(append(5, "f").await.unwrap(), 6);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_get_if_range() {
+        let store = mock_store("mock-conditional-get");
+        let location = object_store::path::Path::from("a.txt");
+        let get = |e_tag: &str| {
+            let if_range = IfRange::ETag(e_tag.to_string());
+            store.get_if_range(&location, (1..3).into(), if_range)
+        };
+
+        // the object is unchanged, so only the range is returned
+        let result = get("\"v2\"").await.unwrap();
+        assert_eq!(result.range, 1..3);
+        assert_eq!(result.meta.size, 4);
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"bc");
+
+        // it has since changed, so it's returned whole
+        let result = get("\"v1\"").await.unwrap();
+        assert_eq!(result.range, 0..4);
+        assert_eq!(result.meta.e_tag.as_deref(), Some("\"v2\""));
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"abcd");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_put_multipart() {
+        let store = mock_store("mock-multipart");
+        let location = object_store::path::Path::from("multipart/large.bin");
+
+        let mut upload = store.put_multipart(&location).await.unwrap();
+        let part = vec![0_u8; crate::utils::MIN_PART_SIZE];
+        upload.put_part(part.clone().into()).await.unwrap();
+        upload.put_part(part.into()).await.unwrap();
+        upload.put_part(vec![1_u8; 1024].into()).await.unwrap();
+        let result = upload.complete().await.unwrap();
+        assert_eq!(result.e_tag.as_deref(), Some("\"mock-multipart-3\""));
+
+        // S3 can't complete uploads without parts, so a single empty part is sent
+        let mut upload = store.put_multipart(&location).await.unwrap();
+        let result = upload.complete().await.unwrap();
+        assert_eq!(result.e_tag.as_deref(), Some("\"mock-multipart-1\""));
+
+        // a part that's never awaited leaves a gap, caught before completing
+        let mut upload = store.put_multipart(&location).await.unwrap();
+        upload.put_part("a".into()).await.unwrap();
+        drop(upload.put_part("b".into()));
+        upload.put_part("c".into()).await.unwrap();
+        let err = upload.complete().await.unwrap_err();
+        assert!(err.to_string().contains("part 2 "), "{err}");
+        upload.abort().await.unwrap();
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_put_multipart_checksums() {
+        let checked_store = |bucket| {
+            AmazonS3Builder::new()
+                .access_key_id("access_key")
+                .secret_access_key("secret_key")
+                .region("us-west-2")
+                .bucket(bucket)
+                .with_retry(1, std::time::Duration::ZERO)
+                .with_checksum_validation(true)
+                .build()
+                .unwrap()
+        };
+        let location = object_store::path::Path::from("multipart/checked.bin");
+
+        // the mock only reports the checksums of parts sent with one, so these
+        // succeed only if each part's checksum is sent
+        let store = checked_store("mock-multipart");
+        let mut upload = store.put_multipart(&location).await.unwrap();
+        upload.put_part("a".into()).await.unwrap();
+        upload.put_part("b".into()).await.unwrap();
+        let result = upload.complete().await.unwrap();
+        assert_eq!(result.e_tag.as_deref(), Some("\"mock-multipart-2\""));
+
+        let store = checked_store("mock-corrupt-multipart");
+        let mut upload = store.put_multipart(&location).await.unwrap();
+        let err = upload.put_part("a".into()).await.unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("SHA256 checksum"), "{message}");
+        assert!(message.contains("for part 1"), "{message}");
+        upload.abort().await.unwrap();
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_list_pages() {
+        let (store, operations) = observed_store("mock-listing");
+        let locations = |objects: Vec<ObjectMeta>| {
+            objects
+                .into_iter()
+                .map(|meta| meta.location.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let listed = store.list(None).try_collect::<Vec<_>>().await.unwrap();
+        assert_eq!(locations(listed), ["a/b", "a/c", "d", "e/f/g", "h"]);
+        assert_eq!(*operations.lock().unwrap(), ["list"; 3]);
+
+        // pages are only requested once the previous one is consumed
+        operations.lock().unwrap().clear();
+        let listed = store.list(None).take(2).try_collect::<Vec<_>>().await;
+        assert_eq!(locations(listed.unwrap()), ["a/b", "a/c"]);
+        assert_eq!(*operations.lock().unwrap(), ["list"]);
+
+        let prefix = object_store::path::Path::from("a");
+        let page = store.list_page(Some(&prefix), None).await.unwrap();
+        assert_eq!(locations(page.objects), ["a/b", "a/c"]);
+        assert_eq!(page.key_count, 2);
+        assert!(!page.is_truncated);
+        assert_eq!(page.next_continuation_token, None);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_list_with_delimiter() {
+        let (store, operations) = observed_store("mock-listing");
+        let result = store.list_with_delimiter(None).await.unwrap();
+        let objects = result
+            .objects
+            .iter()
+            .map(|meta| meta.location.as_ref())
+            .collect::<Vec<_>>();
+        let common_prefixes = result
+            .common_prefixes
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<_>>();
+        assert_eq!(objects, ["d", "h"]);
+        assert_eq!(common_prefixes, ["a", "e"]);
+        // both accumulate across the listing's pages
+        assert_eq!(*operations.lock().unwrap(), ["list"; 2]);
+
+        let prefix = object_store::path::Path::from("e");
+        let result = store.list_with_delimiter(Some(&prefix)).await.unwrap();
+        assert!(result.objects.is_empty());
+        assert_eq!(
+            result.common_prefixes,
+            [object_store::path::Path::from("e/f")]
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_put_modes() {
+        let store = mock_store("mock-conditional-put");
+        let location = object_store::path::Path::from("conditional/a.txt");
+        let put =
+            |data: &'static str, mode: PutMode| store.put_opts(&location, data.into(), mode.into());
+        let update = |result: &PutResult| {
+            PutMode::Update(object_store::UpdateVersion {
+                e_tag: result.e_tag.clone(),
+                version: None,
+            })
+        };
+
+        let created = put("a", PutMode::Create).await.unwrap();
+        let err = put("b", PutMode::Create).await.unwrap_err();
+        assert!(
+            matches!(err, object_store::Error::AlreadyExists { .. }),
+            "{err}"
+        );
+
+        let updated = put("c", update(&created)).await.unwrap();
+        assert_ne!(updated.e_tag, created.e_tag);
+        let err = put("d", update(&created)).await.unwrap_err();
+        assert!(
+            matches!(err, object_store::Error::Precondition { .. }),
+            "{err}"
+        );
+
+        // overwrites are unconditional, leaving earlier versions stale
+        put("e", PutMode::Overwrite).await.unwrap();
+        assert!(put("f", update(&updated)).await.is_err());
+
+        // updates can't be made without an ETag to match
+        let mode = PutMode::Update(object_store::UpdateVersion {
+            e_tag: None,
+            version: None,
+        });
+        assert!(put("g", mode).await.is_err());
+    }
+
+    #[test]
+    fn test_delete_error() {
+        let error = |code: &str| {
+            S3Error::builder()
+                .key("a.txt")
+                .code(code)
+                .message("failed")
+                .build()
+        };
+        let location = || object_store::path::Path::from("a.txt");
+        assert!(matches!(
+            delete_error(location(), error("AccessDenied")),
+            object_store::Error::PermissionDenied { path, .. } if path == "a.txt"
+        ));
+        assert!(matches!(
+            delete_error(location(), error("NoSuchKey")),
+            object_store::Error::NotFound { path, .. } if path == "a.txt"
+        ));
+        let err = delete_error(location(), error("InternalError"));
+        assert!(
+            matches!(err, object_store::Error::Generic { store: STORE, .. }),
+            "{err}"
+        );
+        assert!(err.to_string().contains("InternalError"), "{err}");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_delete_stream() {
+        let (store, operations) = observed_store("mock-bulk-delete");
+        let locations = (0..1500)
+            .map(|i| match i {
+                42 | 1042 => format!("denied/{i}.txt"),
+                _ => format!("bulk/{i}.txt"),
+            })
+            .map(|location| Ok(object_store::path::Path::from(location)))
+            .collect::<Vec<_>>();
+        let results = store
+            .delete_stream(stream::iter(locations).boxed())
+            .collect::<Vec<_>>()
+            .await;
+
+        // one request per batch of 1000 keys
+        assert_eq!(*operations.lock().unwrap(), ["post"; 2]);
+        assert_eq!(results.len(), 1500);
+        for (i, result) in results.into_iter().enumerate() {
+            match i {
+                42 | 1042 => assert!(
+                    matches!(
+                        &result,
+                        Err(object_store::Error::PermissionDenied { path, .. })
+                            if *path == format!("denied/{i}.txt")
+                    ),
+                    "{result:?}"
+                ),
+                _ => assert_eq!(result.unwrap().to_string(), format!("bulk/{i}.txt")),
+            }
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_conditional_get() {
+        use chrono::TimeZone;
+        use object_store::GetOptions;
+
+        /// The outcomes of a head and a get with `options`
+        async fn get(options: GetOptions) -> [object_store::Result<()>; 2] {
+            let store = mock_store("mock-conditional-get");
+            let location = object_store::path::Path::from("a.txt");
+            let head = GetOptions {
+                head: true,
+                ..options.clone()
+            };
+            [
+                store.get_opts(&location, head).await.map(|_| ()),
+                store.get_opts(&location, options).await.map(|_| ()),
+            ]
+        }
+
+        // the object was last modified at the start of 2024
+        let before = Utc.with_ymd_and_hms(2023, 12, 31, 0, 0, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let e_tag = |e_tag: &str| Some(e_tag.to_string());
+
+        // each condition is sent as its own header, and only fails as it should
+        for options in [
+            GetOptions {
+                if_match: e_tag("\"v1\""),
+                ..Default::default()
+            },
+            GetOptions {
+                if_unmodified_since: Some(before),
+                ..Default::default()
+            },
+        ] {
+            for result in get(options).await {
+                assert!(
+                    matches!(result, Err(object_store::Error::Precondition { .. })),
+                    "{result:?}"
+                );
+            }
+        }
+        for options in [
+            GetOptions {
+                if_none_match: e_tag("\"v2\""),
+                ..Default::default()
+            },
+            GetOptions {
+                if_modified_since: Some(after),
+                ..Default::default()
+            },
+        ] {
+            for result in get(options).await {
+                assert!(
+                    matches!(result, Err(object_store::Error::NotModified { .. })),
+                    "{result:?}"
+                );
+            }
+        }
+        for options in [
+            GetOptions {
+                if_match: e_tag("\"v2\""),
+                ..Default::default()
+            },
+            GetOptions {
+                if_unmodified_since: Some(after),
+                ..Default::default()
+            },
+            GetOptions {
+                if_none_match: e_tag("\"v1\""),
+                ..Default::default()
+            },
+            GetOptions {
+                if_modified_since: Some(before),
+                ..Default::default()
+            },
+        ] {
+            for result in get(options).await {
+                result.unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_requires_directory_bucket() {
+        let store = mock_store("logs");
+        let location = object_store::path::Path::from("log.txt");
+        let err =
+            futures::executor::block_on(store.append(&location, 0, "abc".into())).unwrap_err();
+        assert!(
+            matches!(err, object_store::Error::NotSupported { .. }),
+            "{err}"
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_dry_run_tags() {
+        let builder = mock_builder("mock-conditional-get").with_dry_run(true);
+        let (store, operations) = observe(builder);
+        let location = object_store::path::Path::from("a.txt");
+
+        let err = store.put_tags(&location, [("k", "v")]).await.unwrap_err();
+        assert!(
+            err.to_string().contains("would have set 1 tags on a.txt"),
+            "{err}"
+        );
+        let err = store.delete_tags(&location).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("would have removed the tags of a.txt"),
+            "{err}"
+        );
+        // the bucket and object are checked, but no tagging request is sent
+        assert_eq!(*operations.lock().unwrap(), ["head"; 4]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_dry_run_restore() {
+        let builder = mock_builder("mock-conditional-get").with_dry_run(true);
+        let (store, operations) = observe(builder);
+        let location = object_store::path::Path::from("a.txt");
+
+        let err = store
+            .restore_object(&location, 2, Tier::Bulk)
+            .await
+            .unwrap_err();
+        let action = "would have restored a.txt for 2 days at tier Bulk";
+        assert!(err.to_string().contains(action), "{err}");
+        assert_eq!(*operations.lock().unwrap(), ["head"; 2]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_put_entity_too_large() {
+        use builder::script::{error, respond_with, response};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("a.bin");
+        // whether reported by code or only by status
+        respond_with([error(400, "EntityTooLarge"), response(413, &[], "")]);
+        for _ in 0..2 {
+            let err = store.put(&location, "abc".into()).await.unwrap_err();
+            assert!(
+                err.to_string().contains("use put_multipart instead"),
+                "{err}"
+            );
+        }
+        let requests = builder::script::take_requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.method() == http::Method::PUT));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_put_version() {
+        use builder::script::{respond_with, response};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("a.bin");
+        let head = |version: Option<&'static str>| {
+            let mut headers = vec![
+                ("content-length", "3"),
+                ("etag", "\"e\""),
+                ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ];
+            headers.extend(version.map(|version| ("x-amz-version-id", version)));
+            response(200, &headers, "")
+        };
+        respond_with([
+            response(200, &[("etag", "\"e\""), ("x-amz-version-id", "v1")], ""),
+            head(Some("v1")),
+            // an unversioned bucket reports no version id at all
+            response(200, &[("etag", "\"e\"")], ""),
+            head(None),
+        ]);
+        for version in [Some("v1"), None] {
+            let result = store.put(&location, "abc".into()).await.unwrap();
+            assert_eq!(result.version.as_deref(), version);
+            let meta = store.head(&location).await.unwrap();
+            assert_eq!(meta.version.as_deref(), version);
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_endpoint_port() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_builder("mock-scripted")
+            .endpoint("http://localhost:9000/")
+            .build()
+            .unwrap();
+        let location = object_store::path::Path::from("a.bin");
+        let object = [
+            ("content-length", "3"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+        ];
+        respond_with([
+            response(200, &[("etag", "\"e\"")], ""),
+            response(200, &object, "abc"),
+            response(
+                200,
+                &[],
+                "<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>",
+            ),
+        ]);
+        store.put(&location, "abc".into()).await.unwrap();
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(bytes.as_ref(), b"abc");
+        assert!(store
+            .list(None)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .is_empty());
+
+        let requests = take_requests();
+        assert_eq!(requests.len(), 3);
+        for request in requests {
+            let uri = request.uri();
+            assert_eq!(uri.authority().unwrap().as_str(), "localhost:9000", "{uri}");
+            // path-style, the bucket never displaces the host:port
+            assert!(uri.path().starts_with("/mock-scripted"), "{uri}");
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_force_credential_refresh() {
+        use aws_credential_types::{credential_fn::provide_credentials_fn, Credentials};
+        use builder::script::{error, respond_with, response, take_requests};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let location = object_store::path::Path::from("a.bin");
+        for force in [true, false] {
+            // every request is signed with a new access key
+            let provided = Arc::new(AtomicUsize::new(0));
+            let counter = provided.clone();
+            let provider = provide_credentials_fn(move || {
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(Credentials::from_keys(format!("key{n}"), "secret", None)) }
+            });
+            let store = AmazonS3Builder::new()
+                .region("us-west-2")
+                .bucket("mock-scripted")
+                .with_retry(1, std::time::Duration::ZERO)
+                .with_credentials_provider(provider)
+                .with_force_credential_refresh(force)
+                .build()
+                .unwrap();
+            respond_with([
+                error(400, "ExpiredToken"),
+                response(200, &[("etag", "\"e\"")], ""),
+            ]);
+            let result = store.put(&location, "abc".into()).await;
+            let requests = take_requests();
+            if force {
+                result.unwrap();
+                let signed_with_key0: Vec<_> = requests
+                    .iter()
+                    .map(|request| {
+                        let authorization = request.headers()["authorization"].to_str().unwrap();
+                        authorization.contains("Credential=key0/")
+                    })
+                    .collect();
+                // re-issued, signed with fresh credentials
+                assert_eq!(signed_with_key0, [true, false]);
+                assert_eq!(provided.load(Ordering::SeqCst), 2);
+            } else {
+                let err = result.unwrap_err();
+                assert!(
+                    err.to_string().contains("credentials have expired"),
+                    "{err}"
+                );
+                assert_eq!(requests.len(), 1);
+            }
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_rename_failures() {
+        use builder::script::{error, respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let (from, to) = (
+            object_store::path::Path::from("from.bin"),
+            object_store::path::Path::from("to.bin"),
+        );
+        let copied = || {
+            response(
+                200,
+                &[],
+                "<CopyObjectResult><ETag>\"e\"</ETag></CopyObjectResult>",
+            )
+        };
+        let head = || {
+            let object = [
+                ("content-length", "3"),
+                ("etag", "\"e\""),
+                ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ];
+            response(200, &object, "")
+        };
+        let methods = || -> Vec<_> {
+            take_requests()
+                .iter()
+                .map(|request| request.method().to_string())
+                .collect()
+        };
+
+        // a failed copy never deletes the source
+        respond_with([error(403, "AccessDenied")]);
+        store.rename(&from, &to).await.unwrap_err();
+        assert_eq!(methods(), ["PUT"]);
+        // nor does a copy that isn't visible at the destination
+        respond_with([copied(), response(404, &[], "")]);
+        let err = store.rename(&from, &to).await.unwrap_err();
+        assert!(matches!(err, object_store::Error::NotFound { .. }), "{err}");
+        assert_eq!(methods(), ["PUT", "HEAD"]);
+        // a failed delete leaves both objects, and says so
+        respond_with([copied(), head(), error(403, "AccessDenied")]);
+        let err = store.rename(&from, &to).await.unwrap_err();
+        assert!(
+            err.to_string().contains("failed to delete the source"),
+            "{err}"
+        );
+        assert_eq!(methods(), ["PUT", "HEAD", "DELETE"]);
+
+        respond_with([copied(), head(), response(204, &[], "")]);
+        store.rename(&from, &to).await.unwrap();
+        let requests = take_requests();
+        assert_eq!(
+            requests[0].headers()["x-amz-copy-source"],
+            "mock-scripted/from.bin"
+        );
+        assert_eq!(requests[2].uri().path(), "/mock-scripted/from.bin");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_retried_statuses() {
+        use builder::script::{error, respond_with, response, take_requests};
+
+        let store = mock_builder("mock-scripted")
+            .with_retry(3, std::time::Duration::ZERO)
+            .build()
+            .unwrap();
+        let location = object_store::path::Path::from("a.bin");
+        let put = || store.put(&location, "abc".into());
+
+        respond_with([error(400, "InvalidRequest")]);
+        put().await.unwrap_err();
+        assert_eq!(take_requests().len(), 1);
+
+        respond_with([
+            error(503, "SlowDown"),
+            error(503, "ServiceUnavailable"),
+            response(200, &[("etag", "\"e\"")], ""),
+        ]);
+        put().await.unwrap();
+        assert_eq!(take_requests().len(), 3);
+
+        // only the configured failures are retried
+        let policy = RetryPolicy {
+            retriable_statuses: vec![],
+            retriable_codes: vec![],
+            ..Default::default()
+        };
+        let store = mock_builder("mock-scripted")
+            .with_retry_policy(policy)
+            .with_retry(3, std::time::Duration::ZERO)
+            .build()
+            .unwrap();
+        respond_with([error(503, "SlowDown")]);
+        store.put(&location, "abc".into()).await.unwrap_err();
+        assert_eq!(take_requests().len(), 1);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_put_empty() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("empty.bin");
+        let marker = [
+            ("content-length", "0"),
+            ("etag", "\"d41d8cd98f00b204e9800998ecf8427e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+        ];
+        respond_with([
+            response(200, &[("etag", "\"d41d8cd98f00b204e9800998ecf8427e\"")], ""),
+            response(200, &marker, ""),
+        ]);
+        store
+            .put(&location, object_store::PutPayload::new())
+            .await
+            .unwrap();
+        assert_eq!(store.head(&location).await.unwrap().size, 0);
+
+        let requests = take_requests();
+        assert_eq!(requests[0].method(), http::Method::PUT);
+        assert_eq!(requests[0].uri().path(), "/mock-scripted/empty.bin");
+        assert_eq!(requests[0].headers()["content-length"], "0");
+        assert!(requests[0].body().is_empty());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_restore() {
+        use builder::script::{respond_with, response, take_requests};
+        use restore::{RestoreStatus, RESTORE_ATTRIBUTE};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("archived.bin");
+        let status = r#"ongoing-request="false", expiry-date="Fri, 21 Dec 2012 00:00:00 GMT""#;
+        let archived = [
+            ("content-length", "3"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ("x-amz-storage-class", "GLACIER"),
+            ("x-amz-restore", status),
+        ];
+        respond_with([response(200, &archived, ""), response(202, &[], "")]);
+
+        let options = object_store::GetOptions {
+            head: true,
+            ..Default::default()
+        };
+        let result = store.get_opts(&location, options).await.unwrap();
+        let attribute = Attribute::Metadata(RESTORE_ATTRIBUTE.into());
+        let restore = result.attributes.get(&attribute).unwrap();
+        assert!(!RestoreStatus::parse(restore).unwrap().ongoing);
+
+        store
+            .restore_object(&location, 2, Tier::Bulk)
+            .await
+            .unwrap();
+        let requests = take_requests();
+        let request = &requests[1];
+        assert_eq!(request.method(), http::Method::POST);
+        assert_eq!(request.uri().path(), "/mock-scripted/archived.bin");
+        assert!(request.uri().query().unwrap().contains("restore"));
+        let body = std::str::from_utf8(request.body()).unwrap();
+        assert!(body.contains("<Days>2</Days>"), "{body}");
+        assert!(body.contains("<Tier>Bulk</Tier>"), "{body}");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_access_point_arn() {
+        use builder::script::{respond_with, response};
+
+        let store = mock_store("arn:aws:s3:us-west-2:123456789012:accesspoint/mock-scripted");
+        let location = object_store::path::Path::from("a.csv");
+        let object = [
+            ("content-length", "3"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+        ];
+        respond_with([
+            response(200, &[("etag", "\"e\"")], ""),
+            response(200, &object, "abc"),
+            response(204, &[], ""),
+        ]);
+        store.put(&location, "abc".into()).await.unwrap();
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(bytes.as_ref(), b"abc");
+        store.delete(&location).await.unwrap();
+
+        let requests = builder::script::take_requests();
+        let methods: Vec<_> = requests.iter().map(|r| r.method().as_str()).collect();
+        assert_eq!(methods, ["PUT", "GET", "DELETE"]);
+        for request in &requests {
+            // addressed to the access point's own host, rather than path-style
+            let uri = request.uri();
+            assert_eq!(
+                uri.host(),
+                Some("mock-scripted-123456789012.s3-accesspoint.us-west-2.amazonaws.com")
+            );
+            assert_eq!(uri.path(), "/a.csv");
+        }
+    }
+
+    /// Only browsers reject fetches, the native mock can't
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    async fn test_cors_rejection() {
+        let store = mock_store("mock-scripted");
+        builder::script::reject();
+        let err = store
+            .put(&object_store::path::Path::from("a.txt"), "abc".into())
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Failed to fetch"), "{err}");
+        assert!(err.contains("CORS configuration"), "{err}");
+        assert!(err.contains("the PUT method"), "{err}");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_skip_prefix_marker() {
+        use builder::script::{respond_with, response};
+
+        let contents = |key: &str| {
+            format!(
+                "<Contents><Key>{key}</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified>\
+                 <ETag>&quot;e&quot;</ETag><Size>0</Size></Contents>"
+            )
+        };
+        let listing = || {
+            let body = format!(
+                "<ListBucketResult><IsTruncated>false</IsTruncated>{}{}</ListBucketResult>",
+                contents("folder/"),
+                contents("folder/a.txt"),
+            );
+            response(200, &[], &body)
+        };
+        let prefix = object_store::path::Path::from("folder");
+        let listed = |store: AmazonS3| {
+            let prefix = prefix.clone();
+            async move {
+                let list: Vec<_> = store.list(Some(&prefix)).try_collect().await.unwrap();
+                let delimited = store.list_with_delimiter(Some(&prefix)).await.unwrap();
+                [list, delimited.objects].map(|objects| {
+                    objects
+                        .into_iter()
+                        .map(|meta| meta.location.to_string())
+                        .collect::<Vec<_>>()
+                })
+            }
+        };
+
+        respond_with([listing(), listing()]);
+        let store = mock_builder("mock-scripted")
+            .with_skip_prefix_marker(true)
+            .build()
+            .unwrap();
+        assert_eq!(listed(store).await, [["folder/a.txt"], ["folder/a.txt"]]);
+
+        respond_with([listing(), listing()]);
+        let [list, delimited] = listed(mock_store("mock-scripted")).await;
+        assert_eq!(list, ["folder/", "folder/a.txt"]);
+        assert_eq!(delimited, ["folder/", "folder/a.txt"]);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_website_redirect() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("old.html");
+        let redirected = [
+            ("content-length", "0"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ("x-amz-website-redirect-location", "/new.html"),
+        ];
+        respond_with([
+            response(200, &[("etag", "\"e\"")], ""),
+            response(200, &redirected, ""),
+        ]);
+        let attribute = Attribute::Metadata(WEBSITE_REDIRECT_ATTRIBUTE.into());
+        let mut attributes = Attributes::new();
+        attributes.insert(attribute.clone(), "/new.html".into());
+        let options = PutOptions {
+            attributes,
+            ..Default::default()
+        };
+        store
+            .put_opts(&location, object_store::PutPayload::new(), options)
+            .await
+            .unwrap();
+        let head = object_store::GetOptions {
+            head: true,
+            ..Default::default()
+        };
+        let result = store.get_opts(&location, head).await.unwrap();
+        assert_eq!(
+            result.attributes.get(&attribute).unwrap().as_ref(),
+            "/new.html"
+        );
+
+        let requests = take_requests();
+        assert_eq!(
+            requests[0].headers()["x-amz-website-redirect-location"],
+            "/new.html"
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_get_part() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("a.bin");
+        // the second of three 4 byte parts
+        let part = [
+            ("content-length", "4"),
+            ("content-range", "bytes 4-7/12"),
+            ("etag", "\"e-3\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ("x-amz-mp-parts-count", "3"),
+        ];
+        respond_with([response(206, &part, "4567")]);
+        let result = store.get_part(&location, 2).await.unwrap();
+        assert_eq!(result.range, 4..8);
+        assert_eq!(result.meta.size, 12);
+        let parts = Attribute::Metadata(PARTS_COUNT_ATTRIBUTE.into());
+        assert_eq!(result.attributes.get(&parts).unwrap().as_ref(), "3");
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"4567");
+
+        let requests = take_requests();
+        let query = requests[0].uri().query().unwrap();
+        assert!(
+            query.split('&').any(|pair| pair == "partNumber=2"),
+            "{query}"
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_get_archived() {
+        use builder::script::{error, respond_with};
+
+        let store = mock_store("mock-scripted");
+        respond_with([error(403, "InvalidObjectState")]);
+        let err = store
+            .get(&object_store::path::Path::from("archived.bin"))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("restore it with AmazonS3::restore_object"),
+            "{err}"
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_sse_attributes() {
+        use builder::script::{respond_with, response};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("a.bin");
+        let key_id = "arn:aws:kms:us-west-2:123456789012:key/1234abcd";
+        let encrypted = [
+            ("content-length", "3"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ("x-amz-server-side-encryption", "aws:kms"),
+            ("x-amz-server-side-encryption-aws-kms-key-id", key_id),
+        ];
+        respond_with([
+            response(200, &encrypted, "abc"),
+            response(200, &encrypted, ""),
+        ]);
+        let head = object_store::GetOptions {
+            head: true,
+            ..Default::default()
+        };
+        for options in [Default::default(), head] {
+            let result = store.get_opts(&location, options).await.unwrap();
+            let attribute = |key: &'static str| {
+                let value = result.attributes.get(&Attribute::Metadata(key.into()));
+                value.map(|value| value.to_string())
+            };
+            assert_eq!(attribute(SSE_ATTRIBUTE).as_deref(), Some("aws:kms"));
+            assert_eq!(attribute(SSE_KMS_KEY_ID_ATTRIBUTE).as_deref(), Some(key_id));
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_copy_with_metadata() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("a.json");
+        let copied = "<CopyObjectResult><ETag>\"e\"</ETag></CopyObjectResult>";
+        respond_with([response(200, &[], copied), response(200, &[], copied)]);
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentType, "application/json".into());
+        attributes.insert(Attribute::Metadata("origin".into()), "upload".into());
+
+        // fixing the metadata of an object in place
+        store
+            .copy_with_metadata(&location, &location, &attributes)
+            .await
+            .unwrap();
+        store.copy(&location, &location).await.unwrap();
+
+        let requests = take_requests();
+        let headers = requests[0].headers();
+        assert_eq!(headers["x-amz-copy-source"], "mock-scripted/a.json");
+        assert_eq!(headers["x-amz-metadata-directive"], "REPLACE");
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers["x-amz-meta-origin"], "upload");
+        // a plain copy copies the source's metadata
+        assert!(!requests[1]
+            .headers()
+            .contains_key("x-amz-metadata-directive"));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_dualstack_endpoint() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let listing = "<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>";
+        for (dualstack, host) in [
+            (true, "s3.dualstack.us-west-2.amazonaws.com"),
+            (false, "s3.us-west-2.amazonaws.com"),
+        ] {
+            let store = mock_builder("mock-scripted")
+                .with_use_dualstack_endpoint(dualstack)
+                .build()
+                .unwrap();
+            respond_with([response(200, &[], listing)]);
+            store.list_with_delimiter(None).await.unwrap();
+            let requests = take_requests();
+            assert_eq!(requests[0].uri().host(), Some(host));
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_verify_put_etag() {
+        use builder::script::{respond_with, response};
+
+        let location = object_store::path::Path::from("a.txt");
+        let md5 = "\"900150983cd24fb0d6963f7d28e17f72\"";
+        let other = "\"00000000000000000000000000000000\"";
+        let put = |store: AmazonS3| {
+            let location = location.clone();
+            async move { store.put(&location, "abc".into()).await }
+        };
+        let verifying = || {
+            mock_builder("mock-scripted")
+                .with_verify_put_etag(true)
+                .build()
+                .unwrap()
+        };
+
+        respond_with([response(200, &[("etag", md5)], "")]);
+        put(verifying()).await.unwrap();
+        respond_with([response(200, &[("etag", other)], "")]);
+        let err = put(verifying()).await.unwrap_err();
+        assert!(
+            err.to_string().contains(&format!("expected {md5}")),
+            "{err}"
+        );
+        // SSE-KMS ETags aren't the MD5 of the object
+        let kms = [("etag", other), ("x-amz-server-side-encryption", "aws:kms")];
+        respond_with([response(200, &kms, "")]);
+        put(verifying()).await.unwrap();
+        // nor are they checked by default
+        respond_with([response(200, &[("etag", other)], "")]);
+        put(mock_store("mock-scripted")).await.unwrap();
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_expected_bucket_owner() {
+        use builder::script::{error, respond_with, response, take_requests};
+
+        let store = mock_builder("mock-scripted")
+            .with_expected_bucket_owner("123456789012")
+            .build()
+            .unwrap();
+        let location = object_store::path::Path::from("a.txt");
+        let listing = "<ListBucketResult><IsTruncated>false</IsTruncated></ListBucketResult>";
+        respond_with([
+            response(200, &[], listing),
+            response(204, &[], ""),
+            // owned by another account
+            error(403, "AccessDenied"),
+        ]);
+        store.list_with_delimiter(None).await.unwrap();
+        store.delete(&location).await.unwrap();
+        let err = store.get(&location).await.unwrap_err();
+        assert!(err.to_string().contains("expected bucket owner"), "{err}");
+
+        let requests = take_requests();
+        assert_eq!(requests.len(), 3);
+        for request in requests {
+            assert_eq!(
+                request.headers()["x-amz-expected-bucket-owner"],
+                "123456789012"
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_object_acl() {
+        use builder::script::{error, respond_with, response, take_requests};
+
+        let store = mock_builder("mock-scripted")
+            .with_object_acl("public-read")
+            .build()
+            .unwrap();
+        let (from, to) = (
+            object_store::path::Path::from("a.txt"),
+            object_store::path::Path::from("b.txt"),
+        );
+        let copied = "<CopyObjectResult><ETag>\"e\"</ETag></CopyObjectResult>";
+        respond_with([
+            response(200, &[("etag", "\"e\"")], ""),
+            response(200, &[], copied),
+            error(400, "AccessControlListNotSupported"),
+        ]);
+        store.put(&from, "abc".into()).await.unwrap();
+        store.copy(&from, &to).await.unwrap();
+        // the bucket enforces its owner's ownership of objects
+        let err = store.put(&from, "abc".into()).await.unwrap_err();
+        assert!(err.to_string().contains("ACLs disabled"), "{err}");
+
+        let requests = take_requests();
+        assert_eq!(requests.len(), 3);
+        for request in requests {
+            assert_eq!(request.headers()["x-amz-acl"], "public-read");
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_decompress_by_extension() {
+        use async_compression::futures::bufread::GzipEncoder;
+        use builder::script::respond_with;
+
+        let mut gzipped = Vec::new();
+        GzipEncoder::new(&b"hello"[..])
+            .read_to_end(&mut gzipped)
+            .await
+            .unwrap();
+        // stored without a Content-Encoding
+        let response = || {
+            http::Response::builder()
+                .header("content-length", gzipped.len())
+                .header("etag", "\"e\"")
+                .header("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT")
+                .body(Bytes::from(gzipped.clone()))
+                .unwrap()
+        };
+        let location = object_store::path::Path::from("a.txt.gz");
+
+        respond_with([response()]);
+        let store = mock_builder("mock-scripted")
+            .with_decompress_by_extension(true)
+            .build()
+            .unwrap();
+        let result = store.get(&location).await.unwrap();
+        assert_eq!(result.meta.size, gzipped.len());
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"hello");
+
+        // off by default
+        respond_with([response()]);
+        let store = mock_store("mock-scripted");
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(bytes.as_ref(), gzipped);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_content_language() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("index.html");
+        let localized = [
+            ("content-length", "0"),
+            ("content-language", "de-CH"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+        ];
+        respond_with([
+            response(200, &[("etag", "\"e\"")], ""),
+            response(200, &localized, ""),
+        ]);
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentLanguage, "de-CH".into());
+        let options = PutOptions {
+            attributes,
+            ..Default::default()
+        };
+        store
+            .put_opts(&location, object_store::PutPayload::new(), options)
+            .await
+            .unwrap();
+        let head = object_store::GetOptions {
+            head: true,
+            ..Default::default()
+        };
+        let result = store.get_opts(&location, head).await.unwrap();
+        assert_eq!(
+            result
+                .attributes
+                .get(&Attribute::ContentLanguage)
+                .unwrap()
+                .as_ref(),
+            "de-CH"
+        );
+
+        let requests = take_requests();
+        assert_eq!(requests[0].headers()["content-language"], "de-CH");
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_sse_customer_key() {
+        use builder::script::{error, respond_with, response, take_requests};
+
+        // the base64 encoding of "0123456789abcdef0123456789abcdef"
+        let key = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+        assert!(mock_builder("mock-scripted")
+            .with_sse_customer_key("c2hvcnQ=")
+            .build()
+            .is_err());
+        let store = mock_builder("mock-scripted")
+            .with_sse_customer_key(key)
+            .build()
+            .unwrap();
+        assert!(!format!("{store:?}").contains(key));
+        let location = object_store::path::Path::from("a.bin");
+        let object = [
+            ("content-length", "3"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+        ];
+        respond_with([
+            response(200, &[("etag", "\"e\"")], ""),
+            response(200, &object, "abc"),
+            error(403, "AccessDenied"),
+        ]);
+        store.put(&location, "abc".into()).await.unwrap();
+        let result = store.get(&location).await.unwrap();
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"abc");
+        let err = store.get(&location).await.unwrap_err();
+        assert!(!err.to_string().contains(key), "{err}");
+        assert!(!format!("{err:?}").contains(key), "{err:?}");
+
+        let requests = take_requests();
+        for request in &requests[..2] {
+            let headers = request.headers();
+            assert_eq!(
+                headers["x-amz-server-side-encryption-customer-algorithm"],
+                "AES256"
+            );
+            assert_eq!(headers["x-amz-server-side-encryption-customer-key"], key);
+            assert_eq!(
+                headers["x-amz-server-side-encryption-customer-key-md5"],
+                "hRasmdxgYDKV3nvbahU1MA=="
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_delete_version() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("a.bin");
+        let remaining = [
+            ("content-length", "3"),
+            ("etag", "\"e2\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            ("x-amz-version-id", "v2"),
+        ];
+        respond_with([
+            response(204, &[], ""),
+            response(200, &remaining, "abc"),
+            response(
+                200,
+                &[],
+                "<CopyObjectResult><ETag>\"e\"</ETag></CopyObjectResult>",
+            ),
+        ]);
+        store.delete_version(&location, "v1").await.unwrap();
+        // the other version remains readable
+        let options = object_store::GetOptions {
+            version: Some("v2".into()),
+            ..Default::default()
+        };
+        let result = store.get_opts(&location, options).await.unwrap();
+        assert_eq!(result.meta.version.as_deref(), Some("v2"));
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"abc");
+        let restored = object_store::path::Path::from("b.bin");
+        store
+            .copy_version(&location, "v2", &restored)
+            .await
+            .unwrap();
+
+        let requests = take_requests();
+        assert_eq!(requests[0].method(), http::Method::DELETE);
+        let query = |request: &http::Request<Bytes>| {
+            let query = request.uri().query().unwrap_or_default().to_string();
+            query.split('&').map(String::from).collect::<Vec<_>>()
+        };
+        assert!(query(&requests[0]).contains(&"versionId=v1".to_string()));
+        assert!(query(&requests[1]).contains(&"versionId=v2".to_string()));
+        assert_eq!(
+            requests[2].headers()["x-amz-copy-source"],
+            "mock-scripted/a.bin?versionId=v2"
+        );
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_replication_status() {
+        use builder::script::{respond_with, response};
+
+        let store = mock_store("mock-scripted");
+        let location = object_store::path::Path::from("a.bin");
+        let replicated = |status| {
+            let headers = [
+                ("content-length", "3"),
+                ("etag", "\"e\""),
+                ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+                ("x-amz-replication-status", status),
+            ];
+            response(200, &headers, "abc")
+        };
+        respond_with([
+            replicated("COMPLETED"),
+            replicated("PENDING"),
+            response(
+                200,
+                &[
+                    ("content-length", "3"),
+                    ("etag", "\"e\""),
+                    ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+                ],
+                "abc",
+            ),
+        ]);
+        let attribute = Attribute::Metadata(REPLICATION_STATUS_ATTRIBUTE.into());
+        let result = store.get(&location).await.unwrap();
+        assert_eq!(
+            result.attributes.get(&attribute).unwrap().as_ref(),
+            "COMPLETED"
+        );
+        let head = object_store::GetOptions {
+            head: true,
+            ..Default::default()
+        };
+        let result = store.get_opts(&location, head.clone()).await.unwrap();
+        assert_eq!(
+            result.attributes.get(&attribute).unwrap().as_ref(),
+            "PENDING"
+        );
+        // objects that aren't replicated have no status
+        let result = store.get_opts(&location, head).await.unwrap();
+        assert!(result.attributes.get(&attribute).is_none());
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_skip_signature() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let anonymous = || {
+            AmazonS3Builder::new()
+                .region("us-west-2")
+                .bucket("mock-scripted")
+        };
+        // credentials are required, unless requests are unsigned
+        assert!(anonymous().build().is_err());
+        let store = anonymous()
+            .with_config("skip_signature".parse().unwrap(), "true")
+            .build()
+            .unwrap();
+        let object = [
+            ("content-length", "3"),
+            ("etag", "\"e\""),
+            ("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+        ];
+        respond_with([response(200, &object, "abc")]);
+        let location = object_store::path::Path::from("a.bin");
+        let result = store.get(&location).await.unwrap();
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"abc");
+
+        let requests = take_requests();
+        assert_eq!(requests.len(), 1);
+        assert!(!requests[0].headers().contains_key("authorization"));
+        assert!(!requests[0].headers().contains_key("x-amz-security-token"));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_copy_with_tags() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let store = mock_store("mock-scripted");
+        let from = object_store::path::Path::from("a.bin");
+        let to = object_store::path::Path::from("b.bin");
+        let copied = "<CopyObjectResult><ETag>\"e\"</ETag></CopyObjectResult>";
+        respond_with([response(200, &[], copied), response(200, &[], copied)]);
+        let mut tags = TagSet::default();
+        tags.push("stage", "archived");
+        tags.push("team", "data");
+
+        store.copy_with_tags(&from, &to, &tags).await.unwrap();
+        store.copy(&from, &to).await.unwrap();
+
+        let requests = take_requests();
+        let headers = requests[0].headers();
+        assert_eq!(headers["x-amz-tagging-directive"], "REPLACE");
+        assert_eq!(headers["x-amz-tagging"], "stage=archived&team=data");
+        // a plain copy keeps the source's tags
+        let headers = requests[1].headers();
+        assert!(!headers.contains_key("x-amz-tagging-directive"));
+        assert!(!headers.contains_key("x-amz-tagging"));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_unsigned_payload() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let location = object_store::path::Path::from("a.txt");
+        // the hex SHA256 of "abc"
+        let hashed = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        for (unsigned_payload, expected) in [(false, hashed), (true, "UNSIGNED-PAYLOAD")] {
+            let store = mock_builder("mock-scripted")
+                .with_unsigned_payload(unsigned_payload)
+                .build()
+                .unwrap();
+            respond_with([response(200, &[("etag", "\"e\"")], "")]);
+            store.put(&location, "abc".into()).await.unwrap();
+
+            let requests = take_requests();
+            let headers = requests[0].headers();
+            assert_eq!(headers["x-amz-content-sha256"], expected);
+            // the signature covers the header
+            let authorization = headers["authorization"].to_str().unwrap();
+            assert!(
+                authorization.contains("x-amz-content-sha256"),
+                "{authorization}"
+            );
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_retry_deadline() {
+        use builder::script::{error, respond_with, take_requests};
+
+        // a minute's backoff would outlast the second's deadline
+        let store = mock_builder("mock-scripted")
+            .with_retry(10, std::time::Duration::from_secs(60))
+            .with_retry_deadline(std::time::Duration::from_secs(1))
+            .build()
+            .unwrap();
+        respond_with([error(503, "SlowDown")]);
+        let location = object_store::path::Path::from("a.bin");
+        let err = store.get(&location).await.unwrap_err();
+        assert!(format!("{err:?}").contains("DeadlineExceeded"), "{err:?}");
+        // given up on at once, with retries remaining
+        assert_eq!(take_requests().len(), 1);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), tokio::test)]
+    async fn test_fetch_owner() {
+        use builder::script::{respond_with, response, take_requests};
+
+        let listing = "<ListBucketResult><IsTruncated>false</IsTruncated>\
+                       <Contents><Key>a.txt</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified>\
+                       <ETag>&quot;e&quot;</ETag><Size>3</Size>\
+                       <Owner><ID>owner-id</ID><DisplayName>owner</DisplayName></Owner></Contents>\
+                       </ListBucketResult>";
+        let store = mock_builder("mock-scripted")
+            .with_fetch_owner(true)
+            .build()
+            .unwrap();
+        respond_with([response(200, &[], listing)]);
+        let page = store.list_page(None, None).await.unwrap();
+        let location = object_store::path::Path::from("a.txt");
+        assert_eq!(page.objects.len(), 1);
+        let owner = &page.owners[&location];
+        assert_eq!(owner.id.as_deref(), Some("owner-id"));
+        assert_eq!(owner.display_name.as_deref(), Some("owner"));
+        let query = take_requests()[0].uri().query().unwrap().to_string();
+        assert!(
+            query.split('&').any(|pair| pair == "fetch-owner=true"),
+            "{query}"
+        );
+
+        // not requested by default
+        let store = mock_store("mock-scripted");
+        respond_with([response(200, &[], listing)]);
+        store.list_page(None, None).await.unwrap();
+        let query = take_requests()[0].uri().query().unwrap().to_string();
+        assert!(!query.contains("fetch-owner"), "{query}");
+    }
+}
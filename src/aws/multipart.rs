@@ -1,67 +1,271 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use aws_sdk_s3::{
     primitives::ByteStream,
     types::{CompletedMultipartUpload, CompletedPart},
-    Client,
 };
-use object_store::multipart::PartId;
+use aws_smithy_types::base64;
+use bytes::Bytes;
+use object_store::{MultipartUpload, PutPayload, PutResult, UploadPart};
+use sha2::{Digest, Sha256};
 
-use crate::aws::error::Error;
+use crate::aws::{error::Error, AmazonS3, SSE_CUSTOMER_ALGORITHM};
 
+/// An S3 multipart upload, created by [`AmazonS3::put_multipart_opts`]
+#[derive(Debug)]
 pub(crate) struct MultiPartUpload {
-    pub(crate) bucket: String,
+    pub(crate) store: AmazonS3,
     pub(crate) location: String,
     pub(crate) upload_id: String,
-    pub(crate) client: Arc<Client>,
+    /// The number of parts started so far, part numbers are 1-based
+    pub(crate) part_count: i32,
+    /// Parts that have been uploaded, in order of completion
+    pub(crate) parts: Arc<Mutex<Vec<CompletedPart>>>,
 }
 
-// #[async_trait]
-// impl PutPart for MultiPartUpload {
-//     async fn put_part(&self, buf: Vec<u8>, part_idx: usize) -> Result<PartId, object_store::Error> {
-//         let part = part_idx + 1;
-
-//         let response = self
-//             .client
-//             .upload_part()
-//             .bucket(&self.bucket)
-//             .key(&self.location)
-//             .upload_id(&self.upload_id)
-//             .part_number(part as i32)
-//             .body(ByteStream::from(buf))
-//             .send()
-//             .await
-//             .map_err(Error::from)?;
-
-//         Ok(PartId {
-//             content_id: response
-//                 .e_tag()
-//                 .map(|x| x.to_string())
-//                 .ok_or(Error::Unknown)?,
-//         })
-//     }
-
-//     async fn complete(&self, completed_parts: Vec<PartId>) -> Result<(), object_store::Error> {
-//         let upload = CompletedMultipartUpload::builder().set_parts(Some(
-//             completed_parts
-//                 .into_iter()
-//                 .map(|x| {
-//                     Ok(CompletedPart::builder()
-//                         .part_number(x.content_id.parse()?)
-//                         .build())
-//                 })
-//                 .collect::<Result<Vec<_>, Error>>()?,
-//         ));
-//         self.client
-//             .complete_multipart_upload()
-//             .bucket(&self.bucket)
-//             .key(&self.location)
-//             .upload_id(&self.upload_id)
-//             .multipart_upload(upload.build())
-//             .send()
-//             .await
-//             .map_err(Error::from)?;
-//         Ok(())
-//     }
-// }
+#[async_trait]
+impl MultipartUpload for MultiPartUpload {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        self.part_count += 1;
+        let part_number = self.part_count;
+        let store = self.store.clone();
+        let location = self.location.clone();
+        let upload_id = self.upload_id.clone();
+        let parts = self.parts.clone();
+        Box::pin(async move {
+            let sse = &store.sse_customer_key;
+            let buf = Bytes::from(data);
+            let checksum = store
+                .checksum_validation
+                .then(|| base64::encode(Sha256::digest(&buf)));
+            let response = store
+                .send(|| {
+                    store
+                        .client
+                        .upload_part()
+                        .bucket(store.bucket.clone())
+                        .set_expected_bucket_owner(store.expected_bucket_owner.clone())
+                        .key(location.clone())
+                        .upload_id(upload_id.clone())
+                        .part_number(part_number)
+                        .body(ByteStream::from(buf.clone()))
+                        .set_sse_customer_algorithm(
+                            sse.as_ref().map(|_| SSE_CUSTOMER_ALGORITHM.to_string()),
+                        )
+                        .set_sse_customer_key(sse.as_ref().map(|sse| sse.key.clone()))
+                        .set_sse_customer_key_md5(sse.as_ref().map(|sse| sse.key_md5.clone()))
+                        .set_checksum_sha256(checksum.clone())
+                        .send()
+                })
+                .await
+                .map_err(Error::from)?;
+            let e_tag = response
+                .e_tag()
+                .ok_or(Error::MissingField { field: "ETag" })?;
+            if let Some(expected) = &checksum {
+                verify_checksum(
+                    format!("part {part_number}"),
+                    expected,
+                    response.checksum_sha256(),
+                )?;
+            }
+            let part = CompletedPart::builder()
+                .part_number(part_number)
+                .e_tag(e_tag)
+                .set_checksum_sha256(checksum)
+                .build();
+            parts.lock().unwrap().push(part);
+            Ok(())
+        })
+    }
+
+    async fn complete(&mut self) -> object_store::Result<PutResult> {
+        // S3 can't complete an upload without parts, so empty uploads consist of
+        // a single empty part
+        if self.part_count == 0 {
+            self.put_part(PutPayload::default()).await?;
+        }
+        let parts = self.parts.lock().unwrap().clone();
+        let parts = ordered_parts(parts, self.part_count)?;
+        let checksum = match self.store.checksum_validation {
+            true => Some(composite_checksum(&parts)?),
+            false => None,
+        };
+        let upload = CompletedMultipartUpload::builder()
+            .set_parts(Some(parts))
+            .build();
+        let request = self
+            .store
+            .client
+            .complete_multipart_upload()
+            .bucket(self.store.bucket.clone())
+            .set_expected_bucket_owner(self.store.expected_bucket_owner.clone())
+            .key(self.location.clone())
+            .upload_id(self.upload_id.clone())
+            .multipart_upload(upload);
+        let response = self
+            .store
+            .send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        if let Some(expected) = &checksum {
+            let actual = response.checksum_sha256();
+            verify_checksum(self.location.clone(), expected, actual)?;
+        }
+        Ok(PutResult {
+            e_tag: response.e_tag,
+            version: response.version_id,
+        })
+    }
+
+    async fn abort(&mut self) -> object_store::Result<()> {
+        let request = self
+            .store
+            .client
+            .abort_multipart_upload()
+            .bucket(self.store.bucket.clone())
+            .set_expected_bucket_owner(self.store.expected_bucket_owner.clone())
+            .key(self.location.clone())
+            .upload_id(self.upload_id.clone());
+        self.store
+            .send(|| request.clone().send())
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+}
+
+/// Sort `parts` by number, checking they're the contiguous `1..=part_count`
+///
+/// Parts whose upload failed, or was never awaited, leave gaps S3 would reject.
+fn ordered_parts(
+    mut parts: Vec<CompletedPart>,
+    part_count: i32,
+) -> object_store::Result<Vec<CompletedPart>> {
+    parts.sort_by_key(CompletedPart::part_number);
+    let mut numbers = parts.iter().map(CompletedPart::part_number);
+    match (1..=part_count).find(|n| numbers.next() != Some(*n)) {
+        Some(part_number) => Err(Error::MissingPart {
+            part_number,
+            part_count,
+        }
+        .into()),
+        None => Ok(parts),
+    }
+}
+
+/// The checksum S3 reports for a completed multipart upload: the SHA256 of the
+/// concatenated (binary) SHA256 checksums of its parts, suffixed with
+/// `-<part count>`
+fn composite_checksum(parts: &[CompletedPart]) -> object_store::Result<String> {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        let checksum = part
+            .checksum_sha256()
+            .and_then(|checksum| base64::decode(checksum).ok())
+            .ok_or(Error::MissingField {
+                field: "ChecksumSHA256",
+            })?;
+        hasher.update(checksum);
+    }
+    Ok(format!(
+        "{}-{}",
+        base64::encode(hasher.finalize()),
+        parts.len()
+    ))
+}
+
+/// Check the SHA256 checksum S3 reports for `what` is the one expected
+fn verify_checksum(what: String, expected: &str, actual: Option<&str>) -> object_store::Result<()> {
+    match actual {
+        Some(actual) if actual == expected => Ok(()),
+        actual => Err(Error::ChecksumMismatch {
+            what,
+            expected: expected.to_string(),
+            actual: actual.unwrap_or("none").to_string(),
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(part_number: i32) -> CompletedPart {
+        CompletedPart::builder()
+            .part_number(part_number)
+            .e_tag(format!("\"{part_number}\""))
+            .build()
+    }
+
+    #[test]
+    fn test_ordered_parts() {
+        let numbers = |parts: Vec<CompletedPart>| {
+            parts
+                .iter()
+                .map(CompletedPart::part_number)
+                .collect::<Vec<_>>()
+        };
+        // parts complete out of order
+        let parts = ordered_parts(vec![part(3), part(1), part(2)], 3).unwrap();
+        assert_eq!(numbers(parts), [1, 2, 3]);
+        assert!(ordered_parts(vec![], 0).unwrap().is_empty());
+
+        for (parts, part_count, missing) in [
+            (vec![part(3), part(1)], 3, 2),
+            (vec![part(1), part(2)], 3, 3),
+            (vec![part(2)], 2, 1),
+            (vec![], 1, 1),
+        ] {
+            let err = ordered_parts(parts, part_count).unwrap_err();
+            assert!(
+                err.to_string().contains(&format!("part {missing} ")),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_composite_checksum() {
+        let with_checksum = |part_number, checksum: &str| {
+            CompletedPart::builder()
+                .part_number(part_number)
+                .checksum_sha256(checksum)
+                .build()
+        };
+        // the SHA256 checksums of `a` and `b`
+        let parts = [
+            with_checksum(1, "ypeBEsobvcr6wjGzmiPcTaeG7/gUfE5yuYB3ha/uSLs="),
+            with_checksum(2, "PiPoFgA5WUoziU9lZOGxNIu9egCI1CxKy3PurtWcAJ0="),
+        ];
+        assert_eq!(
+            composite_checksum(&parts).unwrap(),
+            "5aAf7hTg7VxIcU8iGA8lrYNltT+XefedxKPX6Tlj+Uo=-2"
+        );
+        // a single part's is the checksum of its checksum, not the checksum itself
+        assert_eq!(
+            composite_checksum(&parts[..1]).unwrap(),
+            "v106/7c+/S7Gw2rTES3ZM+/tY8Thy//PqI4nWcFE8tg=-1"
+        );
+
+        // parts uploaded without checksum validation can't be combined
+        let err = composite_checksum(&[part(1)]).unwrap_err();
+        assert!(err.to_string().contains("ChecksumSHA256"), "{err}");
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        assert!(verify_checksum("part 1".to_string(), "abc=", Some("abc=")).is_ok());
+
+        let err = verify_checksum("part 1".to_string(), "abc=", Some("def=")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Generic S3 error: S3 returned SHA256 checksum def= for part 1, expected abc="
+        );
+        // S3 omits the checksum of parts sent without one
+        let err = verify_checksum("a.txt".to_string(), "abc=", None).unwrap_err();
+        assert!(err.to_string().contains("checksum none for a.txt"), "{err}");
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+
+/// The [`Attribute::Metadata`](object_store::Attribute::Metadata) key under
+/// which the raw `x-amz-restore` header of an archived object is reported
+pub const RESTORE_ATTRIBUTE: &str = "x-amz-restore";
+
+/// The restore status of an object in an archival storage class (e.g. Glacier)
+///
+/// Parsed from the `x-amz-restore` header, e.g.
+/// `ongoing-request="false", expiry-date="Fri, 21 Dec 2012 00:00:00 GMT"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreStatus {
+    /// Whether a restore is still in progress
+    pub ongoing: bool,
+    /// When the restored copy expires, once the restore has completed
+    pub expiry: Option<DateTime<Utc>>,
+}
+
+impl RestoreStatus {
+    /// Parse the value of an `x-amz-restore` header, returning `None` if it is malformed
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut ongoing = None;
+        let mut expiry = None;
+        let mut rest = header.trim();
+        while !rest.is_empty() {
+            let (key, value) = rest.split_once('=')?;
+            let value = value.trim_start().strip_prefix('"')?;
+            let (value, remainder) = value.split_once('"')?;
+            match key.trim() {
+                "ongoing-request" => ongoing = Some(value.parse::<bool>().ok()?),
+                "expiry-date" => {
+                    expiry = Some(
+                        DateTime::parse_from_rfc2822(value)
+                            .ok()?
+                            .with_timezone(&Utc),
+                    )
+                }
+                _ => {}
+            }
+            rest = remainder.trim_start().trim_start_matches(',').trim_start();
+        }
+        Some(Self {
+            ongoing: ongoing?,
+            expiry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse() {
+        let ongoing = RestoreStatus::parse(r#"ongoing-request="true""#).unwrap();
+        assert!(ongoing.ongoing);
+        assert_eq!(ongoing.expiry, None);
+
+        let restored = RestoreStatus::parse(
+            r#"ongoing-request="false", expiry-date="Fri, 21 Dec 2012 00:00:00 GMT""#,
+        )
+        .unwrap();
+        assert!(!restored.ongoing);
+        assert_eq!(
+            restored.expiry,
+            Some(Utc.with_ymd_and_hms(2012, 12, 21, 0, 0, 0).unwrap())
+        );
+
+        for malformed in [
+            "",
+            "ongoing-request",
+            r#"ongoing-request="maybe""#,
+            r#"ongoing-request="false", expiry-date="tomorrow""#,
+            r#"expiry-date="Fri, 21 Dec 2012 00:00:00 GMT""#,
+        ] {
+            assert_eq!(RestoreStatus::parse(malformed), None, "{malformed}");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use aws_sdk_s3::{
+    error::{ProvideErrorMetadata, SdkError},
+    primitives::SdkBody,
+};
+
+/// Decides which failed S3 requests are worth re-issuing
+///
+/// By default server errors (`500`, `502`, `503`, `504`), throttling (`429`,
+/// `SlowDown`), `RequestTimeout` and connection failures are retried, while
+/// other client errors (`4xx`) never are, as repeating them can't succeed.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the initial attempt
+    pub max_retries: usize,
+    /// The delay before the first retry, doubled for every subsequent retry
+    pub initial_backoff: Duration,
+    /// The longest delay between retries, however many have been made
+    pub max_backoff: Duration,
+    /// The longest a request may take overall, across every attempt and the
+    /// delays between them, however many retries remain
+    pub deadline: Option<Duration>,
+    /// HTTP status codes that are retried
+    pub retriable_statuses: Vec<u16>,
+    /// S3 error codes that are retried, regardless of status
+    pub retriable_codes: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(20),
+            deadline: None,
+            retriable_statuses: vec![429, 500, 502, 503, 504],
+            retriable_codes: [
+                "SlowDown",
+                "RequestTimeout",
+                "InternalError",
+                "ServiceUnavailable",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn is_retriable<E: ProvideErrorMetadata>(
+        &self,
+        err: &SdkError<E, http::Response<SdkBody>>,
+    ) -> bool {
+        match err {
+            SdkError::DispatchFailure(_) | SdkError::TimeoutError(_) => true,
+            SdkError::ConstructionFailure(_) => false,
+            _ => {
+                let code_retriable = matches!(
+                    err.code(),
+                    Some(code) if self.retriable_codes.iter().any(|c| c == code)
+                );
+                let status_retriable = matches!(
+                    err.raw_response(),
+                    Some(res) if self.retriable_statuses.contains(&res.status().as_u16())
+                );
+                code_retriable || status_retriable
+            }
+        }
+    }
+
+    /// The delay before retry number `retry` (starting from 0)
+    pub(crate) fn backoff(&self, retry: usize) -> Duration {
+        let factor = 2u32.saturating_pow(retry as u32);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::operation::get_object::GetObjectError;
+    use aws_smithy_types::error::ErrorMetadata;
+
+    type GetObjectSdkError = SdkError<GetObjectError, http::Response<SdkBody>>;
+
+    fn service_error(status: u16, code: &str) -> GetObjectSdkError {
+        let raw = http::Response::builder()
+            .status(status)
+            .body(SdkBody::empty())
+            .unwrap();
+        let err = GetObjectError::generic(ErrorMetadata::builder().code(code).build());
+        SdkError::service_error(err, raw)
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let backoffs = (0..6)
+            .map(|retry| policy.backoff(retry))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            backoffs,
+            [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis)
+        );
+        // however many retries, the factor saturates rather than overflowing
+        assert_eq!(policy.backoff(64), policy.max_backoff);
+        assert_eq!(policy.backoff(usize::MAX), policy.max_backoff);
+
+        let policy = RetryPolicy {
+            initial_backoff: Duration::MAX,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), policy.max_backoff);
+    }
+
+    #[test]
+    fn test_is_retriable() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retriable(&GetObjectSdkError::timeout_error("timed out")));
+        assert!(!policy.is_retriable(&GetObjectSdkError::construction_failure("invalid")));
+        assert!(policy.is_retriable(&service_error(503, "ServiceUnavailable")));
+        // S3 reports throttling and timeouts as 400s, the codes are retried regardless
+        assert!(policy.is_retriable(&service_error(400, "RequestTimeout")));
+        assert!(policy.is_retriable(&service_error(429, "TooManyRequests")));
+        assert!(!policy.is_retriable(&service_error(403, "AccessDenied")));
+        assert!(!policy.is_retriable(&service_error(404, "NoSuchKey")));
+
+        let policy = RetryPolicy {
+            retriable_statuses: vec![],
+            retriable_codes: vec![],
+            ..Default::default()
+        };
+        assert!(!policy.is_retriable(&service_error(503, "ServiceUnavailable")));
+    }
+}
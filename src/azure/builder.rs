@@ -0,0 +1,352 @@
+use std::str::FromStr;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt, Snafu};
+use url::Url;
+
+use crate::azure::{credential::AzureCredential, AzureClient, MicrosoftAzure};
+
+#[derive(Debug, Snafu)]
+#[allow(missing_docs)]
+enum ConfigError {
+    #[snafu(display("Configuration key: '{}' is not known.", key))]
+    UnknownConfigurationKey { key: String },
+
+    #[snafu(display("Missing account name"))]
+    MissingAccount,
+
+    #[snafu(display("Missing container name"))]
+    MissingContainer,
+
+    #[snafu(display("Configure either an access key or a SAS token, not both"))]
+    ConflictingCredentials,
+
+    #[snafu(display("Invalid access key, expected base64: {}", source))]
+    InvalidAccessKey { source: base64::DecodeError },
+
+    #[snafu(display("Unable parse source url. Url: {}, Error: {}", url, source))]
+    UnableToParseUrl {
+        source: url::ParseError,
+        url: String,
+    },
+
+    #[snafu(display("URL did not match any known pattern for scheme: {}", url))]
+    UrlNotRecognised { url: String },
+
+    #[snafu(display(
+        "Unknown url scheme cannot be parsed into storage location: {}",
+        scheme
+    ))]
+    UnknownUrlScheme { scheme: String },
+}
+
+impl From<ConfigError> for object_store::Error {
+    fn from(source: ConfigError) -> Self {
+        match source {
+            ConfigError::UnknownConfigurationKey { key } => Self::UnknownConfigurationKey {
+                store: AzureClient::STORE,
+                key,
+            },
+            _ => Self::Generic {
+                store: AzureClient::STORE,
+                source: Box::new(source),
+            },
+        }
+    }
+}
+
+/// Configuration keys of a [`MicrosoftAzureBuilder`], e.g. as passed to
+/// [`parse_url_opts`](crate::parse::parse_url_opts)
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Copy, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum AzureConfigKey {
+    /// The name of the storage account
+    AccountName,
+    /// The (base64) access key of the storage account, requests being signed
+    /// with it
+    AccessKey,
+    /// A shared access signature, the query string of a SAS URL
+    SasToken,
+    /// The name of the container
+    ContainerName,
+    /// The Blob service endpoint, `https://<account>.blob.core.windows.net` by
+    /// default, e.g. for Azurite
+    Endpoint,
+}
+
+impl AsRef<str> for AzureConfigKey {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::AccountName => "azure_storage_account_name",
+            Self::AccessKey => "azure_storage_account_key",
+            Self::SasToken => "azure_storage_sas_token",
+            Self::ContainerName => "azure_container_name",
+            Self::Endpoint => "azure_storage_endpoint",
+        }
+    }
+}
+
+impl FromStr for AzureConfigKey {
+    type Err = object_store::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "azure_storage_account_name" | "account_name" => Ok(Self::AccountName),
+            "azure_storage_account_key" | "azure_storage_access_key" | "access_key" => {
+                Ok(Self::AccessKey)
+            }
+            "azure_storage_sas_token" | "azure_storage_sas_key" | "sas_token" | "sas_key" => {
+                Ok(Self::SasToken)
+            }
+            "azure_container_name" | "container_name" => Ok(Self::ContainerName),
+            "azure_storage_endpoint" | "azure_endpoint" | "endpoint" => Ok(Self::Endpoint),
+            _ => Err(ConfigError::UnknownConfigurationKey { key: s.into() }.into()),
+        }
+    }
+}
+
+/// Configure a [`MicrosoftAzure`] store
+///
+/// Without an access key or SAS token, requests are made anonymously, which
+/// only containers allowing public read access accept.
+#[derive(Default)]
+pub struct MicrosoftAzureBuilder {
+    url: Option<String>,
+    account_name: Option<String>,
+    access_key: Option<String>,
+    sas_token: Option<String>,
+    container_name: Option<String>,
+    endpoint: Option<String>,
+}
+
+impl MicrosoftAzureBuilder {
+    pub fn new() -> MicrosoftAzureBuilder {
+        Self::default()
+    }
+
+    /// Set the account and container from a URL
+    ///
+    /// Supported formats:
+    /// - `az://<container>/<path>` (also `adl://`, `azure://`)
+    /// - `abfs[s]://<container>@<account>.dfs.core.windows.net/<path>`
+    /// - `https://<account>.blob.core.windows.net/<container>/<path>` (also
+    ///   `dfs.core.windows.net`)
+    ///
+    /// The account of `az://` URLs is configured separately.
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the name of the storage account
+    pub fn with_account(mut self, account: impl Into<String>) -> Self {
+        self.account_name = Some(account.into());
+        self
+    }
+
+    /// Set the name of the container
+    pub fn with_container_name(mut self, container: impl Into<String>) -> Self {
+        self.container_name = Some(container.into());
+        self
+    }
+
+    /// Sign requests with the (base64) access key of the storage account
+    pub fn with_access_key(mut self, access_key: impl Into<String>) -> Self {
+        self.access_key = Some(access_key.into());
+        self
+    }
+
+    /// Authorize requests with a shared access signature, the query string of
+    /// a SAS URL (with or without the leading `?`)
+    pub fn with_sas_token(mut self, sas_token: impl Into<String>) -> Self {
+        self.sas_token = Some(sas_token.into());
+        self
+    }
+
+    /// Set the Blob service endpoint, e.g. `http://127.0.0.1:10000/devstoreaccount1`
+    /// for Azurite
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Set an option on the builder via a key - value pair.
+    pub fn with_config(mut self, key: AzureConfigKey, value: impl Into<String>) -> Self {
+        match key {
+            AzureConfigKey::AccountName => self.account_name = Some(value.into()),
+            AzureConfigKey::AccessKey => self.access_key = Some(value.into()),
+            AzureConfigKey::SasToken => self.sas_token = Some(value.into()),
+            AzureConfigKey::ContainerName => self.container_name = Some(value.into()),
+            AzureConfigKey::Endpoint => self.endpoint = Some(value.into()),
+        };
+        self
+    }
+
+    fn parse_url(&mut self, url: &str) -> object_store::Result<()> {
+        let parsed = Url::parse(url).context(UnableToParseUrlSnafu { url })?;
+        let host = parsed.host_str().context(UrlNotRecognisedSnafu { url })?;
+        let account_of = |host: &str| {
+            let (account, rest) = host.split_once('.')?;
+            matches!(rest, "blob.core.windows.net" | "dfs.core.windows.net")
+                .then(|| account.to_string())
+        };
+        match parsed.scheme() {
+            "az" | "adl" | "azure" => self.container_name = Some(host.to_string()),
+            "abfs" | "abfss" => {
+                // `<container>@<account>.dfs.core.windows.net`
+                let account = account_of(host).context(UrlNotRecognisedSnafu { url })?;
+                self.account_name = Some(account);
+                self.container_name =
+                    Some(parsed.username().to_string()).filter(|container| !container.is_empty());
+            }
+            "https" => {
+                let account = account_of(host).context(UrlNotRecognisedSnafu { url })?;
+                self.account_name = Some(account);
+                let container = parsed.path_segments().into_iter().flatten().next();
+                if let Some(container) = container.filter(|container| !container.is_empty()) {
+                    self.container_name = Some(container.into());
+                }
+            }
+            scheme => return Err(UnknownUrlSchemeSnafu { scheme }.build().into()),
+        }
+        Ok(())
+    }
+
+    pub fn build(mut self) -> Result<MicrosoftAzure, object_store::Error> {
+        if let Some(url) = self.url.take() {
+            self.parse_url(&url)?;
+        }
+        let account = self.account_name.context(MissingAccountSnafu)?;
+        let container = self.container_name.context(MissingContainerSnafu)?;
+        let endpoint = self
+            .endpoint
+            .unwrap_or_else(|| format!("https://{account}.blob.core.windows.net"));
+        let endpoint = Url::parse(&endpoint).context(UnableToParseUrlSnafu { url: endpoint })?;
+        let credential = match (self.access_key, self.sas_token) {
+            (Some(_), Some(_)) => return Err(ConfigError::ConflictingCredentials.into()),
+            (Some(access_key), None) => {
+                let key = BASE64_STANDARD
+                    .decode(access_key.trim())
+                    .context(InvalidAccessKeySnafu)?;
+                AzureCredential::AccessKey(key)
+            }
+            (None, Some(sas_token)) => {
+                let query = sas_token.trim().trim_start_matches('?');
+                AzureCredential::SasToken(
+                    url::form_urlencoded::parse(query.as_bytes())
+                        .into_owned()
+                        .collect(),
+                )
+            }
+            (None, None) => AzureCredential::Anonymous,
+        };
+        Ok(MicrosoftAzure::new(AzureClient::new(
+            account, container, endpoint, credential,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(url: &str) -> MicrosoftAzureBuilder {
+        let mut builder = MicrosoftAzureBuilder::new();
+        builder.parse_url(url).unwrap();
+        builder
+    }
+
+    #[test]
+    fn test_parse_url() {
+        for (url, account, container) in [
+            ("az://container/path/file.parquet", None, Some("container")),
+            ("azure://container", None, Some("container")),
+            (
+                "abfss://container@acct.dfs.core.windows.net/path/file.parquet",
+                Some("acct"),
+                Some("container"),
+            ),
+            ("abfs://acct.dfs.core.windows.net/path", Some("acct"), None),
+            (
+                "https://acct.blob.core.windows.net/container/path/file.parquet",
+                Some("acct"),
+                Some("container"),
+            ),
+            (
+                "https://acct.dfs.core.windows.net/container",
+                Some("acct"),
+                Some("container"),
+            ),
+            ("https://acct.blob.core.windows.net/", Some("acct"), None),
+        ] {
+            let builder = parsed(url);
+            assert_eq!(builder.account_name.as_deref(), account, "{url}");
+            assert_eq!(builder.container_name.as_deref(), container, "{url}");
+        }
+
+        for url in [
+            "https://example.com/container",
+            "https://acct.queue.core.windows.net/container",
+            "abfss://container@acct.example.com/path",
+            "s3://bucket/path",
+            "not a url",
+        ] {
+            let mut builder = MicrosoftAzureBuilder::new();
+            assert!(builder.parse_url(url).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let builder = || MicrosoftAzureBuilder::new().with_url("az://container");
+        assert!(builder().with_account("acct").build().is_ok());
+        // the account of az:// URLs is configured separately
+        assert!(builder().build().is_err());
+        assert!(MicrosoftAzureBuilder::new()
+            .with_account("acct")
+            .build()
+            .is_err());
+
+        let key = BASE64_STANDARD.encode("key");
+        assert!(builder()
+            .with_account("acct")
+            .with_access_key(&key)
+            .build()
+            .is_ok());
+        assert!(builder()
+            .with_account("acct")
+            .with_access_key("not base64!")
+            .build()
+            .is_err());
+        assert!(builder()
+            .with_account("acct")
+            .with_access_key(&key)
+            .with_sas_token("?sv=2022-11-02&sig=abc")
+            .build()
+            .is_err());
+        assert!(builder()
+            .with_account("acct")
+            .with_config(AzureConfigKey::Endpoint, "not a url")
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_keys() {
+        for key in [
+            AzureConfigKey::AccountName,
+            AzureConfigKey::AccessKey,
+            AzureConfigKey::SasToken,
+            AzureConfigKey::ContainerName,
+            AzureConfigKey::Endpoint,
+        ] {
+            assert_eq!(AzureConfigKey::from_str(key.as_ref()).unwrap(), key);
+        }
+        assert_eq!(
+            AzureConfigKey::from_str("sas_key").unwrap(),
+            AzureConfigKey::SasToken
+        );
+        assert!(AzureConfigKey::from_str("azure_unknown").is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Authorization of Blob service requests, with a shared key or a SAS token
+
+use std::fmt::Debug;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{
+        HeaderName, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_TYPE,
+        IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE, RANGE,
+    },
+    Request,
+};
+use sha2::Sha256;
+
+use crate::utils::HTTP_DATE_FORMAT;
+
+/// The version of the Blob service REST API requests are made against
+const AZURE_VERSION: HeaderValue = HeaderValue::from_static("2023-11-03");
+const MS_DATE: HeaderName = HeaderName::from_static("x-ms-date");
+const MS_VERSION: HeaderName = HeaderName::from_static("x-ms-version");
+const CONTENT_MD5: HeaderName = HeaderName::from_static("content-md5");
+
+/// The credential requests are authorized with
+pub(crate) enum AzureCredential {
+    /// The (decoded) account key, requests being signed with it
+    /// (`Authorization: SharedKey`)
+    AccessKey(Vec<u8>),
+    /// The query parameters of a shared access signature, appended to requests
+    SasToken(Vec<(String, String)>),
+    /// No credential, for containers that allow public read access
+    Anonymous,
+}
+
+impl Debug for AzureCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AccessKey(_) => f.write_str("AccessKey(<redacted>)"),
+            Self::SasToken(_) => f.write_str("SasToken(<redacted>)"),
+            Self::Anonymous => f.write_str("Anonymous"),
+        }
+    }
+}
+
+impl AzureCredential {
+    /// Authorize `request`, to a container of `account`
+    ///
+    /// Signed requests must be sent promptly, Azure rejects those whose
+    /// `x-ms-date` is more than 15 minutes out.
+    pub(crate) fn authorize(&self, request: &mut Request, account: &str) {
+        request.headers_mut().insert(MS_VERSION, AZURE_VERSION);
+        match self {
+            Self::AccessKey(key) => {
+                let date = Utc::now().format(HTTP_DATE_FORMAT).to_string();
+                // the date is ASCII, so always a valid header value
+                let date = HeaderValue::from_str(&date).unwrap();
+                request.headers_mut().insert(MS_DATE, date);
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                mac.update(string_to_sign(request, account).as_bytes());
+                let signature = BASE64_STANDARD.encode(mac.finalize().into_bytes());
+                let authorization = format!("SharedKey {account}:{signature}");
+                if let Ok(mut authorization) = HeaderValue::from_str(&authorization) {
+                    authorization.set_sensitive(true);
+                    request.headers_mut().insert(AUTHORIZATION, authorization);
+                }
+            }
+            Self::SasToken(pairs) => {
+                request.url_mut().query_pairs_mut().extend_pairs(pairs);
+            }
+            Self::Anonymous => {}
+        }
+    }
+}
+
+/// The string signed by a shared key, see
+/// <https://learn.microsoft.com/rest/api/storageservices/authorize-with-shared-key>
+fn string_to_sign(request: &Request, account: &str) -> String {
+    let header = |name: &HeaderName| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+    // an empty body is signed with an empty Content-Length, rather than 0
+    let content_length = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(<[u8]>::len)
+        .filter(|len| *len > 0)
+        .map(|len| len.to_string())
+        .unwrap_or_default();
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n\n{}\n{}\n{}\n{}\n{}\n{}{}",
+        request.method().as_str(),
+        header(&CONTENT_ENCODING),
+        header(&CONTENT_LANGUAGE),
+        content_length,
+        header(&CONTENT_MD5),
+        header(&CONTENT_TYPE),
+        // the Date line is left empty in favour of x-ms-date, which browsers
+        // (unlike Date) let requests set
+        header(&IF_MODIFIED_SINCE),
+        header(&IF_MATCH),
+        header(&IF_NONE_MATCH),
+        header(&IF_UNMODIFIED_SINCE),
+        header(&RANGE),
+        canonicalized_headers(request),
+        canonicalized_resource(request, account),
+    )
+}
+
+/// The `x-ms-*` headers of `request`, sorted by name, one `name:value` per line
+fn canonicalized_headers(request: &Request) -> String {
+    let mut headers = request
+        .headers()
+        .iter()
+        .filter(|(name, _)| name.as_str().starts_with("x-ms-"))
+        .map(|(name, value)| {
+            let value = value.to_str().unwrap_or_default().trim();
+            format!("{}:{}\n", name.as_str(), value)
+        })
+        .collect::<Vec<_>>();
+    headers.sort_unstable();
+    headers.concat()
+}
+
+/// The account and (encoded) path of `request`, followed by its query
+/// parameters, sorted by name, one `name:value` per line
+fn canonicalized_resource(request: &Request, account: &str) -> String {
+    let mut resource = format!("/{}{}", account, request.url().path());
+    let mut params: Vec<(String, Vec<String>)> = Vec::new();
+    for (name, value) in request.url().query_pairs() {
+        let name = name.to_lowercase();
+        match params.iter_mut().find(|(param, _)| *param == name) {
+            Some((_, values)) => values.push(value.into_owned()),
+            None => params.push((name, vec![value.into_owned()])),
+        }
+    }
+    params.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, mut values) in params {
+        values.sort_unstable();
+        resource.push_str(&format!("\n{}:{}", name, values.join(",")));
+    }
+    resource
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::{Method, Url};
+
+    fn request(method: Method, url: &str) -> Request {
+        Request::new(method, Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_canonicalized_resource() {
+        // the examples of the Shared Key documentation
+        for (url, expected) in [
+            (
+                "https://myaccount.blob.core.windows.net/mycontainer?restype=container&comp=metadata",
+                "/myaccount/mycontainer\ncomp:metadata\nrestype:container",
+            ),
+            (
+                "https://myaccount.blob.core.windows.net/mycontainer?restype=container&comp=list\
+                 &include=snapshots&include=metadata&include=uncommittedblobs",
+                "/myaccount/mycontainer\ncomp:list\ninclude:metadata,snapshots,uncommittedblobs\
+                 \nrestype:container",
+            ),
+            (
+                "https://myaccount.blob.core.windows.net/mycontainer/a%20b/c.txt",
+                "/myaccount/mycontainer/a%20b/c.txt",
+            ),
+            // the emulator's path names the account too
+            (
+                "http://127.0.0.1:10000/myaccount/mycontainer/c.txt?Comp=List",
+                "/myaccount/myaccount/mycontainer/c.txt\ncomp:List",
+            ),
+        ] {
+            let request = request(Method::GET, url);
+            assert_eq!(canonicalized_resource(&request, "myaccount"), expected);
+        }
+    }
+
+    #[test]
+    fn test_string_to_sign() {
+        let mut put = request(
+            Method::PUT,
+            "https://myaccount.blob.core.windows.net/mycontainer/hello.txt",
+        );
+        let headers = put.headers_mut();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=UTF-8"),
+        );
+        headers.insert(
+            MS_DATE,
+            HeaderValue::from_static("Sun, 20 Sep 2009 20:36:40 GMT"),
+        );
+        headers.insert(MS_VERSION, HeaderValue::from_static("2015-02-21"));
+        headers.insert("x-ms-blob-type", HeaderValue::from_static("BlockBlob"));
+        headers.insert("x-ms-meta-m1", HeaderValue::from_static(" v1 "));
+        *put.body_mut() = Some("Hello World".into());
+        assert_eq!(
+            string_to_sign(&put, "myaccount"),
+            "PUT\n\n\n11\n\ntext/plain; charset=UTF-8\n\n\n\n\n\n\n\
+             x-ms-blob-type:BlockBlob\n\
+             x-ms-date:Sun, 20 Sep 2009 20:36:40 GMT\n\
+             x-ms-meta-m1:v1\n\
+             x-ms-version:2015-02-21\n\
+             /myaccount/mycontainer/hello.txt"
+        );
+
+        // an empty body is signed without a Content-Length
+        let mut get = request(
+            Method::GET,
+            "https://myaccount.blob.core.windows.net/mycontainer/hello.txt",
+        );
+        let headers = get.headers_mut();
+        headers.insert(IF_MATCH, HeaderValue::from_static("\"0x8D\""));
+        headers.insert(RANGE, HeaderValue::from_static("bytes=0-9"));
+        *get.body_mut() = Some(Vec::new().into());
+        assert_eq!(
+            string_to_sign(&get, "myaccount"),
+            "GET\n\n\n\n\n\n\n\n\"0x8D\"\n\n\nbytes=0-9\n/myaccount/mycontainer/hello.txt"
+        );
+    }
+
+    #[test]
+    fn test_authorize() {
+        let url = "https://myaccount.blob.core.windows.net/mycontainer/hello.txt";
+
+        let mut signed = request(Method::GET, url);
+        AzureCredential::AccessKey(b"key".to_vec()).authorize(&mut signed, "myaccount");
+        let headers = signed.headers();
+        assert_eq!(headers[MS_VERSION], AZURE_VERSION);
+        assert!(headers.contains_key(MS_DATE));
+        let authorization = headers[AUTHORIZATION].to_str().unwrap();
+        let signature = authorization.strip_prefix("SharedKey myaccount:").unwrap();
+        assert_eq!(BASE64_STANDARD.decode(signature).unwrap().len(), 32);
+
+        let mut sas = request(Method::GET, url);
+        let pairs = vec![
+            ("sv".to_string(), "2022-11-02".to_string()),
+            ("sig".to_string(), "a/b+c=".to_string()),
+        ];
+        AzureCredential::SasToken(pairs).authorize(&mut sas, "myaccount");
+        assert_eq!(sas.url().query(), Some("sv=2022-11-02&sig=a%2Fb%2Bc%3D"));
+        assert!(!sas.headers().contains_key(AUTHORIZATION));
+
+        let mut anonymous = request(Method::GET, url);
+        AzureCredential::Anonymous.authorize(&mut anonymous, "myaccount");
+        assert_eq!(anonymous.url().query(), None);
+        assert!(!anonymous.headers().contains_key(AUTHORIZATION));
+    }
+}
@@ -0,0 +1,232 @@
+//! Parsing of `List Blobs` responses
+
+use object_store::{path::Path, ObjectMeta};
+use snafu::{ResultExt, Snafu};
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+use crate::utils::{parse_http_date, unescape_xml};
+
+#[derive(Debug, Snafu)]
+pub(crate) enum ListError {
+    #[snafu(display("Invalid List Blobs XML: {}", source))]
+    InvalidXml { source: xmlparser::Error },
+
+    #[snafu(display("Invalid location '{}' in List Blobs response: {}", name, source))]
+    InvalidLocation {
+        name: String,
+        source: object_store::path::Error,
+    },
+
+    #[snafu(display("Blob '{}' missing {} in List Blobs response", name, property))]
+    MissingProperty {
+        name: String,
+        property: &'static str,
+    },
+}
+
+/// A page of a `List Blobs` response
+#[derive(Debug, Default)]
+pub(crate) struct ListPage {
+    pub objects: Vec<ObjectMeta>,
+    /// The marker of the next page, `None` for the last
+    pub next_marker: Option<String>,
+}
+
+/// The text content of the properties of a blob, as it's parsed
+#[derive(Default)]
+struct RawBlob {
+    name: String,
+    last_modified: String,
+    content_length: String,
+    e_tag: String,
+    version: String,
+}
+
+impl RawBlob {
+    fn finish(self) -> Result<ObjectMeta, ListError> {
+        let name = unescape_xml(&self.name);
+        let location = Path::parse(&name).context(InvalidLocationSnafu { name: &name })?;
+        let last_modified =
+            parse_http_date(unescape_xml(&self.last_modified).trim()).ok_or_else(|| {
+                ListError::MissingProperty {
+                    name: name.clone(),
+                    property: "Last-Modified",
+                }
+            })?;
+        let size = self
+            .content_length
+            .trim()
+            .parse()
+            .map_err(|_| ListError::MissingProperty {
+                name: name.clone(),
+                property: "Content-Length",
+            })?;
+        let non_empty = |value: String| Some(unescape_xml(value.trim())).filter(|v| !v.is_empty());
+        Ok(ObjectMeta {
+            location,
+            last_modified,
+            size,
+            e_tag: non_empty(self.e_tag),
+            version: non_empty(self.version),
+        })
+    }
+}
+
+/// Parse the `<Blob>` entries and `<NextMarker>` of an `EnumerationResults` `body`
+pub(crate) fn parse_list_blobs(body: &str) -> Result<ListPage, ListError> {
+    let mut page = ListPage::default();
+    let mut next_marker = String::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut blob: Option<RawBlob> = None;
+    for token in Tokenizer::from(body) {
+        match token.context(InvalidXmlSnafu)? {
+            Token::ElementStart { local, .. } => {
+                let local = local.as_str();
+                if local == "Blob" {
+                    blob = Some(RawBlob::default());
+                }
+                stack.push(local);
+            }
+            Token::ElementEnd {
+                end: ElementEnd::Empty | ElementEnd::Close(..),
+                ..
+            } => {
+                let closed = stack.pop();
+                if closed == Some("Blob") {
+                    page.objects
+                        .extend(blob.take().map(RawBlob::finish).transpose()?);
+                }
+            }
+            Token::Text { text } | Token::Cdata { text, .. } => {
+                if let [.., "EnumerationResults", "NextMarker"] = stack.as_slice() {
+                    next_marker.push_str(text.as_str());
+                    continue;
+                }
+                let blob = match blob.as_mut() {
+                    Some(blob) => blob,
+                    None => continue,
+                };
+                let field = match stack.as_slice() {
+                    [.., "Blob", "Name"] => &mut blob.name,
+                    [.., "Blob", "VersionId"] => &mut blob.version,
+                    [.., "Properties", "Last-Modified"] => &mut blob.last_modified,
+                    [.., "Properties", "Content-Length"] => &mut blob.content_length,
+                    [.., "Properties", "Etag"] => &mut blob.e_tag,
+                    _ => continue,
+                };
+                field.push_str(text.as_str());
+            }
+            _ => {}
+        }
+    }
+    let next_marker = unescape_xml(next_marker.trim());
+    page.next_marker = Some(next_marker).filter(|marker| !marker.is_empty());
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list_blobs() {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<EnumerationResults ServiceEndpoint="https://acct.blob.core.windows.net/" ContainerName="data">
+  <Prefix>a/</Prefix>
+  <Blobs>
+    <Blob>
+      <Name>a/one &amp; two.txt</Name>
+      <Properties>
+        <Last-Modified>Sun, 06 Nov 1994 08:49:37 GMT</Last-Modified>
+        <Etag>0x8DBF1E6A5B2C3D4</Etag>
+        <Content-Length>12</Content-Length>
+        <Content-Type>text/plain</Content-Type>
+      </Properties>
+    </Blob>
+    <Blob>
+      <Name>a/three.txt</Name>
+      <VersionId>2024-01-01T00:00:00.0000000Z</VersionId>
+      <Properties>
+        <Last-Modified>Mon, 07 Nov 1994 08:49:37 GMT</Last-Modified>
+        <Content-Length>0</Content-Length>
+        <Etag />
+      </Properties>
+      <Metadata><Name>not the blob name</Name></Metadata>
+    </Blob>
+  </Blobs>
+  <NextMarker>2!96!MDAwMDI5IWEvdGhyZWUudHh0ITAwMDAyOCE5OTk5LTEyLTMxVDIzOjU5OjU5Ljk5OTk5OTlaIQ--</NextMarker>
+</EnumerationResults>"#;
+        let page = parse_list_blobs(body).unwrap();
+        assert_eq!(
+            page.next_marker.as_deref(),
+            Some(
+                "2!96!MDAwMDI5IWEvdGhyZWUudHh0ITAwMDAyOCE5OTk5LTEyLTMxVDIzOjU5OjU5Ljk5OTk5OTlaIQ--"
+            )
+        );
+        assert_eq!(page.objects.len(), 2);
+
+        let first = &page.objects[0];
+        assert_eq!(first.location.as_ref(), "a/one & two.txt");
+        assert_eq!(first.size, 12);
+        assert_eq!(first.e_tag.as_deref(), Some("0x8DBF1E6A5B2C3D4"));
+        assert_eq!(first.version, None);
+        assert_eq!(
+            first.last_modified.to_rfc3339(),
+            "1994-11-06T08:49:37+00:00"
+        );
+
+        let second = &page.objects[1];
+        assert_eq!(second.location.as_ref(), "a/three.txt");
+        assert_eq!(second.size, 0);
+        assert_eq!(second.e_tag, None);
+        assert_eq!(
+            second.version.as_deref(),
+            Some("2024-01-01T00:00:00.0000000Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_list_blobs_last_page() {
+        let body = "<EnumerationResults><Blobs /><NextMarker /></EnumerationResults>";
+        let page = parse_list_blobs(body).unwrap();
+        assert!(page.objects.is_empty());
+        assert_eq!(page.next_marker, None);
+    }
+
+    #[test]
+    fn test_parse_list_blobs_errors() {
+        let missing_length = "<EnumerationResults><Blobs><Blob><Name>a.txt</Name>\
+            <Properties><Last-Modified>Sun, 06 Nov 1994 08:49:37 GMT</Last-Modified>\
+            </Properties></Blob></Blobs></EnumerationResults>";
+        assert!(matches!(
+            parse_list_blobs(missing_length),
+            Err(ListError::MissingProperty {
+                property: "Content-Length",
+                ..
+            })
+        ));
+
+        let missing_date = "<EnumerationResults><Blobs><Blob><Name>a.txt</Name>\
+            <Properties><Content-Length>1</Content-Length></Properties>\
+            </Blob></Blobs></EnumerationResults>";
+        assert!(matches!(
+            parse_list_blobs(missing_date),
+            Err(ListError::MissingProperty {
+                property: "Last-Modified",
+                ..
+            })
+        ));
+
+        let invalid_name = "<EnumerationResults><Blobs><Blob><Name>a//b.txt</Name>\
+            </Blob></Blobs></EnumerationResults>";
+        assert!(matches!(
+            parse_list_blobs(invalid_name),
+            Err(ListError::InvalidLocation { .. })
+        ));
+
+        assert!(matches!(
+            parse_list_blobs("<EnumerationResults><Blobs><<Blob>"),
+            Err(ListError::InvalidXml { .. })
+        ));
+    }
+}
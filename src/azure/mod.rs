@@ -0,0 +1,489 @@
+//! An [`ObjectStore`] for Azure Blob Storage, over the Blob service REST API
+//!
+//! Only block blobs are written, whole, with a single `Put Blob` request.
+
+use std::fmt::Display;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use backon::{ExponentialBuilder, Retryable};
+use bytes::Bytes;
+use futures::channel::oneshot;
+use futures::stream::BoxStream;
+use futures::{SinkExt, StreamExt};
+use object_store::{path::Path, Attribute, Attributes, ObjectMeta, PutMode};
+use object_store::{
+    Error, GetOptions, GetRange, GetResult, GetResultPayload, ObjectStore, PutOptions, PutPayload,
+    PutResult, Result,
+};
+use reqwest::{
+    header::{
+        HeaderName, HeaderValue, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_ENCODING,
+        CONTENT_LANGUAGE, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH,
+    },
+    Client, Method, RequestBuilder, Response, StatusCode,
+};
+use snafu::Snafu;
+use url::Url;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::http::{header_meta, GetOptionsExt, HeaderConfig};
+use credential::AzureCredential;
+
+mod builder;
+mod credential;
+mod list;
+
+pub use builder::{AzureConfigKey, MicrosoftAzureBuilder};
+
+/// The prefix of the headers user metadata is stored in
+const META_PREFIX: &str = "x-ms-meta-";
+
+#[derive(Debug, Snafu)]
+enum AzureError {
+    #[snafu(display(
+        "Suffix range request for {} not supported by Azure Blob Storage",
+        path
+    ))]
+    SuffixRange { path: String },
+
+    #[snafu(display("{} has not been modified", path))]
+    NotModified { path: String },
+
+    #[snafu(display("ETag required for conditional update"))]
+    MissingEtag,
+
+    #[snafu(display("Invalid header value for {}: {}", name, source))]
+    InvalidHeader {
+        name: String,
+        source: reqwest::header::InvalidHeaderValue,
+    },
+}
+
+/// Whether a request may succeed if re-sent: it failed to get a response, or
+/// the service responded with a transient error
+fn is_retryable(source: &reqwest::Error) -> bool {
+    match source.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => true,
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AzureClient {
+    client: Client,
+    account: String,
+    container: String,
+    /// The Blob service endpoint, `https://<account>.blob.core.windows.net`
+    endpoint: Url,
+    credential: AzureCredential,
+}
+
+impl AzureClient {
+    pub(crate) const STORE: &'static str = "MicrosoftAzure";
+    const HEADER_CONFIG: HeaderConfig = HeaderConfig {
+        etag_required: true,
+        last_modified_required: true,
+        version_header: Some("x-ms-version-id"),
+    };
+
+    pub(crate) fn new(
+        account: String,
+        container: String,
+        endpoint: Url,
+        credential: AzureCredential,
+    ) -> Self {
+        Self {
+            client: Client::new(),
+            account,
+            container,
+            endpoint,
+            credential,
+        }
+    }
+
+    /// The URL of the container, which ends without a `/`
+    fn container_url(&self) -> Url {
+        let mut url = self.endpoint.clone();
+        url.path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .push(&self.container);
+        url
+    }
+
+    fn blob_url(&self, location: &Path) -> Url {
+        let mut url = self.container_url();
+        url.path_segments_mut().unwrap().extend(location.parts());
+        url
+    }
+
+    /// Authorize and send the request of `builder`, retrying transient failures,
+    /// each attempt being signed afresh
+    async fn send(&self, builder: RequestBuilder) -> Result<Response, reqwest::Error> {
+        let send = || async {
+            let mut request = builder.try_clone().unwrap().build()?;
+            self.credential.authorize(&mut request, &self.account);
+            self.client
+                .execute(request)
+                .await
+                .and_then(|res| res.error_for_status())
+        };
+        send.retry(&ExponentialBuilder::default())
+            .when(is_retryable)
+            .await
+    }
+
+    fn map_err(source: reqwest::Error, path: &Path) -> Error {
+        let path = path.to_string();
+        let source = Box::new(source);
+        match source.status() {
+            Some(StatusCode::NOT_FOUND) => Error::NotFound { path, source },
+            Some(StatusCode::PRECONDITION_FAILED) => Error::Precondition { path, source },
+            Some(StatusCode::CONFLICT) => Error::AlreadyExists { path, source },
+            Some(StatusCode::UNAUTHORIZED) => Error::Unauthenticated { path, source },
+            Some(StatusCode::FORBIDDEN) => Error::PermissionDenied { path, source },
+            _ => Error::Generic {
+                store: Self::STORE,
+                source,
+            },
+        }
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        if let Some(GetRange::Suffix(_)) = options.range {
+            return Err(Error::NotSupported {
+                source: Box::new(AzureError::SuffixRange {
+                    path: location.to_string(),
+                }),
+            });
+        }
+        let mut url = self.blob_url(location);
+        if let Some(version) = &options.version {
+            url.query_pairs_mut().append_pair("versionid", version);
+        }
+        let range = options.range.clone();
+        let no_body = options.head;
+        let method = match no_body {
+            true => Method::HEAD,
+            false => Method::GET,
+        };
+        let builder = self.client.request(method, url).with_get_options(options);
+        let response = self
+            .send(builder)
+            .await
+            .map_err(|source| Self::map_err(source, location))?;
+        // a 304 answers If-None-Match/If-Modified-Since, without a body
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Err(Error::NotModified {
+                path: location.to_string(),
+                source: Box::new(AzureError::NotModified {
+                    path: location.to_string(),
+                }),
+            });
+        }
+        let meta = header_meta(location, response.headers(), Self::HEADER_CONFIG).map_err(|e| {
+            Error::Generic {
+                store: Self::STORE,
+                source: Box::new(e),
+            }
+        })?;
+        let attributes = response_attributes(&response);
+        let resolved_range = match range {
+            Some(GetRange::Bounded(range)) => range.start..range.end.min(meta.size),
+            Some(GetRange::Offset(offset)) => offset..meta.size,
+            _ => 0..meta.size,
+        };
+        if no_body {
+            return Ok(GetResult {
+                range: Default::default(),
+                payload: GetResultPayload::Stream(futures::stream::empty().boxed()),
+                meta,
+                attributes,
+            });
+        }
+        let (mut tx, rx) = futures::channel::mpsc::channel(1);
+        spawn_local(async move {
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|source| Error::Generic {
+                    store: Self::STORE,
+                    source: Box::new(source),
+                });
+                let failed = chunk.is_err();
+                if tx.send(chunk).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Ok(GetResult {
+            range: resolved_range,
+            payload: GetResultPayload::Stream(rx.boxed()),
+            meta,
+            attributes,
+        })
+    }
+
+    async fn put(&self, location: &Path, payload: Bytes, options: PutOptions) -> Result<PutResult> {
+        let mut builder = self
+            .client
+            .put(self.blob_url(location))
+            .header("x-ms-blob-type", "BlockBlob")
+            .body(payload);
+        let create = matches!(options.mode, PutMode::Create);
+        match options.mode {
+            PutMode::Overwrite => {}
+            PutMode::Create => builder = builder.header(IF_NONE_MATCH, "*"),
+            PutMode::Update(version) => {
+                let e_tag = version.e_tag.ok_or_else(|| Error::Generic {
+                    store: Self::STORE,
+                    source: Box::new(AzureError::MissingEtag),
+                })?;
+                builder = builder.header(IF_MATCH, e_tag);
+            }
+        }
+        for (attribute, value) in &options.attributes {
+            let name = match attribute {
+                Attribute::ContentType => "x-ms-blob-content-type".to_string(),
+                Attribute::ContentEncoding => "x-ms-blob-content-encoding".to_string(),
+                Attribute::ContentLanguage => "x-ms-blob-content-language".to_string(),
+                Attribute::ContentDisposition => "x-ms-blob-content-disposition".to_string(),
+                Attribute::CacheControl => "x-ms-blob-cache-control".to_string(),
+                Attribute::Metadata(key) => format!("{META_PREFIX}{key}"),
+                _ => continue,
+            };
+            let value = HeaderValue::from_str(value.as_ref()).map_err(|source| Error::Generic {
+                store: Self::STORE,
+                source: Box::new(AzureError::InvalidHeader {
+                    name: name.clone(),
+                    source,
+                }),
+            })?;
+            builder = builder.header(name, value);
+        }
+        let tags = options.tags.encoded();
+        if !tags.is_empty() {
+            builder = builder.header("x-ms-tags", tags);
+        }
+        let response = self
+            .send(builder)
+            .await
+            .map_err(|source| match source.status() {
+                // an existing blob fails If-None-Match: * with 409 BlobAlreadyExists
+                Some(StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED) if create => {
+                    Error::AlreadyExists {
+                        path: location.to_string(),
+                        source: Box::new(source),
+                    }
+                }
+                _ => Self::map_err(source, location),
+            })?;
+        let header = |name: &str| {
+            let value = response.headers().get(name)?.to_str().ok()?;
+            Some(value.to_string())
+        };
+        Ok(PutResult {
+            e_tag: header(ETAG.as_str()),
+            version: header("x-ms-version-id"),
+        })
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        let builder = self.client.delete(self.blob_url(location));
+        self.send(builder)
+            .await
+            .map_err(|source| Self::map_err(source, location))?;
+        Ok(())
+    }
+
+    /// List a page of the blobs under `prefix`, from `marker`
+    async fn list_page(
+        &self,
+        prefix: Option<&Path>,
+        marker: Option<&str>,
+    ) -> Result<list::ListPage> {
+        let mut url = self.container_url();
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("restype", "container")
+                .append_pair("comp", "list");
+            if let Some(prefix) = prefix.filter(|prefix| !prefix.as_ref().is_empty()) {
+                query.append_pair(
+                    "prefix",
+                    &format!("{prefix}{}", object_store::path::DELIMITER),
+                );
+            }
+            if let Some(marker) = marker {
+                query.append_pair("marker", marker);
+            }
+        }
+        let path = prefix.cloned().unwrap_or_default();
+        let body = match self.send(self.client.get(url)).await {
+            Ok(response) => response.text().await,
+            Err(err) => Err(err),
+        }
+        .map_err(|source| Self::map_err(source, &path))?;
+        list::parse_list_blobs(&body).map_err(|source| Error::Generic {
+            store: Self::STORE,
+            source: Box::new(source),
+        })
+    }
+}
+
+/// The attributes reported by the headers of `response`
+fn response_attributes(response: &Response) -> Attributes {
+    let mut attributes = Attributes::new();
+    let standard: [(HeaderName, Attribute); 5] = [
+        (CONTENT_TYPE, Attribute::ContentType),
+        (CONTENT_ENCODING, Attribute::ContentEncoding),
+        (CONTENT_LANGUAGE, Attribute::ContentLanguage),
+        (CONTENT_DISPOSITION, Attribute::ContentDisposition),
+        (CACHE_CONTROL, Attribute::CacheControl),
+    ];
+    for (name, attribute) in standard {
+        if let Some(value) = response.headers().get(name).and_then(|v| v.to_str().ok()) {
+            attributes.insert(attribute, value.to_string().into());
+        }
+    }
+    for (name, value) in response.headers() {
+        let key = match name.as_str().strip_prefix(META_PREFIX) {
+            Some(key) => key,
+            None => continue,
+        };
+        if let Ok(value) = value.to_str() {
+            attributes.insert(
+                Attribute::Metadata(key.to_string().into()),
+                value.to_string().into(),
+            );
+        }
+    }
+    attributes
+}
+
+/// An [`ObjectStore`] for a container of an Azure storage account, see
+/// [`MicrosoftAzureBuilder`]
+#[derive(Debug)]
+pub struct MicrosoftAzure {
+    client: Arc<AzureClient>,
+}
+
+impl MicrosoftAzure {
+    pub(crate) fn new(client: AzureClient) -> Self {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MicrosoftAzure {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        options: PutOptions,
+    ) -> Result<PutResult> {
+        let (sender, receiver) = oneshot::channel();
+        let client = self.client.clone();
+        let location = location.clone();
+        spawn_local(async move {
+            let res = client.put(&location, payload.into(), options).await;
+            let _ = sender.send(res);
+        });
+        receiver.await.unwrap()
+    }
+
+    async fn put_multipart(
+        &self,
+        _location: &Path,
+    ) -> Result<Box<dyn object_store::MultipartUpload>> {
+        Err(Error::NotImplemented)
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        _location: &Path,
+        _opts: object_store::PutMultipartOpts,
+    ) -> Result<Box<dyn object_store::MultipartUpload>> {
+        Err(Error::NotImplemented)
+    }
+
+    /// Suffix ranges (`bytes=-N`), which the Blob service doesn't support,
+    /// fail with [`Error::NotSupported`]
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        let (sender, receiver) = oneshot::channel();
+        let client = self.client.clone();
+        let location = location.clone();
+        spawn_local(async move {
+            let res = client.get_opts(&location, options).await;
+            let _ = sender.send(res);
+        });
+        receiver.await.unwrap()
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        let client = self.client.clone();
+        let location = location.clone();
+        spawn_local(async move {
+            let res = client.delete(&location).await;
+            let _ = sender.send(res);
+        });
+        receiver.await.unwrap()
+    }
+
+    /// Lists the blobs under `prefix`, a page being requested as the stream
+    /// reaches it
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, Result<ObjectMeta>> {
+        let prefix = prefix.cloned();
+        // `None` once the last page has been requested
+        let initial: Option<Option<String>> = Some(None);
+        futures::stream::unfold(initial, move |marker| {
+            let client = self.client.clone();
+            let prefix = prefix.clone();
+            async move {
+                let marker = marker?;
+                let (sender, receiver) = oneshot::channel();
+                spawn_local(async move {
+                    let res = client.list_page(prefix.as_ref(), marker.as_deref()).await;
+                    let _ = sender.send(res);
+                });
+                match receiver.await.unwrap() {
+                    Ok(page) => {
+                        let objects = page.objects.into_iter().map(Ok).collect::<Vec<_>>();
+                        Some((objects, page.next_marker.map(Some)))
+                    }
+                    Err(err) => Some((vec![Err(err)], None)),
+                }
+            }
+        })
+        .flat_map(futures::stream::iter)
+        .boxed()
+    }
+
+    async fn list_with_delimiter(
+        &self,
+        _prefix: Option<&Path>,
+    ) -> Result<object_store::ListResult> {
+        Err(Error::NotImplemented)
+    }
+
+    async fn copy(&self, _from: &Path, _to: &Path) -> Result<()> {
+        Err(Error::NotImplemented)
+    }
+
+    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> Result<()> {
+        Err(Error::NotImplemented)
+    }
+}
+
+impl Display for MicrosoftAzure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "MicrosoftAzure {{ account: {}, container: {} }}",
+            self.client.account, self.client.container
+        )
+    }
+}
@@ -0,0 +1,488 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt, Snafu};
+use url::Url;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::http::{HttpStore, InnerClient};
+use crate::utils::str_is_truthy;
+
+#[derive(Debug, Snafu)]
+#[allow(missing_docs)]
+enum ConfigError {
+    #[snafu(display("Configuration key: '{}' is not known.", key))]
+    UnknownConfigurationKey { key: String },
+
+    #[snafu(display("Missing URL"))]
+    MissingUrl,
+
+    #[snafu(display("Basic authentication requires a username"))]
+    MissingUsername,
+
+    #[snafu(display("Configure either bearer or basic authentication, not both"))]
+    ConflictingAuth,
+
+    #[snafu(display("Invalid value for {}: '{}', expected a whole number", key, value))]
+    InvalidNumber { key: String, value: String },
+
+    #[snafu(display("Invalid header '{}', expected 'name: value'", header))]
+    InvalidHeader { header: String },
+
+    #[snafu(display("Unable parse source url. Url: {}, Error: {}", url, source))]
+    UnableToParseUrl {
+        source: url::ParseError,
+        url: String,
+    },
+}
+
+impl From<ConfigError> for object_store::Error {
+    fn from(source: ConfigError) -> Self {
+        match source {
+            ConfigError::UnknownConfigurationKey { key } => Self::UnknownConfigurationKey {
+                store: InnerClient::STORE,
+                key,
+            },
+            _ => Self::Generic {
+                store: InnerClient::STORE,
+                source: Box::new(source),
+            },
+        }
+    }
+}
+
+/// Configuration keys of an [`HttpBuilder`], e.g. as passed to
+/// [`parse_url_opts`](crate::parse::parse_url_opts)
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Copy, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum HttpConfigKey {
+    /// See [`HttpStore::with_accept`]
+    Accept,
+    /// See [`HttpStore::with_accept_encoding`]
+    AcceptEncoding,
+    /// See [`HttpStore::with_connect_timeout`], in milliseconds
+    ConnectTimeout,
+    /// See [`HttpStore::with_weak_etags`]
+    WeakEtags,
+    /// See [`HttpStore::with_content_type_inference`]
+    ContentTypeInference,
+    /// See [`HttpStore::with_range_fallback`]
+    RangeFallback,
+    /// See [`HttpStore::with_max_response_size`], in bytes
+    MaxResponseSize,
+    /// See [`HttpStore::with_delete_concurrency`]
+    DeleteConcurrency,
+    /// See [`HttpStore::with_meta_cache`], the TTL in milliseconds
+    MetaCacheTtl,
+    /// See [`HttpStore::with_bearer_auth`]
+    BearerToken,
+    /// See [`HttpStore::with_basic_auth`], along with [`Self::Password`]
+    Username,
+    /// See [`HttpStore::with_basic_auth`], along with [`Self::Username`]
+    Password,
+    /// See [`HttpStore::with_headers`], one `name: value` header per line
+    Headers,
+    /// See [`HttpStore::with_retry`]
+    RetryMaxAttempts,
+    /// See [`HttpStore::with_retry`], in milliseconds
+    RetryInitialBackoff,
+    /// See [`HttpStore::with_max_backoff`], in milliseconds
+    RetryMaxBackoff,
+}
+
+impl AsRef<str> for HttpConfigKey {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Accept => "http_accept",
+            Self::AcceptEncoding => "http_accept_encoding",
+            Self::ConnectTimeout => "http_connect_timeout_ms",
+            Self::WeakEtags => "http_weak_etags",
+            Self::ContentTypeInference => "http_infer_content_type",
+            Self::RangeFallback => "http_range_fallback",
+            Self::MaxResponseSize => "http_max_response_size",
+            Self::DeleteConcurrency => "http_delete_concurrency",
+            Self::MetaCacheTtl => "http_meta_cache_ttl_ms",
+            Self::BearerToken => "http_bearer_token",
+            Self::Username => "http_username",
+            Self::Password => "http_password",
+            Self::Headers => "http_headers",
+            Self::RetryMaxAttempts => "http_retry_max_attempts",
+            Self::RetryInitialBackoff => "http_retry_initial_backoff_ms",
+            Self::RetryMaxBackoff => "http_retry_max_backoff_ms",
+        }
+    }
+}
+
+impl FromStr for HttpConfigKey {
+    type Err = object_store::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "http_accept" | "accept" => Ok(Self::Accept),
+            "http_accept_encoding" | "accept_encoding" => Ok(Self::AcceptEncoding),
+            "http_connect_timeout_ms" | "connect_timeout_ms" => Ok(Self::ConnectTimeout),
+            "http_weak_etags" | "weak_etags" => Ok(Self::WeakEtags),
+            "http_infer_content_type" | "infer_content_type" => Ok(Self::ContentTypeInference),
+            "http_range_fallback" | "range_fallback" => Ok(Self::RangeFallback),
+            "http_max_response_size" | "max_response_size" => Ok(Self::MaxResponseSize),
+            "http_delete_concurrency" | "delete_concurrency" => Ok(Self::DeleteConcurrency),
+            "http_meta_cache_ttl_ms" | "meta_cache_ttl_ms" => Ok(Self::MetaCacheTtl),
+            "http_bearer_token" | "bearer_token" | "token" => Ok(Self::BearerToken),
+            "http_username" | "username" => Ok(Self::Username),
+            "http_password" | "password" => Ok(Self::Password),
+            "http_headers" | "headers" => Ok(Self::Headers),
+            "http_retry_max_attempts" | "retry_max_attempts" | "max_attempts" => {
+                Ok(Self::RetryMaxAttempts)
+            }
+            "http_retry_initial_backoff_ms" | "retry_initial_backoff_ms" => {
+                Ok(Self::RetryInitialBackoff)
+            }
+            "http_retry_max_backoff_ms" | "retry_max_backoff_ms" => Ok(Self::RetryMaxBackoff),
+            _ => Err(ConfigError::UnknownConfigurationKey { key: s.into() }.into()),
+        }
+    }
+}
+
+/// Parse the configuration value of `key` as a whole number
+fn parse_number(key: HttpConfigKey, value: String) -> Result<u64, ConfigError> {
+    match value.trim().parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(ConfigError::InvalidNumber {
+            key: key.as_ref().to_string(),
+            value,
+        }),
+    }
+}
+
+/// Parse `value`, one `name: value` header per line, blank lines aside
+///
+/// The values are marked sensitive, as they're often API keys, so that they're
+/// redacted from debug output.
+fn parse_headers(value: &str) -> Result<HeaderMap, ConfigError> {
+    let mut headers = HeaderMap::new();
+    for line in value.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let header = line.split_once(':').and_then(|(name, value)| {
+            let name = HeaderName::from_bytes(name.trim().as_bytes()).ok()?;
+            Some((name, HeaderValue::from_str(value.trim()).ok()?))
+        });
+        let (name, mut value) = header.context(InvalidHeaderSnafu { header: line })?;
+        value.set_sensitive(true);
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+/// Configure an [`HttpStore`] from a URL and key - value options
+///
+/// Options not expressible as strings (e.g. observers) are set on the built
+/// store itself.
+#[derive(Default)]
+pub struct HttpBuilder {
+    url: Option<String>,
+    accept: Option<String>,
+    accept_encoding: Option<String>,
+    connect_timeout: Option<String>,
+    weak_etags: bool,
+    infer_content_type: bool,
+    range_fallback: bool,
+    max_response_size: Option<String>,
+    delete_concurrency: Option<String>,
+    meta_cache_ttl: Option<String>,
+    bearer_token: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    headers: Option<String>,
+    retry_max_attempts: Option<String>,
+    retry_initial_backoff: Option<String>,
+    retry_max_backoff: Option<String>,
+}
+
+impl HttpBuilder {
+    pub fn new() -> HttpBuilder {
+        Self::default()
+    }
+
+    /// Set the base URL of the store, which locations are resolved against
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set an option on the builder via a key - value pair.
+    pub fn with_config(mut self, key: HttpConfigKey, value: impl Into<String>) -> Self {
+        match key {
+            HttpConfigKey::Accept => self.accept = Some(value.into()),
+            HttpConfigKey::AcceptEncoding => self.accept_encoding = Some(value.into()),
+            HttpConfigKey::ConnectTimeout => self.connect_timeout = Some(value.into()),
+            HttpConfigKey::WeakEtags => self.weak_etags = str_is_truthy(&value.into()),
+            HttpConfigKey::ContentTypeInference => {
+                self.infer_content_type = str_is_truthy(&value.into())
+            }
+            HttpConfigKey::RangeFallback => self.range_fallback = str_is_truthy(&value.into()),
+            HttpConfigKey::MaxResponseSize => self.max_response_size = Some(value.into()),
+            HttpConfigKey::DeleteConcurrency => self.delete_concurrency = Some(value.into()),
+            HttpConfigKey::MetaCacheTtl => self.meta_cache_ttl = Some(value.into()),
+            HttpConfigKey::BearerToken => self.bearer_token = Some(value.into()),
+            HttpConfigKey::Username => self.username = Some(value.into()),
+            HttpConfigKey::Password => self.password = Some(value.into()),
+            HttpConfigKey::Headers => self.headers = Some(value.into()),
+            HttpConfigKey::RetryMaxAttempts => self.retry_max_attempts = Some(value.into()),
+            HttpConfigKey::RetryInitialBackoff => self.retry_initial_backoff = Some(value.into()),
+            HttpConfigKey::RetryMaxBackoff => self.retry_max_backoff = Some(value.into()),
+        };
+        self
+    }
+
+    pub fn build(self) -> Result<HttpStore, object_store::Error> {
+        let url = self.url.context(MissingUrlSnafu)?;
+        let url = Url::parse(&url).context(UnableToParseUrlSnafu { url })?;
+        let mut store = HttpStore::new(url)
+            .with_weak_etags(self.weak_etags)
+            .with_content_type_inference(self.infer_content_type)
+            .with_range_fallback(self.range_fallback);
+        if let Some(accept) = self.accept {
+            store = store.with_accept(accept);
+        }
+        if let Some(accept_encoding) = self.accept_encoding {
+            store = store.with_accept_encoding(accept_encoding);
+        }
+        if let Some(value) = self.connect_timeout {
+            let millis = parse_number(HttpConfigKey::ConnectTimeout, value)?;
+            store = store.with_connect_timeout(Duration::from_millis(millis));
+        }
+        if let Some(value) = self.max_response_size {
+            let max = parse_number(HttpConfigKey::MaxResponseSize, value)?;
+            store = store.with_max_response_size(max as usize);
+        }
+        if let Some(value) = self.delete_concurrency {
+            let concurrency = parse_number(HttpConfigKey::DeleteConcurrency, value)?;
+            store = store.with_delete_concurrency(concurrency as usize);
+        }
+        if let Some(value) = self.meta_cache_ttl {
+            let millis = parse_number(HttpConfigKey::MetaCacheTtl, value)?;
+            store = store.with_meta_cache(Duration::from_millis(millis));
+        }
+        if let Some(value) = self.headers {
+            store = store.with_headers(parse_headers(&value)?);
+        }
+        if self.retry_max_attempts.is_some() || self.retry_initial_backoff.is_some() {
+            let max_attempts = match self.retry_max_attempts {
+                Some(value) => parse_number(HttpConfigKey::RetryMaxAttempts, value)? as usize,
+                None => InnerClient::DEFAULT_RETRY_ATTEMPTS,
+            };
+            let initial_backoff = match self.retry_initial_backoff {
+                Some(value) => {
+                    Duration::from_millis(parse_number(HttpConfigKey::RetryInitialBackoff, value)?)
+                }
+                None => InnerClient::DEFAULT_RETRY_BACKOFF,
+            };
+            store = store.with_retry(max_attempts, initial_backoff);
+        }
+        if let Some(value) = self.retry_max_backoff {
+            let millis = parse_number(HttpConfigKey::RetryMaxBackoff, value)?;
+            store = store.with_max_backoff(Duration::from_millis(millis));
+        }
+        store = match (self.bearer_token, self.username, self.password) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                return Err(ConfigError::ConflictingAuth.into())
+            }
+            (Some(token), None, None) => store.with_bearer_auth(token),
+            (None, Some(username), password) => {
+                store.with_basic_auth(username, password.unwrap_or_default())
+            }
+            (None, None, Some(_)) => return Err(ConfigError::MissingUsername.into()),
+            (None, None, None) => store,
+        };
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_keys() {
+        for key in [
+            HttpConfigKey::Headers,
+            HttpConfigKey::RetryMaxAttempts,
+            HttpConfigKey::RetryInitialBackoff,
+            HttpConfigKey::RetryMaxBackoff,
+            HttpConfigKey::MetaCacheTtl,
+        ] {
+            assert_eq!(HttpConfigKey::from_str(key.as_ref()).unwrap(), key);
+        }
+        assert_eq!(
+            HttpConfigKey::from_str("max_attempts").unwrap(),
+            HttpConfigKey::RetryMaxAttempts
+        );
+        assert!(matches!(
+            HttpConfigKey::from_str("http_unknown"),
+            Err(object_store::Error::UnknownConfigurationKey { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let headers =
+            parse_headers("X-Api-Key: secret\n\n  x-trace:a: b  \nx-api-key: other").unwrap();
+        let api_keys = headers.get_all("x-api-key").iter().collect::<Vec<_>>();
+        assert_eq!(api_keys, ["secret", "other"]);
+        assert_eq!(headers["x-trace"], "a: b");
+        assert!(headers["x-trace"].is_sensitive());
+
+        for invalid in ["no colon", "bad name: value", "x-ok: bad\u{7f}value"] {
+            assert!(
+                matches!(
+                    parse_headers(invalid),
+                    Err(ConfigError::InvalidHeader { .. })
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let store = HttpBuilder::new()
+            .with_url("https://example.com/data/")
+            .with_config(HttpConfigKey::Headers, "x-api-key: secret")
+            .with_config(HttpConfigKey::RetryMaxAttempts, "2")
+            .with_config(HttpConfigKey::RetryMaxBackoff, "500")
+            .build()
+            .unwrap();
+        assert_eq!(store.client.url.as_str(), "https://example.com/data/");
+        assert_eq!(store.client.headers["x-api-key"], "secret");
+        let retry = format!("{:?}", store.client.retry);
+        assert!(retry.contains("max_times: Some(1)"), "{retry}");
+        assert!(retry.contains("min_delay: 1s"), "{retry}");
+        assert!(retry.contains("max_delay: Some(500ms)"), "{retry}");
+
+        let invalid = HttpBuilder::new()
+            .with_url("https://example.com")
+            .with_config(HttpConfigKey::RetryInitialBackoff, "soon")
+            .build();
+        assert!(invalid.unwrap_err().to_string().contains("soon"));
+        let invalid = HttpBuilder::new()
+            .with_url("https://example.com")
+            .with_config(HttpConfigKey::Headers, "x-api-key")
+            .build();
+        assert!(invalid.unwrap_err().to_string().contains("x-api-key"));
+    }
+
+    #[test]
+    fn test_auth() {
+        let build = |options: &[(HttpConfigKey, &str)]| {
+            let builder = HttpBuilder::new().with_url("https://example.com");
+            let builder = options.iter().fold(builder, |builder, (key, value)| {
+                builder.with_config(*key, *value)
+            });
+            builder.build()
+        };
+        let authorization = |store: HttpStore| store.client.authorization.unwrap();
+
+        let bearer = build(&[(HttpConfigKey::BearerToken, "token")]).unwrap();
+        let bearer = authorization(bearer);
+        assert_eq!(bearer, "Bearer token");
+        assert!(bearer.is_sensitive());
+        let basic = build(&[
+            (HttpConfigKey::Username, "user"),
+            (HttpConfigKey::Password, "pass"),
+        ]);
+        assert_eq!(authorization(basic.unwrap()), "Basic dXNlcjpwYXNz");
+        // a username alone is sent with an empty password
+        let username = build(&[(HttpConfigKey::Username, "user")]);
+        assert_eq!(authorization(username.unwrap()), "Basic dXNlcjo=");
+        assert!(build(&[]).unwrap().client.authorization.is_none());
+
+        for conflicting in [
+            vec![
+                (HttpConfigKey::BearerToken, "token"),
+                (HttpConfigKey::Username, "user"),
+            ],
+            vec![
+                (HttpConfigKey::BearerToken, "token"),
+                (HttpConfigKey::Password, "pass"),
+            ],
+        ] {
+            let err = build(&conflicting).unwrap_err();
+            assert!(err.to_string().contains("not both"), "{err}");
+        }
+        let err = build(&[(HttpConfigKey::Password, "pass")]).unwrap_err();
+        assert!(err.to_string().contains("requires a username"), "{err}");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_auth_requests() {
+        let (url, requests) = crate::http::tests::serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\nabc",
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let store = HttpBuilder::new()
+            .with_url(url.as_str())
+            .with_config(HttpConfigKey::BearerToken, "token")
+            .with_config(HttpConfigKey::RetryInitialBackoff, "0")
+            .build()
+            .unwrap();
+        let location = object_store::path::Path::from("a.txt");
+
+        // GETs and PUTs, each retried once, then a DELETE
+        let get = store
+            .client
+            .get_request(&location, Default::default(), None)
+            .await
+            .unwrap();
+        assert_eq!(get.text().await.unwrap(), "abc");
+        store
+            .client
+            .put(&location, bytes::Bytes::from_static(b"abcd"), None)
+            .await
+            .unwrap();
+        store.client.delete(&location).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let methods = requests
+            .iter()
+            .map(|request| request.split(' ').next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(methods, ["GET", "GET", "PUT", "PUT", "DELETE"]);
+        for request in requests.iter() {
+            assert!(request.contains("authorization: Bearer token"), "{request}");
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_headers_and_retries() {
+        use crate::http::tests::{head_options, serve};
+
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let store = HttpBuilder::new()
+            .with_url(url.as_str())
+            .with_config(HttpConfigKey::Headers, "x-api-key: secret")
+            .with_config(HttpConfigKey::RetryMaxAttempts, "2")
+            .with_config(HttpConfigKey::RetryInitialBackoff, "0")
+            .build()
+            .unwrap();
+        let location = object_store::path::Path::from("a.txt");
+        let head = store.client.get_opts(&location, head_options(), None).await;
+        assert_eq!(head.unwrap().meta.size, 3);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        for request in requests.iter() {
+            assert!(request.contains("x-api-key: secret"), "{request}");
+        }
+    }
+}
@@ -1,30 +1,53 @@
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use bytes::Bytes;
 use chrono::{DateTime, TimeZone, Utc};
 use futures::channel::oneshot;
 use futures::stream::BoxStream;
-use futures::stream::StreamExt;
+use futures::stream::{StreamExt, TryStreamExt};
 use object_store::PutResult;
 use object_store::{path::Path, ObjectMeta};
-use object_store::{Attributes, PutMode};
+use object_store::{Attribute, Attributes, PutMode};
 use object_store::{Error, GetOptions, GetRange, GetResult, GetResultPayload, ObjectStore, Result};
 use url::Url;
+// native tests drive the store's tasks on a tokio `LocalSet` instead
+#[cfg(all(test, not(target_arch = "wasm32")))]
+use tokio::task::spawn_local;
+#[cfg(not(all(test, not(target_arch = "wasm32"))))]
 use wasm_bindgen_futures::spawn_local;
+
+use crate::telemetry::{observe, RequestObserver};
+use crate::utils::{expires_attribute_value, parse_http_date, EXPIRES_ATTRIBUTE, HTTP_DATE_FORMAT};
 // use tracing::info;
 use backon::ExponentialBuilder;
 use backon::Retryable;
 
 use async_trait::async_trait;
+use base64::{prelude::BASE64_STANDARD, Engine};
 use reqwest::{
-    header::{HeaderMap, CONTENT_LENGTH, ETAG, LAST_MODIFIED},
+    header::{
+        HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, ACCEPT_RANGES, AUTHORIZATION,
+        CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+        EXPIRES, IF_MATCH, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, LOCATION,
+    },
     Client, Method, RequestBuilder, Response, StatusCode,
 };
 use snafu::{OptionExt, ResultExt, Snafu};
+use webdav::{DavEntry, PROPFIND_BODY};
+
+mod builder;
+mod webdav;
+
+pub use crate::utils::IfRange;
+pub use builder::{HttpBuilder, HttpConfigKey};
 
 #[derive(Debug, Copy, Clone)]
 /// Configuration for header extraction
-struct HeaderConfig {
+pub(crate) struct HeaderConfig {
     /// Whether to require an ETag header when extracting [`ObjectMeta`] from headers.
     ///
     /// Defaults to `true`
@@ -39,7 +62,7 @@ struct HeaderConfig {
 }
 
 #[derive(Debug, Snafu)]
-enum HeaderError {
+pub(crate) enum HeaderError {
     #[snafu(display("ETag Header missing from response"))]
     MissingEtag,
 
@@ -63,6 +86,96 @@ enum HeaderError {
         content_length: String,
         source: std::num::ParseIntError,
     },
+
+    #[snafu(display("Invalid content range '{}'", content_range))]
+    InvalidContentRange { content_range: String },
+}
+
+#[derive(Debug, Snafu)]
+enum WriteError {
+    #[snafu(display("Error performing request: {}", source))]
+    Request { source: reqwest::Error },
+
+    #[snafu(display(
+        "Server redirected {} request with status {}, which would not preserve the method",
+        method,
+        status
+    ))]
+    MethodChangingRedirect { method: Method, status: StatusCode },
+
+    #[snafu(display("Redirect response missing a valid Location header"))]
+    InvalidRedirectLocation,
+
+    #[snafu(display("Exceeded {} redirects", max))]
+    TooManyRedirects { max: usize },
+}
+
+impl WriteError {
+    fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Request { source } => source.status(),
+            _ => None,
+        }
+    }
+
+    /// Whether the write may succeed if re-sent: it failed to get a response,
+    /// or the server responded with a transient error
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Request { source } => match source.status() {
+                Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+                None => true,
+            },
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+enum ResponseError {
+    #[snafu(display(
+        "Response body for {} exceeds the configured maximum of {} bytes",
+        path,
+        max
+    ))]
+    TooLarge { path: String, max: usize },
+
+    #[snafu(display("{} has not been modified", path))]
+    NotModified { path: String },
+}
+
+#[derive(Debug, Snafu)]
+enum RangeError {
+    #[snafu(display(
+        "Range request for {} not supported by the server{}, enable HttpStore::with_range_fallback to fetch the whole object and slice it instead",
+        path,
+        if *advertised_none { " (Accept-Ranges: none)" } else { "" }
+    ))]
+    RangeNotSupported { path: String, advertised_none: bool },
+
+    #[snafu(display(
+        "Range request for {} was answered with Content-Encoding {}, so the range is of the encoded bytes, which can't be decoded on their own. Fetch the whole object, or store it without a Content-Encoding",
+        path,
+        encoding
+    ))]
+    EncodedRange { path: String, encoding: String },
+
+    #[snafu(display("Range {} of {} is beyond its size of {} bytes", range, path, size))]
+    Unsatisfiable {
+        path: String,
+        range: GetRange,
+        size: usize,
+    },
+}
+
+#[derive(Debug, Snafu)]
+enum ResumeError {
+    #[snafu(display(
+        "{} changed while being read (no longer matches ETag {}), a consistent download can't be resumed",
+        path,
+        e_tag
+    ))]
+    ObjectChanged { path: String, e_tag: String },
 }
 
 fn get_etag(headers: &HeaderMap) -> Result<String, HeaderError> {
@@ -70,7 +183,59 @@ fn get_etag(headers: &HeaderMap) -> Result<String, HeaderError> {
     Ok(e_tag.to_str().context(BadHeaderSnafu)?.to_string())
 }
 
-fn header_meta(
+/// A parsed `Content-Range: bytes <first>-<last>/<total>` header
+#[derive(Debug)]
+struct ContentRange {
+    /// The returned byte range, with an exclusive end
+    range: Range<usize>,
+    /// The total size of the object, unless the server reported it as `*`
+    total: Option<usize>,
+}
+
+fn get_content_range(headers: &HeaderMap) -> Result<Option<ContentRange>, HeaderError> {
+    let content_range = match headers.get(CONTENT_RANGE) {
+        Some(content_range) => content_range.to_str().context(BadHeaderSnafu)?,
+        None => return Ok(None),
+    };
+    let parse = || {
+        let (range, total) = content_range.strip_prefix("bytes ")?.split_once('/')?;
+        let (first, last) = range.split_once('-')?;
+        let range = first.parse().ok()?..last.parse::<usize>().ok()? + 1;
+        let total = match total {
+            "*" => None,
+            total => Some(total.parse().ok()?),
+        };
+        Some(ContentRange { range, total })
+    };
+    match parse() {
+        Some(content_range) => Ok(Some(content_range)),
+        None => InvalidContentRangeSnafu { content_range }.fail(),
+    }
+}
+
+/// The range of an object of `size` bytes returned for a request of `requested`,
+/// the whole object if it `changed` since an `If-Range` validator
+///
+/// The server may clamp an over-long range to the object (e.g. `bytes=0-999`
+/// of a 100 byte object answered with `Content-Range: bytes 0-99/100`), so
+/// the returned `content_range` is authoritative over the requested one.
+fn resolve_range(
+    content_range: Option<ContentRange>,
+    requested: Option<GetRange>,
+    size: usize,
+    changed: bool,
+) -> Range<usize> {
+    match (content_range, requested) {
+        (Some(content_range), _) => content_range.range,
+        (None, _) if changed => 0..size,
+        (None, Some(GetRange::Bounded(range))) => range.start..range.end.min(size),
+        (None, Some(GetRange::Offset(offset))) => offset..size,
+        (None, Some(GetRange::Suffix(suffix))) => size.saturating_sub(suffix)..size,
+        (None, None) => 0..size,
+    }
+}
+
+pub(crate) fn header_meta(
     location: &Path,
     headers: &HeaderMap,
     cfg: HeaderConfig,
@@ -92,14 +257,23 @@ fn header_meta(
         Err(e) => return Err(e),
     };
 
-    let content_length = headers
-        .get(CONTENT_LENGTH)
-        .context(MissingContentLengthSnafu)?;
+    // for partial responses, Content-Length is the length of the returned range,
+    // the size of the object as a whole is only reported by Content-Range
+    let size = match get_content_range(headers)? {
+        Some(ContentRange {
+            total: Some(total), ..
+        }) => total,
+        _ => {
+            let content_length = headers
+                .get(CONTENT_LENGTH)
+                .context(MissingContentLengthSnafu)?;
 
-    let content_length = content_length.to_str().context(BadHeaderSnafu)?;
-    let size = content_length
-        .parse()
-        .context(InvalidContentLengthSnafu { content_length })?;
+            let content_length = content_length.to_str().context(BadHeaderSnafu)?;
+            content_length
+                .parse()
+                .context(InvalidContentLengthSnafu { content_length })?
+        }
+    };
 
     let version = match cfg.version_header.and_then(|h| headers.get(h)) {
         Some(v) => Some(v.to_str().context(BadHeaderSnafu)?.to_string()),
@@ -115,6 +289,78 @@ fn header_meta(
     })
 }
 
+/// Infer a MIME type from the extension of `location`, for servers that omit `Content-Type`
+fn content_type_from_extension(location: &Path) -> Option<&'static str> {
+    let content_type = match location.extension()?.to_ascii_lowercase().as_str() {
+        "json" => "application/json",
+        "geojson" => "application/geo+json",
+        "csv" => "text/csv",
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "xml" => "application/xml",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "tif" | "tiff" => "image/tiff",
+        "mp4" => "video/mp4",
+        "parquet" | "arrow" | "feather" => "application/octet-stream",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+/// Format each entity tag of an `If-None-Match` list as a weak validator,
+/// e.g. `"abc", W/"def"` becomes `W/"abc", W/"def"`
+fn weak_etags(tags: &str) -> String {
+    tags.split(',')
+        .map(str::trim)
+        .map(|tag| match tag {
+            "*" => tag.to_string(),
+            tag if tag.starts_with("W/") => tag.to_string(),
+            tag if tag.starts_with('"') => format!("W/{tag}"),
+            tag => format!("W/\"{tag}\""),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The metadata of the objects among `entries`, collections being skipped
+fn dav_objects(entries: Vec<(Path, DavEntry)>) -> impl Iterator<Item = ObjectMeta> {
+    entries
+        .into_iter()
+        .filter(|(_, entry)| !entry.is_collection)
+        .map(|(location, entry)| entry.into_object_meta(location))
+}
+
+/// Split the members of the collection at `prefix`, as listed by a `Depth: 1`
+/// `PROPFIND`, into objects and common prefixes (the member collections)
+fn dav_listing(prefix: &Path, entries: Vec<(Path, DavEntry)>) -> object_store::ListResult {
+    let mut common_prefixes = Vec::new();
+    let mut objects = Vec::new();
+    // the collection itself is reported alongside its members
+    for (location, entry) in entries
+        .into_iter()
+        .filter(|(location, _)| location != prefix)
+    {
+        match entry.is_collection {
+            true => common_prefixes.push(location),
+            false => objects.push(entry.into_object_meta(location)),
+        }
+    }
+    object_store::ListResult {
+        common_prefixes,
+        objects,
+    }
+}
+
 pub trait GetOptionsExt {
     fn with_get_options(self, options: GetOptions) -> Self;
 }
@@ -144,23 +390,147 @@ impl GetOptionsExt for RequestBuilder {
             self = self.header(IF_NONE_MATCH, tag);
         }
 
-        const DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
         if let Some(date) = options.if_unmodified_since {
-            self = self.header(IF_UNMODIFIED_SINCE, date.format(DATE_FORMAT).to_string());
+            self = self.header(
+                IF_UNMODIFIED_SINCE,
+                date.format(HTTP_DATE_FORMAT).to_string(),
+            );
         }
 
         if let Some(date) = options.if_modified_since {
-            self = self.header(IF_MODIFIED_SINCE, date.format(DATE_FORMAT).to_string());
+            self = self.header(IF_MODIFIED_SINCE, date.format(HTTP_DATE_FORMAT).to_string());
         }
 
         self
     }
 }
 
+/// Short-lived cache of [`ObjectMeta`] and [`Attributes`] keyed by path, used
+/// to serve repeated HEAD requests without a round trip, and to complete the
+/// meta of partial responses of the same version (path and ETag)
+#[derive(Debug)]
+struct MetaCache {
+    ttl: chrono::Duration,
+    entries: Mutex<HashMap<Path, CachedMeta>>,
+}
+
+#[derive(Debug)]
+struct CachedMeta {
+    meta: ObjectMeta,
+    attributes: Attributes,
+    fetched_at: DateTime<Utc>,
+}
+
+impl MetaCache {
+    fn new(ttl: chrono::Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, location: &Path) -> Option<(ObjectMeta, Attributes)> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(location) {
+            Some(cached) if Utc::now() - cached.fetched_at < self.ttl => {
+                Some((cached.meta.clone(), cached.attributes.clone()))
+            }
+            Some(_) => {
+                entries.remove(location);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Records `meta`, replacing any entry for the same path (and therefore
+    /// any entry with a stale ETag)
+    fn insert(&self, meta: &ObjectMeta, attributes: &Attributes) {
+        self.entries.lock().unwrap().insert(
+            meta.location.clone(),
+            CachedMeta {
+                meta: meta.clone(),
+                attributes: attributes.clone(),
+                fetched_at: Utc::now(),
+            },
+        );
+    }
+
+    /// The cached meta of `location`, provided it's of the version `e_tag`
+    fn get_version(
+        &self,
+        location: &Path,
+        e_tag: Option<&str>,
+    ) -> Option<(ObjectMeta, Attributes)> {
+        let e_tag = e_tag?;
+        self.get(location)
+            .filter(|(meta, _)| meta.e_tag.as_deref() == Some(e_tag))
+    }
+
+    fn invalidate(&self, location: &Path) {
+        self.entries.lock().unwrap().remove(location);
+    }
+}
+
+/// Evaluate the preconditions of `options` against `meta`, as the server would,
+/// see <https://datatracker.ietf.org/doc/html/rfc9110#section-13.2.2>
+fn check_preconditions(options: &GetOptions, meta: &ObjectMeta) -> Result<()> {
+    // without an ETag, no entity tag (but `*`) matches
+    let e_tag = meta.e_tag.as_deref().unwrap_or("*");
+    let matches = |tags: &str| tags.split(',').map(str::trim).any(|tag| tag == e_tag);
+    if let Some(tags) = &options.if_match {
+        if tags != "*" && !matches(tags) {
+            return Err(Error::Precondition {
+                path: meta.location.to_string(),
+                source: format!("{e_tag} does not match {tags}").into(),
+            });
+        }
+    } else if let Some(date) = options.if_unmodified_since {
+        if meta.last_modified > date {
+            return Err(Error::Precondition {
+                path: meta.location.to_string(),
+                source: format!("modified at {}, after {date}", meta.last_modified).into(),
+            });
+        }
+    }
+    if let Some(tags) = &options.if_none_match {
+        if tags == "*" || matches(tags) {
+            return Err(Error::NotModified {
+                path: meta.location.to_string(),
+                source: format!("{e_tag} matches {tags}").into(),
+            });
+        }
+    } else if let Some(date) = options.if_modified_since {
+        if meta.last_modified <= date {
+            return Err(Error::NotModified {
+                path: meta.location.to_string(),
+                source: format!("modified at {}, not after {date}", meta.last_modified).into(),
+            });
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 struct InnerClient {
     url: Url,
     client: Client,
+    write_client: Client,
+    accept: Option<String>,
+    accept_encoding: Option<String>,
+    meta_cache: Option<Arc<MetaCache>>,
+    observer: Option<RequestObserver>,
+    weak_etags: bool,
+    infer_content_type: bool,
+    delete_concurrency: usize,
+    max_response_size: Option<usize>,
+    range_fallback: bool,
+    connect_timeout: Option<Duration>,
+    /// The `Authorization` header sent with every request
+    authorization: Option<HeaderValue>,
+    /// Further headers sent with every request
+    headers: HeaderMap,
+    retry: ExponentialBuilder,
 }
 
 impl InnerClient {
@@ -170,10 +540,85 @@ impl InnerClient {
         last_modified_required: false,
         version_header: None,
     };
+    const MAX_REDIRECTS: usize = 10;
+    /// Browsers allow 6 concurrent HTTP/1.1 connections per host, queueing the rest
+    const DEFAULT_DELETE_CONCURRENCY: usize = 6;
+    /// Resumptions of a single GET body that fails part way
+    const MAX_RESUMES: usize = 3;
+    /// Attempts at each request, including the first
+    const DEFAULT_RETRY_ATTEMPTS: usize = 4;
+    /// The delay before the first retry, doubled for every subsequent retry
+    const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_secs(1);
     fn new(url: Url) -> Self {
+        let (client, write_client) = Self::clients(None, None, &HeaderMap::new());
         Self {
             url,
-            client: Client::new(),
+            client,
+            write_client,
+            accept: None,
+            accept_encoding: None,
+            meta_cache: None,
+            observer: None,
+            weak_etags: false,
+            infer_content_type: false,
+            delete_concurrency: Self::DEFAULT_DELETE_CONCURRENCY,
+            max_response_size: None,
+            range_fallback: false,
+            connect_timeout: None,
+            authorization: None,
+            headers: HeaderMap::new(),
+            retry: ExponentialBuilder::default()
+                .with_max_times(Self::DEFAULT_RETRY_ATTEMPTS - 1)
+                .with_min_delay(Self::DEFAULT_RETRY_BACKOFF),
+        }
+    }
+
+    /// The clients for reads and writes respectively, connecting within
+    /// `connect_timeout`, where supported, and sending `headers` and
+    /// `authorization` with every request
+    fn clients(
+        connect_timeout: Option<Duration>,
+        authorization: Option<&HeaderValue>,
+        headers: &HeaderMap,
+    ) -> (Client, Client) {
+        let mut headers = headers.clone();
+        if let Some(authorization) = authorization {
+            headers.insert(AUTHORIZATION, authorization.clone());
+        }
+        let client = Client::builder().default_headers(headers.clone());
+        // redirects for writes are followed manually (see `send_write`), as the
+        // default policy rewrites PUT/DELETE to GET and drops the body. Browser
+        // fetch always follows redirects itself, preserving the method on 307/308.
+        let write_client = Client::builder().default_headers(headers);
+        #[cfg(not(target_arch = "wasm32"))]
+        let write_client = write_client.redirect(reqwest::redirect::Policy::none());
+        #[cfg(not(target_arch = "wasm32"))]
+        let (client, write_client) = match connect_timeout {
+            Some(timeout) => (
+                client.connect_timeout(timeout),
+                write_client.connect_timeout(timeout),
+            ),
+            None => (client, write_client),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let _ = connect_timeout;
+        (client.build().unwrap(), write_client.build().unwrap())
+    }
+
+    /// Rebuild the clients, following a change to their configuration
+    fn rebuild_clients(&mut self) {
+        let (client, write_client) = Self::clients(
+            self.connect_timeout,
+            self.authorization.as_ref(),
+            &self.headers,
+        );
+        self.client = client;
+        self.write_client = write_client;
+    }
+
+    fn invalidate_meta(&self, location: &Path) {
+        if let Some(cache) = &self.meta_cache {
+            cache.invalidate(location);
         }
     }
 
@@ -186,24 +631,48 @@ impl InnerClient {
         url
     }
 
-    async fn get_request(&self, path: &Path, options: GetOptions) -> Result<Response> {
+    async fn get_request(
+        &self,
+        path: &Path,
+        mut options: GetOptions,
+        if_range: Option<&IfRange>,
+    ) -> Result<Response> {
         let url = self.path_url(path);
+        if self.weak_etags {
+            options.if_none_match = options.if_none_match.as_deref().map(weak_etags);
+        }
         let has_range = options.range.is_some();
-        let method = match options.head {
-            true => Method::HEAD,
-            false => Method::GET,
+        let (method, operation) = match options.head {
+            true => (Method::HEAD, "head"),
+            false => (Method::GET, "get"),
         };
-        let builder = self.client.request(method, url).with_get_options(options);
+        let mut builder = self.client.request(method, url).with_get_options(options);
+        if let Some(if_range) = if_range {
+            builder = builder.header(IF_RANGE, if_range.header_value());
+        }
+        if let Some(accept) = &self.accept {
+            builder = builder.header(ACCEPT, accept);
+        }
+        // NB: the response body is never decoded by this client, so an explicit
+        // Accept-Encoding yields the encoded bytes, with the applied encoding
+        // reported via Attribute::ContentEncoding. Browsers treat
+        // Accept-Encoding as a forbidden header and will silently drop it.
+        if let Some(accept_encoding) = &self.accept_encoding {
+            builder = builder.header(ACCEPT_ENCODING, accept_encoding);
+        }
+        // each attempt is observed, as a request in its own right
         let res_func = || async {
-            builder
-                .try_clone()
-                .unwrap()
-                .send()
-                .await
-                .and_then(|res| res.error_for_status())
+            let started = Utc::now();
+            let res = builder.try_clone().unwrap().send().await;
+            let bytes = match (&res, operation) {
+                (Ok(res), "get") => res.content_length().unwrap_or(0) as usize,
+                _ => 0,
+            };
+            observe(self.observer.as_ref(), operation, bytes, started);
+            res.and_then(|res| res.error_for_status())
         };
         let res = res_func
-            .retry(&ExponentialBuilder::default())
+            .retry(&self.retry)
             .await
             .map_err(|source| match source.status() {
                 // Some stores return METHOD_NOT_ALLOWED for get on directories
@@ -211,18 +680,43 @@ impl InnerClient {
                     source: Box::new(source),
                     path: path.to_string(),
                 },
+                Some(StatusCode::PRECONDITION_FAILED) => Error::Precondition {
+                    source: Box::new(source),
+                    path: path.to_string(),
+                },
                 _ => Error::Generic {
                     store: InnerClient::STORE,
                     source: Box::new(source),
                 },
             })?;
 
+        // a 304 answers If-None-Match/If-Modified-Since, without a body
+        if res.status() == StatusCode::NOT_MODIFIED {
+            return Err(Error::NotModified {
+                path: path.to_string(),
+                source: Box::new(ResponseError::NotModified {
+                    path: path.to_string(),
+                }),
+            });
+        }
+
         // We expect a 206 Partial Content response if a range was requested
-        // a 200 OK response would indicate the server did not fulfill the request
-        if has_range && res.status() != StatusCode::PARTIAL_CONTENT {
+        // a 200 OK response would indicate the server did not fulfill the request,
+        // unless the If-Range validator didn't match
+        if has_range
+            && res.status() != StatusCode::PARTIAL_CONTENT
+            && !self.range_fallback
+            && if_range.is_none()
+        {
+            let advertised_none = res
+                .headers()
+                .get(ACCEPT_RANGES)
+                .map(|value| value.as_bytes().eq_ignore_ascii_case(b"none"))
+                .unwrap_or(false);
             return Err(Error::NotSupported {
-                source: Box::new(Error::NotImplemented {
-                    // href: path.to_string(),
+                source: Box::new(RangeError::RangeNotSupported {
+                    path: path.to_string(),
+                    advertised_none,
                 }),
             });
         }
@@ -230,61 +724,402 @@ impl InnerClient {
         Ok(res)
     }
 
-    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+        if_range: Option<IfRange>,
+    ) -> Result<GetResult> {
         let range = options.range.clone();
         let no_body = options.head;
-        let response = self.get_request(location, options).await?;
-        let meta =
-            header_meta(location, response.headers(), InnerClient::HEADER_CONFIG).map_err(|e| {
-                Error::Generic {
+        // the cache only holds the current version of each object
+        let cache = self
+            .meta_cache
+            .as_ref()
+            .filter(|_| options.version.is_none());
+        if let Some((meta, attributes)) = cache.and_then(|cache| cache.get(location)) {
+            check_preconditions(&options, &meta)?;
+            if no_body {
+                return Ok(GetResult {
+                    range: 0..meta.size,
+                    payload: GetResultPayload::Stream(futures::stream::empty().boxed()),
+                    meta,
+                    attributes,
+                });
+            }
+            // with `if_range`, a changed object is returned whole, whatever the range
+            let unsatisfiable = match &range {
+                Some(GetRange::Bounded(range)) => range.start >= meta.size,
+                Some(GetRange::Offset(offset)) => *offset >= meta.size,
+                _ => false,
+            };
+            if unsatisfiable && if_range.is_none() {
+                return Err(Error::Generic {
                     store: InnerClient::STORE,
-                    source: Box::new(e),
-                }
+                    source: Box::new(RangeError::Unsatisfiable {
+                        path: location.to_string(),
+                        range: range.unwrap(),
+                        size: meta.size,
+                    }),
+                });
+            }
+        }
+        let response = self
+            .get_request(location, options, if_range.as_ref())
+            .await?;
+        if let Some(max) = self.max_response_size {
+            if !no_body && response.content_length().unwrap_or(0) as usize > max {
+                return Err(self.response_too_large(location, max));
+            }
+        }
+        let content_range = get_content_range(response.headers()).map_err(|e| Error::Generic {
+            store: InnerClient::STORE,
+            source: Box::new(e),
+        })?;
+        let mut meta = header_meta(location, response.headers(), InnerClient::HEADER_CONFIG)
+            .map_err(|e| Error::Generic {
+                store: InnerClient::STORE,
+                source: Box::new(e),
+            })?;
+        // partial responses without the object's total size report a partial size
+        let whole_meta = content_range
+            .as_ref()
+            .map_or(true, |content_range| content_range.total.is_some());
+        // which are completed from cached meta of the same version, sparing a
+        // HEAD for the object's size
+        let cached = match whole_meta {
+            true => None,
+            false => cache.and_then(|cache| cache.get_version(location, meta.e_tag.as_deref())),
+        };
+        if let Some((cached, _)) = cached {
+            meta = cached;
+        }
+        let mut attributes = Attributes::new();
+        if let Some(encoding) = response.headers().get(CONTENT_ENCODING) {
+            let encoding = encoding.to_str().map_err(|source| Error::Generic {
+                store: InnerClient::STORE,
+                source: Box::new(HeaderError::BadHeader { source }),
+            })?;
+            // a partial response's range covers the encoded bytes, which can't be
+            // decoded in isolation (and browsers would attempt to)
+            if content_range.is_some() && !encoding.eq_ignore_ascii_case("identity") {
+                return Err(Error::NotSupported {
+                    source: Box::new(RangeError::EncodedRange {
+                        path: location.to_string(),
+                        encoding: encoding.to_string(),
+                    }),
+                });
+            }
+            attributes.insert(Attribute::ContentEncoding, encoding.to_string().into());
+        }
+        // invalid dates (commonly `0`) mean "already expired", and are omitted
+        let expires = response.headers().get(EXPIRES);
+        if let Some(date) = expires.and_then(|value| parse_http_date(value.to_str().ok()?)) {
+            attributes.insert(
+                Attribute::Metadata(EXPIRES_ATTRIBUTE.into()),
+                expires_attribute_value(date).into(),
+            );
+        }
+        if let Some(language) = response.headers().get(CONTENT_LANGUAGE) {
+            let language = language.to_str().map_err(|source| Error::Generic {
+                store: InnerClient::STORE,
+                source: Box::new(HeaderError::BadHeader { source }),
             })?;
+            attributes.insert(Attribute::ContentLanguage, language.to_string().into());
+        }
+        match response.headers().get(CONTENT_TYPE) {
+            Some(content_type) => {
+                let content_type = content_type.to_str().map_err(|source| Error::Generic {
+                    store: InnerClient::STORE,
+                    source: Box::new(HeaderError::BadHeader { source }),
+                })?;
+                attributes.insert(Attribute::ContentType, content_type.to_string().into());
+            }
+            None if self.infer_content_type => {
+                if let Some(content_type) = content_type_from_extension(location) {
+                    attributes.insert(Attribute::ContentType, content_type.into());
+                }
+            }
+            None => {}
+        }
+        if let Some(cache) = cache.filter(|_| whole_meta) {
+            cache.insert(&meta, &attributes);
+        }
         if no_body {
             return Ok(GetResult {
-                range: Default::default(),
+                range: 0..meta.size,
                 payload: GetResultPayload::Stream(futures::stream::empty().boxed()),
                 meta,
-                attributes: Attributes::new(),
+                attributes,
             });
         }
+        let changed = if_range.is_some() && response.status() == StatusCode::OK;
+        let resolved_range = resolve_range(content_range, range, meta.size, changed);
         let (tx, rx) = futures::channel::mpsc::channel(1);
+        let client = self.clone();
+        let copied_location = location.clone();
+        let e_tag = meta.e_tag.clone();
+        let body_range = resolved_range.clone();
         spawn_local(async move {
-            let stream = response.bytes_stream();
-            stream
-                .map(|chunk| {
-                    Ok(chunk.map_err(|source| Error::Generic {
-                        store: InnerClient::STORE,
-                        source: Box::new(source),
-                    }))
-                })
-                .forward(tx)
-                .await
-                .unwrap();
+            client
+                .forward_body(copied_location, response, body_range, e_tag, tx)
+                .await;
         });
         let safe_stream = rx.boxed();
 
-        let resolved_range = match range {
-            Some(GetRange::Bounded(inner_range)) => inner_range,
-            Some(GetRange::Offset(lower_limit)) => lower_limit..meta.size,
-            Some(GetRange::Suffix(upper_limit)) => 0..upper_limit,
-            None => 0..meta.size,
-        };
         Ok(GetResult {
             range: resolved_range,
             payload: GetResultPayload::Stream(safe_stream),
             meta,
-            attributes: Attributes::new(),
+            attributes,
+        })
+    }
+    fn response_too_large(&self, location: &Path, max: usize) -> Error {
+        Error::Generic {
+            store: InnerClient::STORE,
+            source: Box::new(ResponseError::TooLarge {
+                path: location.to_string(),
+                max,
+            }),
+        }
+    }
+    /// Forward the body of `response`, covering `range` of the object, to `tx`
+    ///
+    /// Should the body fail part way, the remainder is requested with
+    /// `If-Range: <e_tag>`, so that the bytes received are never a mix of two
+    /// versions of the object: a changed object is answered in full, and
+    /// reported as such. Without a strong ETag the failure is forwarded.
+    async fn forward_body(
+        self,
+        location: Path,
+        mut response: Response,
+        range: Range<usize>,
+        e_tag: Option<String>,
+        mut tx: futures::channel::mpsc::Sender<Result<Bytes>>,
+    ) {
+        use futures::SinkExt;
+
+        // `offset` is the next byte of the object to forward, `position` that of
+        // the next byte of the body: a range request answered in full (see
+        // `with_range_fallback`) starts at 0, and is sliced here
+        let mut offset = range.start;
+        let mut resumes = 0;
+        loop {
+            let mut position = match response.status() {
+                StatusCode::PARTIAL_CONTENT => offset,
+                _ => 0,
+            };
+            let mut stream = response.bytes_stream();
+            let failure = loop {
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        let start = offset.saturating_sub(position).min(chunk.len());
+                        let end = range.end.saturating_sub(position).min(chunk.len());
+                        position += chunk.len();
+                        if start >= end {
+                            if position >= range.end {
+                                return;
+                            }
+                            continue;
+                        }
+                        let chunk = chunk.slice(start..end);
+                        offset += chunk.len();
+                        // Content-Length was checked up front, but needn't be honest
+                        if let Some(max) = self.max_response_size {
+                            if offset - range.start > max {
+                                let _ = tx.send(Err(self.response_too_large(&location, max))).await;
+                                return;
+                            }
+                        }
+                        if tx.send(Ok(chunk)).await.is_err() || offset >= range.end {
+                            return;
+                        }
+                    }
+                    Some(Err(source)) => break source,
+                    None => return,
+                }
+            };
+            let e_tag = match &e_tag {
+                Some(e_tag)
+                    if !e_tag.starts_with("W/")
+                        && resumes < Self::MAX_RESUMES
+                        && offset < range.end =>
+                {
+                    e_tag.clone()
+                }
+                _ => {
+                    let _ = tx
+                        .send(Err(Error::Generic {
+                            store: InnerClient::STORE,
+                            source: Box::new(failure),
+                        }))
+                        .await;
+                    return;
+                }
+            };
+            resumes += 1;
+            let options = GetOptions {
+                range: Some(GetRange::Bounded(offset..range.end)),
+                ..Default::default()
+            };
+            let if_range = IfRange::ETag(e_tag.clone());
+            response = match self.get_request(&location, options, Some(&if_range)).await {
+                // the object changed if answered in full, unless the server ignored
+                // the range, or with another ETag, should it ignore If-Range
+                Ok(response)
+                    if match get_etag(response.headers()) {
+                        Ok(current) => current != e_tag,
+                        Err(_) => response.status() == StatusCode::OK,
+                    } =>
+                {
+                    let path = location.to_string();
+                    let source = Box::new(ResumeError::ObjectChanged {
+                        path: path.clone(),
+                        e_tag,
+                    });
+                    let _ = tx.send(Err(Error::Precondition { path, source })).await;
+                    return;
+                }
+                Ok(response) => response,
+                Err(err) => {
+                    let _ = tx.send(Err(err)).await;
+                    return;
+                }
+            };
+        }
+    }
+    /// Issue a write request, re-sending it to the new location on
+    /// method-preserving redirects (307/308) and failing on redirects that
+    /// would change the method (301/302/303)
+    async fn send_write(
+        &self,
+        operation: &'static str,
+        method: Method,
+        path: &Path,
+        body: Option<Bytes>,
+        headers: HeaderMap,
+    ) -> Result<Response, WriteError> {
+        let mut url = self.path_url(path);
+        let bytes = body.as_ref().map(Bytes::len).unwrap_or(0);
+        for _ in 0..InnerClient::MAX_REDIRECTS {
+            let mut builder = self
+                .write_client
+                .request(method.clone(), url.clone())
+                .headers(headers.clone());
+            if let Some(body) = &body {
+                // stated even when empty, as servers may refuse (411) writes without
+                // it. Browsers set it themselves, ignoring this one
+                builder = builder
+                    .header(CONTENT_LENGTH, body.len())
+                    .body(body.clone());
+            }
+            let started = Utc::now();
+            let res = builder.send().await;
+            observe(self.observer.as_ref(), operation, bytes, started);
+            let res = res.context(RequestSnafu)?;
+            match res.status() {
+                StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {
+                    url = res
+                        .headers()
+                        .get(LOCATION)
+                        .and_then(|location| location.to_str().ok())
+                        .and_then(|location| url.join(location).ok())
+                        .context(InvalidRedirectLocationSnafu)?;
+                }
+                status @ (StatusCode::MOVED_PERMANENTLY
+                | StatusCode::FOUND
+                | StatusCode::SEE_OTHER) => {
+                    return Err(WriteError::MethodChangingRedirect { method, status })
+                }
+                _ => return res.error_for_status().context(RequestSnafu),
+            }
+        }
+        Err(WriteError::TooManyRedirects {
+            max: InnerClient::MAX_REDIRECTS,
         })
     }
+
+    /// The URL of the collection `prefix`, which ends with a `/`
+    fn collection_url(&self, prefix: Option<&Path>) -> Url {
+        let mut url = match prefix {
+            Some(prefix) => self.path_url(prefix),
+            None => self.url.clone(),
+        };
+        url.path_segments_mut().unwrap().pop_if_empty().push("");
+        url
+    }
+
+    /// The location of the resource `href`, as reported by a `PROPFIND` of
+    /// `url`, or `None` where it's outside this store
+    fn href_path(&self, url: &Url, href: &str) -> Option<Path> {
+        let href = url.join(href).ok()?;
+        let base = self.collection_url(None);
+        let relative = href.path().strip_prefix(base.path())?;
+        Path::from_url_path(relative.trim_end_matches('/')).ok()
+    }
+
+    /// Issue a WebDAV `PROPFIND` of the collection `prefix` to `depth` (`1` or
+    /// `infinity`), returning the resources reported, including `prefix` itself
+    async fn propfind(
+        &self,
+        prefix: Option<&Path>,
+        depth: &'static str,
+    ) -> Result<Vec<(Path, DavEntry)>> {
+        let url = self.collection_url(prefix);
+        let builder = self
+            .client
+            .request(Method::from_bytes(b"PROPFIND").unwrap(), url.clone())
+            .header("Depth", depth)
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY);
+        let path = prefix.map(Path::to_string).unwrap_or_default();
+        let res_func = || async {
+            let started = Utc::now();
+            let res = builder.try_clone().unwrap().send().await;
+            observe(self.observer.as_ref(), "list", 0, started);
+            res.and_then(|res| res.error_for_status())
+        };
+        let res = res_func.retry(&self.retry).await;
+        let body = match res {
+            Ok(res) => res.text().await,
+            Err(err) => Err(err),
+        }
+        .map_err(|source| match source.status() {
+            // servers without WebDAV support reject the method
+            Some(StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED) => {
+                Error::NotSupported {
+                    source: Box::new(source),
+                }
+            }
+            Some(StatusCode::NOT_FOUND) => Error::NotFound {
+                path,
+                source: Box::new(source),
+            },
+            _ => Error::Generic {
+                store: InnerClient::STORE,
+                source: Box::new(source),
+            },
+        })?;
+        self.multistatus_entries(&url, &body)
+    }
+
+    /// The resources of the multistatus `body` answering a `PROPFIND` of `url`,
+    /// along with their locations, omitting those outside this store
+    fn multistatus_entries(&self, url: &Url, body: &str) -> Result<Vec<(Path, DavEntry)>> {
+        let entries = webdav::parse_multistatus(body).map_err(|source| Error::Generic {
+            store: InnerClient::STORE,
+            source: Box::new(source),
+        })?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| Some((self.href_path(url, &entry.href)?, entry)))
+            .collect())
+    }
+
     pub async fn delete(&self, path: &Path) -> Result<()> {
-        let url = self.path_url(path);
-        self.client
-            .delete(url)
-            .send()
+        self.invalidate_meta(path);
+        self.send_write("delete", Method::DELETE, path, None, HeaderMap::new())
             .await
-            .and_then(|res| res.error_for_status())
             .map_err(|source| match source.status() {
                 Some(StatusCode::NOT_FOUND) => Error::NotFound {
                     source: Box::new(source),
@@ -299,11 +1134,152 @@ impl InnerClient {
         Ok(())
     }
 
-    pub async fn put(&self, _path: &Path, _payload: Bytes) -> Result<Response> {
-        todo!()
+    pub async fn put(
+        &self,
+        path: &Path,
+        payload: Bytes,
+        precondition: Option<WritePrecondition>,
+    ) -> Result<Response> {
+        self.invalidate_meta(path);
+        let mut headers = HeaderMap::new();
+        if let Some(precondition) = &precondition {
+            let (name, value) = match precondition {
+                WritePrecondition::Create => (IF_NONE_MATCH, "*"),
+                WritePrecondition::IfNoneMatch(e_tag) => (IF_NONE_MATCH, e_tag.as_str()),
+                WritePrecondition::IfMatch(e_tag) => (IF_MATCH, e_tag.as_str()),
+            };
+            let value = HeaderValue::from_str(value).map_err(|source| Error::Generic {
+                store: InnerClient::STORE,
+                source: Box::new(source),
+            })?;
+            headers.insert(name, value);
+        }
+        let put = || {
+            self.send_write(
+                "put",
+                Method::PUT,
+                path,
+                Some(payload.clone()),
+                headers.clone(),
+            )
+        };
+        put.retry(&self.retry)
+            .when(WriteError::is_retryable)
+            .await
+            .map_err(|source| match (source.status(), precondition) {
+                (Some(StatusCode::PRECONDITION_FAILED), Some(WritePrecondition::Create)) => {
+                    Error::AlreadyExists {
+                        path: path.to_string(),
+                        source: Box::new(source),
+                    }
+                }
+                (Some(StatusCode::PRECONDITION_FAILED), Some(_)) => Error::Precondition {
+                    path: path.to_string(),
+                    source: Box::new(source),
+                },
+                (Some(StatusCode::NOT_FOUND), _) => Error::NotFound {
+                    path: path.to_string(),
+                    source: Box::new(source),
+                },
+                (Some(StatusCode::UNAUTHORIZED), _) => Error::Unauthenticated {
+                    path: path.to_string(),
+                    source: Box::new(source),
+                },
+                (Some(StatusCode::FORBIDDEN), _) => Error::PermissionDenied {
+                    path: path.to_string(),
+                    source: Box::new(source),
+                },
+                _ => Error::Generic {
+                    store: InnerClient::STORE,
+                    source: Box::new(source),
+                },
+            })
+    }
+
+    /// Copy `from` to `to` with a WebDAV `COPY`, replacing any object at `to`
+    /// only if `overwrite` is set (`Overwrite: T`)
+    pub async fn copy(&self, from: &Path, to: &Path, overwrite: bool) -> Result<()> {
+        self.invalidate_meta(to);
+        let mut headers = HeaderMap::new();
+        let destination =
+            HeaderValue::from_str(self.path_url(to).as_str()).map_err(|source| Error::Generic {
+                store: InnerClient::STORE,
+                source: Box::new(source),
+            })?;
+        headers.insert("Destination", destination);
+        let overwrite_flag = match overwrite {
+            true => "T",
+            false => "F",
+        };
+        headers.insert("Overwrite", HeaderValue::from_static(overwrite_flag));
+        let copy = || {
+            self.send_write(
+                "copy",
+                Method::from_bytes(b"COPY").unwrap(),
+                from,
+                None,
+                headers.clone(),
+            )
+        };
+        copy.retry(&self.retry)
+            .when(WriteError::is_retryable)
+            .await
+            .map_err(|source| match source.status() {
+                // the destination exists, and Overwrite was F
+                Some(StatusCode::PRECONDITION_FAILED) if !overwrite => Error::AlreadyExists {
+                    path: to.to_string(),
+                    source: Box::new(source),
+                },
+                Some(StatusCode::NOT_FOUND) => Error::NotFound {
+                    path: from.to_string(),
+                    source: Box::new(source),
+                },
+                // servers without WebDAV support reject the method
+                Some(StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED) => {
+                    Error::NotSupported {
+                        source: Box::new(source),
+                    }
+                }
+                Some(StatusCode::UNAUTHORIZED) => Error::Unauthenticated {
+                    path: from.to_string(),
+                    source: Box::new(source),
+                },
+                Some(StatusCode::FORBIDDEN) => Error::PermissionDenied {
+                    path: to.to_string(),
+                    source: Box::new(source),
+                },
+                _ => Error::Generic {
+                    store: InnerClient::STORE,
+                    source: Box::new(source),
+                },
+            })?;
+        Ok(())
     }
 }
 
+/// A condition a write is made subject to, failing with `412 Precondition Failed`
+/// should it not hold
+#[derive(Debug, Clone)]
+pub enum WritePrecondition {
+    /// Only create the object if it doesn't already exist (`If-None-Match: *`)
+    Create,
+    /// Only write if the current object doesn't match the given ETag, e.g. to
+    /// create or replace anything but a known version
+    IfNoneMatch(String),
+    /// Only write if the current object matches the given ETag
+    IfMatch(String),
+}
+
+/// An [`ObjectStore`](object_store::ObjectStore) of a plain HTTP, or WebDAV, server
+///
+/// Writes follow method-preserving redirects (307/308), re-sending the body,
+/// and fail on redirects that would change the method (301/302/303).
+///
+/// In the browser, fetch follows redirects itself, out of sight of the store
+/// (a manual redirect mode only yields an opaque response, without the
+/// `Location`). Browsers keep the method and body on 301/302/307/308, but turn
+/// writes redirected with 303 into `GET`s, which then appear to succeed: don't
+/// redirect writes with 303 where the store is used from wasm.
 #[derive(Debug)]
 pub struct HttpStore {
     client: InnerClient,
@@ -315,6 +1291,235 @@ impl HttpStore {
             client: InnerClient::new(url),
         }
     }
+
+    /// Set the `Accept` header sent with every GET/HEAD request
+    pub fn with_accept(mut self, accept: impl Into<String>) -> Self {
+        self.client.accept = Some(accept.into());
+        self
+    }
+
+    /// Set the `Accept-Encoding` header sent with every GET/HEAD request
+    ///
+    /// Response bodies are passed through undecoded, check
+    /// [`Attribute::ContentEncoding`] on the [`GetResult`] for the encoding applied
+    pub fn with_accept_encoding(mut self, accept_encoding: impl Into<String>) -> Self {
+        self.client.accept_encoding = Some(accept_encoding.into());
+        self
+    }
+
+    /// Fail requests whose connection isn't established within `timeout`
+    ///
+    /// Browser fetch doesn't expose the connection phase of a request, nor
+    /// accept a timeout, so this has no effect in the browser, where only the
+    /// user agent's own connection timeout applies.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.client.connect_timeout = Some(timeout);
+        self.client.rebuild_clients();
+        self
+    }
+
+    /// Authenticate every request with `Authorization: Bearer <token>`
+    pub fn with_bearer_auth(self, token: impl Display) -> Self {
+        self.with_authorization(format!("Bearer {token}"))
+    }
+
+    /// Authenticate every request with HTTP basic authentication,
+    /// `Authorization: Basic <base64(username:password)>`
+    pub fn with_basic_auth(self, username: impl Display, password: impl Display) -> Self {
+        let credentials = BASE64_STANDARD.encode(format!("{username}:{password}"));
+        self.with_authorization(format!("Basic {credentials}"))
+    }
+
+    /// Send `authorization` as the `Authorization` header of every request,
+    /// marked sensitive so that it's redacted from debug output
+    ///
+    /// Values that aren't valid header values are ignored.
+    fn with_authorization(mut self, authorization: String) -> Self {
+        if let Ok(mut authorization) = HeaderValue::from_str(&authorization) {
+            authorization.set_sensitive(true);
+            self.client.authorization = Some(authorization);
+            self.client.rebuild_clients();
+        }
+        self
+    }
+
+    /// Send `headers` with every request, e.g. API keys, unless the request
+    /// itself sets them (as with `Range`, or the `Authorization` of
+    /// [`Self::with_bearer_auth`])
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.client.headers = headers;
+        self.client.rebuild_clients();
+        self
+    }
+
+    /// Make up to `max_attempts` attempts (including the first) at each request,
+    /// waiting `initial_backoff` before the first retry, doubling thereafter
+    ///
+    /// Requests are retried on network errors and server errors (including
+    /// `429 Too Many Requests`). Defaults to 4 attempts, from 1s.
+    pub fn with_retry(mut self, max_attempts: usize, initial_backoff: Duration) -> Self {
+        self.client.retry = self
+            .client
+            .retry
+            .with_max_times(max_attempts.saturating_sub(1))
+            .with_min_delay(initial_backoff);
+        self
+    }
+
+    /// Cap the (exponentially growing) delay between retries at `max_backoff`,
+    /// 60s by default
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.client.retry = self.client.retry.with_max_delay(max_backoff);
+        self
+    }
+
+    /// Invoke `observer` once per HTTP request issued, including retries
+    pub fn with_observer(mut self, observer: RequestObserver) -> Self {
+        self.client.observer = Some(observer);
+        self
+    }
+
+    /// Send the entity tags of `if_none_match` as weak validators (`W/"<etag>"`)
+    ///
+    /// Entity tags are otherwise sent verbatim, and compared by the server: per
+    /// RFC 9110, `If-None-Match` uses weak comparison (`W/"a"` matches `"a"`),
+    /// whereas `If-Match` uses strong comparison, so never matches a weak tag and
+    /// is left untouched. Useful where cached tags were stored without their
+    /// `W/` prefix, or for servers that only emit weak validators.
+    pub fn with_weak_etags(mut self, weak_etags: bool) -> Self {
+        self.client.weak_etags = weak_etags;
+        self
+    }
+
+    /// Where the server sends no `Content-Type`, infer [`Attribute::ContentType`]
+    /// from the extension of the requested path (e.g. `application/json` for `.json`)
+    pub fn with_content_type_inference(mut self, infer: bool) -> Self {
+        self.client.infer_content_type = infer;
+        self
+    }
+
+    /// Serve range requests from servers that don't support them (responding
+    /// `200 OK` with the whole object, typically advertising `Accept-Ranges: none`)
+    /// by slicing the full response, rather than failing with [`Error::NotSupported`]
+    ///
+    /// The whole object is downloaded, up to the end of the requested range.
+    pub fn with_range_fallback(mut self, fallback: bool) -> Self {
+        self.client.range_fallback = fallback;
+        self
+    }
+
+    /// Fail reads of responses larger than `max` bytes, rather than exhausting
+    /// the wasm heap when buffering them (e.g. via [`GetResult::bytes`])
+    ///
+    /// Responses advertising a larger `Content-Length` are rejected before their
+    /// body is read, and streamed bodies error once `max` bytes are exceeded.
+    pub fn with_max_response_size(mut self, max: usize) -> Self {
+        self.client.max_response_size = Some(max);
+        self
+    }
+
+    /// Set the number of deletes `delete_stream` issues concurrently, 6 by default
+    pub fn with_delete_concurrency(mut self, concurrency: usize) -> Self {
+        self.client.delete_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Cache [`ObjectMeta`] from recent requests for `ttl`, so that a `head`
+    /// following a `head` or `get` of the same path doesn't issue another request
+    ///
+    /// Ranged `get`s of servers that don't report the object's total size (e.g.
+    /// `Content-Range: bytes 0-99/*`) take it from cached meta with the same
+    /// ETag, rather than reporting the size of the range.
+    ///
+    /// Cached meta also settles the preconditions of later requests, and
+    /// rejects ranges beyond the object, without a round trip.
+    ///
+    /// Entries are invalidated by `put` and `delete` through this store, but
+    /// writes by other clients won't be observed until `ttl` elapses
+    pub fn with_meta_cache(mut self, ttl: std::time::Duration) -> Self {
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+        self.client.meta_cache = Some(Arc::new(MetaCache::new(ttl)));
+        self
+    }
+
+    /// Get `range` of `location` if the object still matches `if_range`, or the
+    /// whole object if it has since changed
+    ///
+    /// The result's `range` covers the whole object in the latter case. This is
+    /// the building block of resumable downloads: resuming a download of an
+    /// object that changed in the meantime restarts it, in a single request.
+    pub async fn get_if_range(
+        &self,
+        location: &Path,
+        range: GetRange,
+        if_range: IfRange,
+    ) -> Result<GetResult> {
+        let (sender, receiver) = oneshot::channel();
+        let copied_client = self.client.clone();
+        let copied_location = location.clone();
+        spawn_local(async move {
+            let options = GetOptions {
+                range: Some(range),
+                ..Default::default()
+            };
+            let res = copied_client
+                .get_opts(&copied_location, options, Some(if_range))
+                .await;
+            sender.send(res).unwrap();
+        });
+
+        receiver.await.unwrap()
+    }
+
+    /// Put `payload` to `location`, subject to `precondition`
+    ///
+    /// Extends the [`PutMode`]s of `put_opts` with [`WritePrecondition::IfNoneMatch`].
+    /// A failed [`WritePrecondition::Create`] is reported as [`Error::AlreadyExists`],
+    /// any other failed precondition as [`Error::Precondition`].
+    pub async fn put_with_precondition(
+        &self,
+        location: &Path,
+        payload: object_store::PutPayload,
+        precondition: Option<WritePrecondition>,
+    ) -> Result<PutResult> {
+        let (sender, receiver) = oneshot::channel();
+        let copied_client = self.client.clone();
+        let cloned_location = location.clone();
+        spawn_local(async move {
+            let res = copied_client
+                .put(&cloned_location, payload.into(), precondition)
+                .await
+                .map(|response| response.headers().clone());
+            sender.send(res).unwrap();
+        });
+        let headers = receiver.await.unwrap()?;
+        let e_tag = match get_etag(&headers) {
+            Ok(e_tag) => Some(e_tag),
+            Err(HeaderError::MissingEtag) => None,
+            Err(source) => {
+                return Err(Error::Generic {
+                    store: InnerClient::STORE,
+                    source: Box::new(source),
+                })
+            }
+        };
+
+        Ok(PutResult {
+            e_tag,
+            version: None,
+        })
+    }
+
+    async fn copy_with_overwrite(&self, from: &Path, to: &Path, overwrite: bool) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        let copied_client = self.client.clone();
+        let (from, to) = (from.clone(), to.clone());
+        spawn_local(async move {
+            let res = copied_client.copy(&from, &to, overwrite).await;
+            sender.send(res).unwrap();
+        });
+        receiver.await.unwrap()
+    }
 }
 
 #[async_trait]
@@ -334,11 +1539,13 @@ impl ObjectStore for HttpStore {
         Err(Error::NotImplemented)
     }
 
-    async fn copy(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
-        todo!()
+    /// Copies with a WebDAV `COPY`, which servers without WebDAV support
+    /// reject with [`Error::NotSupported`]
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.copy_with_overwrite(from, to, true).await
     }
-    async fn copy_if_not_exists(&self, _from: &Path, _to: &Path) -> object_store::Result<()> {
-        todo!()
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.copy_with_overwrite(from, to, false).await
     }
     async fn delete(&self, _location: &Path) -> object_store::Result<()> {
         let (sender, receiver) = oneshot::channel();
@@ -350,6 +1557,21 @@ impl ObjectStore for HttpStore {
         });
         receiver.await.unwrap()
     }
+    /// Deletes with bounded concurrency, yielding each location's outcome in
+    /// order: a failed delete (e.g. of a missing key) doesn't end the stream
+    fn delete_stream<'a>(
+        &'a self,
+        locations: BoxStream<'a, object_store::Result<Path>>,
+    ) -> BoxStream<'a, object_store::Result<Path>> {
+        locations
+            .map(move |location| async move {
+                let location = location?;
+                self.delete(&location).await?;
+                Ok(location)
+            })
+            .buffered(self.client.delete_concurrency)
+            .boxed()
+    }
 
     async fn get_opts(
         &self,
@@ -360,7 +1582,9 @@ impl ObjectStore for HttpStore {
         let copied_client = self.client.clone();
         let copied_location = location.clone();
         spawn_local(async move {
-            let res = copied_client.get_opts(&copied_location, options).await;
+            let res = copied_client
+                .get_opts(&copied_location, options, None)
+                .await;
             sender.send(res).unwrap();
         });
 
@@ -368,40 +1592,65 @@ impl ObjectStore for HttpStore {
     }
     async fn put_opts(
         &self,
-        _location: &Path,
+        location: &Path,
         payload: object_store::PutPayload,
-        _options: object_store::PutOptions,
+        options: object_store::PutOptions,
     ) -> object_store::Result<object_store::PutResult> {
-        if _options.mode != PutMode::Overwrite {
-            // TODO: Add support for If header - https://datatracker.ietf.org/doc/html/rfc2518#section-9.4
-            return Err(Error::NotImplemented);
-        }
-
-        let response = self.client.put(_location, payload.into()).await?;
-        let e_tag = match get_etag(response.headers()) {
-            Ok(e_tag) => Some(e_tag),
-            Err(HeaderError::MissingEtag) => None,
-            Err(source) => {
-                return Err(Error::Generic {
-                    store: InnerClient::STORE,
-                    source: Box::new(source),
-                })
-            }
+        let precondition = match options.mode {
+            PutMode::Overwrite => None,
+            PutMode::Create => Some(WritePrecondition::Create),
+            PutMode::Update(version) => match version.e_tag {
+                Some(e_tag) => Some(WritePrecondition::IfMatch(e_tag)),
+                None => {
+                    return Err(Error::Generic {
+                        store: InnerClient::STORE,
+                        source: "ETag required for conditional update".into(),
+                    })
+                }
+            },
         };
-
-        Ok(PutResult {
-            e_tag,
-            version: None,
-        })
+        self.put_with_precondition(location, payload, precondition)
+            .await
     }
-    fn list(&self, _prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
-        todo!()
+
+    /// Lists the objects under `prefix` with a WebDAV `PROPFIND` (`Depth:
+    /// infinity`), issued once the stream is first polled
+    ///
+    /// Servers without WebDAV support fail with [`Error::NotSupported`], as do
+    /// some WebDAV servers, which refuse infinite depth requests.
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        let client = self.client.clone();
+        let prefix = prefix.cloned();
+        let entries = async move {
+            let (sender, receiver) = oneshot::channel();
+            spawn_local(async move {
+                let res = client.propfind(prefix.as_ref(), "infinity").await;
+                let _ = sender.send(res);
+            });
+            receiver.await.unwrap()
+        };
+        futures::stream::once(entries)
+            .map_ok(|entries| futures::stream::iter(dav_objects(entries).map(Ok)))
+            .try_flatten()
+            .boxed()
     }
+
+    /// Lists the objects and collections directly under `prefix` with a WebDAV
+    /// `PROPFIND` (`Depth: 1`), collections being the common prefixes
     async fn list_with_delimiter(
         &self,
-        _prefix: Option<&Path>,
+        prefix: Option<&Path>,
     ) -> object_store::Result<object_store::ListResult> {
-        todo!()
+        let client = self.client.clone();
+        let prefix = prefix.cloned().unwrap_or_default();
+        let (sender, receiver) = oneshot::channel();
+        let propfind_prefix = prefix.clone();
+        spawn_local(async move {
+            let res = client.propfind(Some(&propfind_prefix), "1").await;
+            let _ = sender.send(res);
+        });
+        let entries = receiver.await.unwrap()?;
+        Ok(dav_listing(&prefix, entries))
     }
 }
 impl Display for HttpStore {
@@ -409,3 +1658,913 @@ impl Display for HttpStore {
         write!(f, "{:?}", self.client)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_href_path() {
+        let client = InnerClient::new(Url::parse("https://example.com/dav").unwrap());
+        let url = client.collection_url(Some(&Path::from("dir")));
+        assert_eq!(url.as_str(), "https://example.com/dav/dir/");
+
+        let cases = [
+            ("https://example.com/dav/dir/a%20b.txt", Some("dir/a b.txt")),
+            ("/dav/dir/file.txt", Some("dir/file.txt")),
+            ("file.txt", Some("dir/file.txt")),
+            ("/dav/dir/sub/", Some("dir/sub")),
+            ("/dav/", Some("")),
+            ("/other/file.txt", None),
+        ];
+        for (href, expected) in cases {
+            let location = client.href_path(&url, href);
+            assert_eq!(location.as_ref().map(Path::as_ref), expected, "{href}");
+        }
+    }
+
+    #[test]
+    fn test_multistatus_objects() {
+        let client = InnerClient::new(Url::parse("https://example.com/dav/").unwrap());
+        let url = client.collection_url(None);
+        let body = r#"<multistatus xmlns="DAV:">
+  <response><href>/dav/</href><propstat><prop>
+    <resourcetype><collection/></resourcetype>
+  </prop></propstat></response>
+  <response><href>/dav/dir/</href><propstat><prop>
+    <resourcetype><collection/></resourcetype>
+  </prop></propstat></response>
+  <response><href>/dav/dir/a%26b.txt</href><propstat><prop>
+    <resourcetype/><getcontentlength>3</getcontentlength>
+  </prop></propstat></response>
+  <response><href>/elsewhere/c.txt</href><propstat><prop>
+    <resourcetype/>
+  </prop></propstat></response>
+</multistatus>"#;
+        let entries = client.multistatus_entries(&url, body).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        let objects = dav_objects(entries).collect::<Vec<_>>();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].location.as_ref(), "dir/a&b.txt");
+        assert_eq!(objects[0].size, 3);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_list_with_delimiter() {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<multistatus xmlns="DAV:">
+  <response><href>/dav/dir/</href><propstat><prop>
+    <resourcetype><collection/></resourcetype>
+  </prop></propstat></response>
+  <response><href>/dav/dir/sub/</href><propstat><prop>
+    <resourcetype><collection/></resourcetype>
+  </prop></propstat></response>
+  <response><href>/dav/dir/file.txt</href><propstat><prop>
+    <resourcetype/><getcontentlength>3</getcontentlength>
+    <getlastmodified>Sun, 06 Nov 1994 08:49:37 GMT</getlastmodified>
+  </prop></propstat></response>
+</multistatus>"#;
+        let response = format!(
+            "HTTP/1.1 207 Multi-Status\r\nContent-Type: application/xml\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let (url, requests) = serve(vec![response]).await;
+        let client = InnerClient::new(url.join("dav/").unwrap());
+        let prefix = Path::from("dir");
+
+        let entries = client.propfind(Some(&prefix), "1").await.unwrap();
+        let request = requests.lock().unwrap()[0].to_lowercase();
+        assert!(request.starts_with("propfind /dav/dir/ "), "{request}");
+        assert!(request.contains("\r\ndepth: 1\r\n"), "{request}");
+
+        // the listed collection itself is neither an object nor a prefix
+        let listing = dav_listing(&prefix, entries);
+        assert_eq!(listing.common_prefixes, [Path::from("dir/sub")]);
+        assert_eq!(listing.objects.len(), 1);
+        assert_eq!(listing.objects[0].location.as_ref(), "dir/file.txt");
+        assert_eq!(listing.objects[0].size, 3);
+    }
+
+    #[test]
+    fn test_weak_etags() {
+        assert_eq!(weak_etags("\"abc\""), "W/\"abc\"");
+        assert_eq!(weak_etags("abc"), "W/\"abc\"");
+        assert_eq!(weak_etags("W/\"abc\""), "W/\"abc\"");
+        assert_eq!(weak_etags("*"), "*");
+        assert_eq!(
+            weak_etags("\"abc\",W/\"def\" ,  ghi"),
+            "W/\"abc\", W/\"def\", W/\"ghi\""
+        );
+    }
+    #[test]
+    fn test_content_type_from_extension() {
+        let content_type = |location: &str| content_type_from_extension(&Path::from(location));
+        assert_eq!(content_type("data/file.json"), Some("application/json"));
+        assert_eq!(content_type("IMAGE.JPEG"), Some("image/jpeg"));
+        assert_eq!(
+            content_type("a.b/tiles.geojson"),
+            Some("application/geo+json")
+        );
+        assert_eq!(
+            content_type("table.parquet"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(content_type("archive.tar.gz"), Some("application/gzip"));
+        assert_eq!(content_type("a.b/no_extension"), None);
+        assert_eq!(content_type("file.unknown"), None);
+    }
+
+    /// Answer a connection with each of `responses` in turn on a local port,
+    /// recording each request, head and body
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) async fn serve<R>(responses: Vec<R>) -> (Url, Arc<Mutex<Vec<String>>>)
+    where
+        R: AsRef<[u8]> + Send + 'static,
+    {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                let head = loop {
+                    let read = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .filter_map(|line| line.split_once(':'))
+                            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                            .map_or(0, |(_, value)| value.trim().parse().unwrap());
+                        if body.len() >= length {
+                            break text;
+                        }
+                    }
+                    assert!(read > 0, "connection closed mid-request");
+                };
+                recorded.lock().unwrap().push(head);
+                stream.write_all(response.as_ref()).await.unwrap();
+            }
+        });
+        (Url::parse(&url).unwrap(), requests)
+    }
+
+    pub(super) fn head_options() -> GetOptions {
+        GetOptions {
+            head: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_suffix_range() {
+        let options = GetOptions {
+            range: Some(GetRange::Suffix(10)),
+            ..Default::default()
+        };
+        let request = Client::new()
+            .get("https://example.com/a.bin")
+            .with_get_options(options)
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[reqwest::header::RANGE], "bytes=-10");
+
+        // the last 10 bytes of a 100 byte object
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 90-99/100"));
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("10"));
+        let content_range = get_content_range(&headers).unwrap();
+        let meta = header_meta(&Path::from("a.bin"), &headers, InnerClient::HEADER_CONFIG).unwrap();
+        assert_eq!(meta.size, 100);
+        let suffix = Some(GetRange::Suffix(10));
+        assert_eq!(
+            resolve_range(content_range, suffix.clone(), meta.size, false),
+            90..100
+        );
+        // without a Content-Range, resolved against the size
+        assert_eq!(resolve_range(None, suffix.clone(), 100, false), 90..100);
+        assert_eq!(resolve_range(None, suffix.clone(), 4, false), 0..4);
+        // unless the object changed since an If-Range validator
+        assert_eq!(resolve_range(None, suffix, 100, true), 0..100);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_get_suffix() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 90-99/100\r\n\
+             Content-Length: 10\r\nConnection: close\r\n\r\n0123456789",
+        ])
+        .await;
+        let client = InnerClient::new(url);
+        let options = GetOptions {
+            range: Some(GetRange::Suffix(10)),
+            ..Default::default()
+        };
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let result = client
+                    .get_opts(&Path::from("a.bin"), options, None)
+                    .await
+                    .unwrap();
+                assert_eq!(result.range, 90..100);
+                assert_eq!(result.meta.size, 100);
+                assert_eq!(result.bytes().await.unwrap().as_ref(), b"0123456789");
+            })
+            .await;
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].to_lowercase().contains("range: bytes=-10"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_get_offset_whole_object() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-9/10\r\n\
+             Content-Length: 10\r\nConnection: close\r\n\r\n0123456789",
+        ])
+        .await;
+        let client = InnerClient::new(url);
+        let options = GetOptions {
+            range: Some(GetRange::Offset(0)),
+            ..Default::default()
+        };
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let result = client
+                    .get_opts(&Path::from("a.bin"), options, None)
+                    .await
+                    .unwrap();
+                assert_eq!(result.range, 0..10);
+                assert_eq!(result.meta.size, 10);
+                assert_eq!(result.bytes().await.unwrap().len(), 10);
+            })
+            .await;
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].to_lowercase().contains("range: bytes=0-\r\n"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_get_clamped_range() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\n\
+             Content-Length: 5\r\nConnection: close\r\n\r\n56789",
+        ])
+        .await;
+        let client = InnerClient::new(url);
+        let options = GetOptions {
+            range: Some(GetRange::Bounded(5..100)),
+            ..Default::default()
+        };
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let result = client
+                    .get_opts(&Path::from("a.bin"), options, None)
+                    .await
+                    .unwrap();
+                // what the server sent, not what was asked for
+                assert_eq!(result.range, 5..10);
+                assert_eq!(result.meta.size, 10);
+                assert_eq!(result.bytes().await.unwrap().as_ref(), b"56789");
+            })
+            .await;
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].to_lowercase().contains("range: bytes=5-99\r\n"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_get_resume() {
+        // each read is cut short after 5 of its 10 bytes, then resumed
+        let truncated = "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"v1\"\r\n\
+                         Connection: close\r\n\r\n01234";
+        let (url, requests) = serve(vec![
+            truncated,
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\n\
+             Content-Length: 5\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n56789",
+            truncated,
+            // If-Range no longer matching, the whole of the new version is sent
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"v2\"\r\n\
+             Connection: close\r\n\r\nabcdefghij",
+        ])
+        .await;
+        let client = InnerClient::new(url);
+        let location = Path::from("a.bin");
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let result = client.get_opts(&location, Default::default(), None);
+                let bytes = result.await.unwrap().bytes().await.unwrap();
+                assert_eq!(bytes.as_ref(), b"0123456789");
+
+                // the object changed in between, so the bytes would be a mix
+                let result = client.get_opts(&location, Default::default(), None);
+                let err = result.await.unwrap().bytes().await.unwrap_err();
+                assert!(matches!(err, Error::Precondition { .. }), "{err}");
+                assert!(
+                    err.to_string().contains("changed while being read"),
+                    "{err}"
+                );
+            })
+            .await;
+        let requests: Vec<_> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.to_lowercase())
+            .collect();
+        for resume in [&requests[1], &requests[3]] {
+            assert!(resume.contains("range: bytes=5-9\r\n"), "{resume}");
+            assert!(resume.contains("if-range: \"v1\"\r\n"), "{resume}");
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_delete_stream() {
+        let deleted = "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n";
+        let (url, requests) = serve(vec![
+            deleted,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            deleted,
+        ])
+        .await;
+        let store = HttpStore::new(url);
+        let locations = ["a.txt", "b.txt", "c.txt"].map(|location| Ok(Path::from(location)));
+
+        // deletes are made from local tasks
+        let results = tokio::task::LocalSet::new()
+            .run_until(async {
+                let locations = futures::stream::iter(locations).boxed();
+                store.delete_stream(locations).collect::<Vec<_>>().await
+            })
+            .await;
+        // which delete is answered 404 depends on the order they're made in,
+        // but it fails alone
+        assert_eq!(results.len(), 3);
+        let missing: Vec<_> = results.iter().filter(|result| result.is_err()).collect();
+        assert!(
+            matches!(missing[..], [Err(Error::NotFound { .. })]),
+            "{results:?}"
+        );
+        let requests = requests.lock().unwrap();
+        assert!(requests
+            .iter()
+            .all(|request| request.starts_with("DELETE /")));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let (url, _) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789",
+            // without a Content-Length, only the body itself gives it away
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-9/10\r\n\
+             Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+             a\r\n0123456789\r\n0\r\n\r\n",
+        ])
+        .await;
+        let mut client = InnerClient::new(url);
+        client.max_response_size = Some(4);
+        let location = Path::from("a.bin");
+        let too_large = "exceeds the configured maximum of 4 bytes";
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let result = client.get_opts(&location, Default::default(), None);
+                let err = result.await.unwrap_err();
+                assert!(err.to_string().contains(too_large), "{err}");
+
+                let options = GetOptions {
+                    range: Some(GetRange::Offset(0)),
+                    ..Default::default()
+                };
+                let result = client.get_opts(&location, options, None);
+                let err = result.await.unwrap().bytes().await.unwrap_err();
+                assert!(err.to_string().contains(too_large), "{err}");
+            })
+            .await;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_put_if_none_match() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 201 Created\r\nETag: \"v2\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 412 Precondition Failed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let store = HttpStore::new(url);
+        let location = Path::from("a.txt");
+        let precondition = || Some(WritePrecondition::IfNoneMatch("\"v1\"".to_string()));
+
+        // writes are made from local tasks
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let put = store.put_with_precondition(&location, "abc".into(), precondition());
+                assert_eq!(put.await.unwrap().e_tag.as_deref(), Some("\"v2\""));
+                // the object is at the version it shouldn't be
+                let put = store.put_with_precondition(&location, "abc".into(), precondition());
+                let err = put.await.unwrap_err();
+                assert!(matches!(err, Error::Precondition { .. }), "{err}");
+            })
+            .await;
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        for request in requests.iter() {
+            assert!(request.to_lowercase().contains("if-none-match: \"v1\"\r\n"));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_get_range_requires_partial_content() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 2-4/10\r\n\
+             Content-Length: 3\r\nConnection: close\r\n\r\n234",
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789",
+        ])
+        .await;
+        let client = InnerClient::new(url);
+        let location = Path::from("a.bin");
+        let options = || GetOptions {
+            range: Some(GetRange::Bounded(2..5)),
+            ..Default::default()
+        };
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let result = client.get_opts(&location, options(), None).await.unwrap();
+                assert_eq!(result.range, 2..5);
+                assert_eq!(result.bytes().await.unwrap().as_ref(), b"234");
+
+                // the whole object in answer to a ranged request
+                let err = client
+                    .get_opts(&location, options(), None)
+                    .await
+                    .unwrap_err();
+                assert!(matches!(err, Error::NotSupported { .. }), "{err}");
+            })
+            .await;
+        let requests = requests.lock().unwrap();
+        for request in requests.iter() {
+            assert!(request.to_lowercase().contains("range: bytes=2-4\r\n"));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_accept_ranges_none() {
+        let whole = "HTTP/1.1 200 OK\r\nAccept-Ranges: none\r\nContent-Length: 10\r\n\
+                     Connection: close\r\n\r\n0123456789";
+        let (url, _) = serve(vec![whole, whole]).await;
+        let mut client = InnerClient::new(url);
+        let location = Path::from("a.bin");
+        let options = || GetOptions {
+            range: Some(GetRange::Bounded(2..5)),
+            ..Default::default()
+        };
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let err = client
+                    .get_opts(&location, options(), None)
+                    .await
+                    .unwrap_err();
+                assert!(matches!(err, Error::NotSupported { .. }), "{err}");
+                assert!(err.to_string().contains("(Accept-Ranges: none)"), "{err}");
+
+                client.range_fallback = true;
+                let result = client.get_opts(&location, options(), None).await.unwrap();
+                assert_eq!(result.range, 2..5);
+                assert_eq!(result.bytes().await.unwrap().as_ref(), b"234");
+            })
+            .await;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_observer() {
+        use crate::telemetry::RequestEvent;
+
+        let (url, _) = serve(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let events = Arc::new(Mutex::new(Vec::<RequestEvent>::new()));
+        let recorded = events.clone();
+        let store = HttpStore::new(url)
+            .with_retry(2, Duration::ZERO)
+            .with_observer(RequestObserver::new(move |event| {
+                recorded.lock().unwrap().push(event)
+            }));
+        let location = Path::from("a.txt");
+
+        // the failed attempt is observed as well as its retry
+        let head = store.client.get_opts(&location, head_options(), None).await;
+        assert_eq!(head.unwrap().meta.size, 3);
+        store
+            .client
+            .put(&location, Bytes::from_static(b"abcd"), None)
+            .await
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        let observed = events
+            .iter()
+            .map(|event| (event.operation, event.bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(observed, [("head", 0), ("head", 0), ("put", 4)]);
+    }
+
+    #[test]
+    fn test_meta_cache() {
+        let meta = ObjectMeta {
+            location: Path::from("a.txt"),
+            last_modified: Utc.timestamp_nanos(0),
+            size: 3,
+            e_tag: Some("\"v1\"".to_string()),
+            version: None,
+        };
+        let mut attributes = Attributes::new();
+        attributes.insert(Attribute::ContentType, "text/plain".into());
+
+        let cache = MetaCache::new(chrono::Duration::seconds(60));
+        assert!(cache.get(&meta.location).is_none());
+        cache.insert(&meta, &attributes);
+        let (cached, cached_attributes) = cache.get(&meta.location).unwrap();
+        assert_eq!(cached, meta);
+        assert_eq!(cached_attributes, attributes);
+        cache.invalidate(&meta.location);
+        assert!(cache.get(&meta.location).is_none());
+
+        // entries expire after the TTL, and are then dropped
+        let expired = MetaCache::new(chrono::Duration::zero());
+        expired.insert(&meta, &attributes);
+        assert!(expired.get(&meta.location).is_none());
+        assert!(expired.entries.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_preconditions() {
+        let meta = ObjectMeta {
+            location: Path::from("a.txt"),
+            last_modified: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            size: 3,
+            e_tag: Some("\"v1\"".to_string()),
+            version: None,
+        };
+        let check = |options: GetOptions| check_preconditions(&options, &meta);
+        assert!(check(GetOptions::default()).is_ok());
+
+        for if_match in ["\"v1\"", "*", "\"v0\", \"v1\""] {
+            let options = GetOptions {
+                if_match: Some(if_match.to_string()),
+                ..Default::default()
+            };
+            assert!(check(options).is_ok(), "{if_match}");
+        }
+        let options = GetOptions {
+            if_match: Some("\"v0\"".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(check(options), Err(Error::Precondition { .. })));
+
+        for if_none_match in ["\"v1\"", "*", "\"v0\",\"v1\""] {
+            let options = GetOptions {
+                if_none_match: Some(if_none_match.to_string()),
+                ..Default::default()
+            };
+            assert!(
+                matches!(check(options), Err(Error::NotModified { .. })),
+                "{if_none_match}"
+            );
+        }
+
+        let before = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+        let options = GetOptions {
+            if_unmodified_since: Some(before),
+            ..Default::default()
+        };
+        assert!(matches!(check(options), Err(Error::Precondition { .. })));
+        let options = GetOptions {
+            if_modified_since: Some(meta.last_modified),
+            ..Default::default()
+        };
+        assert!(matches!(check(options), Err(Error::NotModified { .. })));
+        let options = GetOptions {
+            if_modified_since: Some(before),
+            ..Default::default()
+        };
+        assert!(check(options).is_ok());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_meta_cache_requests() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nETag: \"v1\"\r\n\
+             Last-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\nContent-Type: text/plain\r\n\
+             Connection: close\r\n\r\n",
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nETag: \"v2\"\r\n\
+             Last-Modified: Mon, 07 Nov 1994 08:49:37 GMT\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let mut client = InnerClient::new(url);
+        client.meta_cache = Some(Arc::new(MetaCache::new(chrono::Duration::seconds(60))));
+        let location = Path::from("a.txt");
+
+        let head = client
+            .get_opts(&location, head_options(), None)
+            .await
+            .unwrap();
+        assert_eq!(head.meta.e_tag.as_deref(), Some("\"v1\""));
+        assert_eq!(head.range, 0..3);
+
+        // answered from the cache, attributes and all
+        let cached = client
+            .get_opts(&location, head_options(), None)
+            .await
+            .unwrap();
+        assert_eq!(cached.meta, head.meta);
+        assert_eq!(cached.range, 0..3);
+        assert_eq!(
+            cached.attributes.get(&Attribute::ContentType),
+            Some(&"text/plain".into())
+        );
+        let revalidate = GetOptions {
+            if_none_match: Some("\"v1\"".to_string()),
+            ..Default::default()
+        };
+        let err = client.get_opts(&location, revalidate, None).await;
+        assert!(matches!(err, Err(Error::NotModified { .. })));
+        let conditional = GetOptions {
+            if_match: Some("\"v0\"".to_string()),
+            ..head_options()
+        };
+        let err = client.get_opts(&location, conditional, None).await;
+        assert!(matches!(err, Err(Error::Precondition { .. })));
+        let beyond = GetOptions {
+            range: Some((5..8).into()),
+            ..Default::default()
+        };
+        let err = client.get_opts(&location, beyond, None).await.unwrap_err();
+        assert!(err.to_string().contains("beyond its size"), "{err}");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // a put invalidates the entry
+        client
+            .put(&location, Bytes::from_static(b"next"), None)
+            .await
+            .unwrap();
+        let head = client
+            .get_opts(&location, head_options(), None)
+            .await
+            .unwrap();
+        assert_eq!(head.meta.e_tag.as_deref(), Some("\"v2\""));
+        assert_eq!(head.range, 0..4);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+
+        // as does a delete
+        client.delete(&location).await.unwrap();
+        assert!(client.meta_cache.as_ref().unwrap().get(&location).is_none());
+        let requests = requests.lock().unwrap();
+        assert!(requests[1].starts_with("PUT /a.txt "), "{}", requests[1]);
+        assert!(requests[3].starts_with("DELETE /a.txt "), "{}", requests[3]);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_put_redirect() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: /moved/a.txt\r\n\
+             Content-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 302 Found\r\nLocation: /moved/a.txt\r\n\
+             Content-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let client = InnerClient::new(url);
+        let location = Path::from("a.txt");
+
+        // re-sent, body and all, to the new location
+        client
+            .put(&location, Bytes::from_static(b"abc"), None)
+            .await
+            .unwrap();
+        // but not where the redirect would turn the PUT into a GET
+        let err = client
+            .put(&location, Bytes::from_static(b"abc"), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("302"), "{err}");
+
+        let requests = requests.lock().unwrap();
+        let request_lines: Vec<_> = requests.iter().filter_map(|r| r.lines().next()).collect();
+        assert_eq!(
+            request_lines,
+            [
+                "PUT /a.txt HTTP/1.1",
+                "PUT /moved/a.txt HTTP/1.1",
+                "PUT /a.txt HTTP/1.1"
+            ]
+        );
+        assert!(requests[1].to_lowercase().contains("content-length: 3"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_put() {
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\
+                           Connection: close\r\n\r\n";
+        let (url, requests) = serve(vec![
+            unavailable,
+            "HTTP/1.1 201 Created\r\nETag: \"v1\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let store = HttpStore::new(url);
+        let location = Path::from("a.txt");
+
+        // writes are made from local tasks
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                // the transient failure is retried
+                let result = store.put(&location, "abc".into()).await.unwrap();
+                assert_eq!(result.e_tag.as_deref(), Some("\"v1\""));
+                let err = store.put(&location, "abc".into()).await.unwrap_err();
+                assert!(matches!(err, Error::PermissionDenied { .. }), "{err}");
+            })
+            .await;
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        for request in requests.iter() {
+            assert!(request.starts_with("PUT /a.txt HTTP/1.1"));
+            assert!(request.ends_with("\r\n\r\nabc"));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_copy() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 412 Precondition Failed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let store = HttpStore::new(url.clone());
+        let (from, to) = (Path::from("a.txt"), Path::from("b/c.txt"));
+
+        // writes are made from local tasks
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                store.copy(&from, &to).await.unwrap();
+                store.copy_if_not_exists(&from, &to).await.unwrap();
+                // the destination exists
+                let err = store.copy_if_not_exists(&from, &to).await.unwrap_err();
+                assert!(matches!(err, Error::AlreadyExists { .. }), "{err}");
+            })
+            .await;
+        let requests = requests.lock().unwrap();
+        let destination = format!("destination: {url}b/c.txt\r\n");
+        for (request, overwrite) in requests.iter().zip(["T", "F", "F"]) {
+            let request = request.to_lowercase();
+            assert!(request.starts_with("copy /a.txt http/1.1"), "{request}");
+            assert!(request.contains(&destination), "{request}");
+            let overwrite = format!("overwrite: {}\r\n", overwrite.to_lowercase());
+            assert!(request.contains(&overwrite), "{request}");
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_put_empty() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nETag: \"v1\"\r\n\
+             Last-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let client = InnerClient::new(url);
+        let location = Path::from("empty.bin");
+
+        client.put(&location, Bytes::new(), None).await.unwrap();
+        let head = client
+            .get_opts(&location, head_options(), None)
+            .await
+            .unwrap();
+        assert_eq!(head.meta.size, 0);
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("PUT /empty.bin HTTP/1.1"));
+        assert!(requests[0].to_lowercase().contains("content-length: 0"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_meta_cache_partial_get() {
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let (url, requests) = serve(vec![
+                    "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nETag: \"v1\"\r\n\
+                     Last-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\nConnection: close\r\n\r\n",
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-3/*\r\n\
+                     Content-Length: 4\r\nETag: \"v1\"\r\n\
+                     Last-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\nConnection: close\r\n\r\nabcd",
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-3/*\r\n\
+                     Content-Length: 4\r\nETag: \"v2\"\r\n\
+                     Last-Modified: Mon, 07 Nov 1994 08:49:37 GMT\r\nConnection: close\r\n\r\nwxyz",
+                ])
+                .await;
+                let mut client = InnerClient::new(url);
+                client.meta_cache = Some(Arc::new(MetaCache::new(chrono::Duration::seconds(60))));
+                let location = Path::from("a.txt");
+                let first_bytes = || GetOptions {
+                    range: Some((0..4).into()),
+                    ..Default::default()
+                };
+
+                client
+                    .get_opts(&location, head_options(), None)
+                    .await
+                    .unwrap();
+                // the range's response lacks the object's size, which the cached meta
+                // of the same version has
+                let result = client
+                    .get_opts(&location, first_bytes(), None)
+                    .await
+                    .unwrap();
+                assert_eq!(result.meta.size, 10);
+                assert_eq!(result.range, 0..4);
+                assert_eq!(result.bytes().await.unwrap().as_ref(), b"abcd");
+                assert_eq!(requests.lock().unwrap().len(), 2);
+
+                // but not that of another version
+                let result = client
+                    .get_opts(&location, first_bytes(), None)
+                    .await
+                    .unwrap();
+                assert_eq!(result.meta.e_tag.as_deref(), Some("\"v2\""));
+                assert_eq!(result.meta.size, 4);
+            })
+            .await;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_accept_headers() {
+        let (url, requests) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nContent-Encoding: gzip\r\n\
+             Connection: close\r\n\r\ngzip",
+        ])
+        .await;
+        let store = HttpStore::new(url)
+            .with_accept("application/json")
+            .with_accept_encoding("gzip");
+
+        // the body is forwarded from a local task
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let result = store
+                    .client
+                    .get_opts(&Path::from("a.json"), GetOptions::default(), None)
+                    .await
+                    .unwrap();
+                // passed through as encoded, with the encoding reported
+                assert_eq!(
+                    result.attributes.get(&Attribute::ContentEncoding),
+                    Some(&"gzip".into())
+                );
+                assert_eq!(result.bytes().await.unwrap().as_ref(), b"gzip");
+            })
+            .await;
+        let request = requests.lock().unwrap()[0].to_lowercase();
+        assert!(request.contains("accept: application/json"), "{request}");
+        assert!(request.contains("accept-encoding: gzip"), "{request}");
+    }
+}
@@ -0,0 +1,201 @@
+//! Parsing of WebDAV `PROPFIND` multistatus responses (RFC 4918)
+
+use chrono::{DateTime, TimeZone, Utc};
+use object_store::{path::Path, ObjectMeta};
+use snafu::{ResultExt, Snafu};
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+use crate::utils::{parse_http_date, unescape_xml};
+
+/// The body of a `PROPFIND` request for the properties of [`DavEntry`]
+pub(crate) const PROPFIND_BODY: &str = concat!(
+    r#"<?xml version="1.0" encoding="utf-8"?>"#,
+    r#"<D:propfind xmlns:D="DAV:"><D:prop>"#,
+    "<D:resourcetype/><D:getlastmodified/><D:getcontentlength/><D:getetag/>",
+    "</D:prop></D:propfind>"
+);
+
+#[derive(Debug, Snafu)]
+pub(crate) enum MultistatusError {
+    #[snafu(display("Invalid multistatus XML: {}", source))]
+    InvalidXml { source: xmlparser::Error },
+
+    #[snafu(display("Multistatus response without an href"))]
+    MissingHref,
+}
+
+/// A resource reported by a multistatus response
+#[derive(Debug, Default)]
+pub(crate) struct DavEntry {
+    /// The (percent-encoded) URL of the resource, typically an absolute path
+    pub href: String,
+    /// Whether the resource is a collection (directory)
+    pub is_collection: bool,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub content_length: Option<usize>,
+    pub e_tag: Option<String>,
+}
+
+impl DavEntry {
+    /// The last modified date, the epoch where the server didn't report one
+    pub fn last_modified_or_epoch(&self) -> DateTime<Utc> {
+        self.last_modified.unwrap_or_else(|| Utc.timestamp_nanos(0))
+    }
+
+    /// The metadata of the (non-collection) resource at `location`
+    pub fn into_object_meta(self, location: Path) -> ObjectMeta {
+        ObjectMeta {
+            location,
+            last_modified: self.last_modified_or_epoch(),
+            size: self.content_length.unwrap_or(0),
+            e_tag: self.e_tag,
+            version: None,
+        }
+    }
+}
+
+/// The text content of the properties of an entry, as it's parsed
+#[derive(Default)]
+struct RawEntry {
+    href: String,
+    is_collection: bool,
+    last_modified: String,
+    content_length: String,
+    e_tag: String,
+}
+
+impl RawEntry {
+    fn finish(self) -> Result<DavEntry, MultistatusError> {
+        let href = unescape_xml(self.href.trim());
+        if href.is_empty() {
+            return MissingHrefSnafu.fail();
+        }
+        let non_empty = |value: String| Some(unescape_xml(value.trim())).filter(|v| !v.is_empty());
+        Ok(DavEntry {
+            href,
+            is_collection: self.is_collection,
+            last_modified: non_empty(self.last_modified).and_then(|v| parse_http_date(&v)),
+            content_length: non_empty(self.content_length).and_then(|v| v.parse().ok()),
+            e_tag: non_empty(self.e_tag),
+        })
+    }
+}
+
+/// Parse the `<response>` entries of a multistatus `body`
+///
+/// Elements are matched by local name, whatever namespace prefix the server
+/// uses for `DAV:`. Properties a server can't report (e.g. the length of a
+/// collection) are `None`.
+pub(crate) fn parse_multistatus(body: &str) -> Result<Vec<DavEntry>, MultistatusError> {
+    let mut entries = Vec::new();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut entry: Option<RawEntry> = None;
+    for token in Tokenizer::from(body) {
+        match token.context(InvalidXmlSnafu)? {
+            Token::ElementStart { local, .. } => {
+                let local = local.as_str();
+                match local {
+                    "response" => entry = Some(RawEntry::default()),
+                    "collection" if stack.contains(&"resourcetype") => {
+                        if let Some(entry) = entry.as_mut() {
+                            entry.is_collection = true;
+                        }
+                    }
+                    _ => {}
+                }
+                stack.push(local);
+            }
+            Token::ElementEnd {
+                end: ElementEnd::Empty | ElementEnd::Close(..),
+                ..
+            } => {
+                let closed = stack.pop();
+                if closed == Some("response") {
+                    entries.extend(entry.take().map(RawEntry::finish).transpose()?);
+                }
+            }
+            Token::Text { text } | Token::Cdata { text, .. } => {
+                let entry = match entry.as_mut() {
+                    Some(entry) => entry,
+                    None => continue,
+                };
+                let field = match stack.as_slice() {
+                    [.., "response", "href"] => &mut entry.href,
+                    [.., "getlastmodified"] => &mut entry.last_modified,
+                    [.., "getcontentlength"] => &mut entry.content_length,
+                    [.., "getetag"] => &mut entry.e_tag,
+                    _ => continue,
+                };
+                field.push_str(text.as_str());
+            }
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/dav/</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype><d:collection/></d:resourcetype>
+    </d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/a%20b&amp;c.txt</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype/>
+      <d:getlastmodified>Sun, 06 Nov 1994 08:49:37 GMT</d:getlastmodified>
+      <d:getcontentlength>12</d:getcontentlength>
+      <d:getetag>&quot;abc&quot;</d:getetag>
+    </d:prop></d:propstat>
+  </d:response>
+  <response xmlns="DAV:">
+    <href>https://example.com/dav/bare.txt</href>
+    <propstat><prop><resourcetype/></prop></propstat>
+  </response>
+</d:multistatus>"#;
+        let entries = parse_multistatus(body).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].href, "/dav/");
+        assert!(entries[0].is_collection);
+        assert_eq!(entries[0].content_length, None);
+
+        assert_eq!(entries[1].href, "/dav/a%20b&c.txt");
+        assert!(!entries[1].is_collection);
+        assert_eq!(entries[1].content_length, Some(12));
+        assert_eq!(entries[1].e_tag.as_deref(), Some("\"abc\""));
+        assert_eq!(
+            entries[1].last_modified,
+            Some(Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 37).unwrap())
+        );
+
+        assert_eq!(entries[2].href, "https://example.com/dav/bare.txt");
+        assert_eq!(entries[2].last_modified, None);
+        assert_eq!(entries[2].last_modified_or_epoch(), Utc.timestamp_nanos(0));
+    }
+
+    #[test]
+    fn test_parse_multistatus_errors() {
+        let missing_href =
+            r#"<multistatus xmlns="DAV:"><response><href> </href></response></multistatus>"#;
+        assert!(matches!(
+            parse_multistatus(missing_href),
+            Err(MultistatusError::MissingHref)
+        ));
+
+        let invalid = r#"<multistatus xmlns="DAV:"><response><href>/a</hr"#;
+        assert!(matches!(
+            parse_multistatus(invalid),
+            Err(MultistatusError::InvalidXml { .. })
+        ));
+    }
+}
@@ -1,13 +1,24 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use crate::parse::parse_url_opts as _parse_url_opts;
+use crate::telemetry::RequestEvent;
+use crate::utils::{multipart_part_size, EXPIRES_ATTRIBUTE, MIN_PART_SIZE};
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures::stream::StreamExt;
+use futures::stream::{BoxStream, LocalBoxStream, StreamExt, TryStreamExt};
 use js_sys::Object;
 use object_store::path::Path;
-use object_store::{GetOptions, GetRange, ObjectStore};
+use object_store::{
+    Attribute, Attributes, GetOptions, GetRange, GetResult, MultipartUpload, ObjectStore, PutMode,
+    PutMultipartOpts, PutOptions, PutResult, UpdateVersion,
+};
 use url::Url;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+/// Options for [`WasmObjectStore::get`] and its variants
 #[derive(Debug, Default)]
 #[wasm_bindgen]
 pub struct WasmGetOptions {
@@ -20,6 +31,57 @@ pub struct WasmGetOptions {
     head: bool,
 }
 
+#[wasm_bindgen]
+impl WasmGetOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request bytes `start` up to, but excluding, `end`
+    pub fn set_range_bounded(&mut self, start: usize, end: usize) -> Result<(), JsError> {
+        if start >= end {
+            return Err(JsError::new(&format!(
+                "invalid range {start}..{end}, the end must exceed the start"
+            )));
+        }
+        self.range = Some(GetRange::Bounded(start..end));
+        Ok(())
+    }
+
+    /// Request the bytes from `offset` to the end of the object
+    pub fn set_range_offset(&mut self, offset: usize) {
+        self.range = Some(GetRange::Offset(offset));
+    }
+
+    /// Request the last `length` bytes of the object
+    pub fn set_range_suffix(&mut self, length: usize) {
+        self.range = Some(GetRange::Suffix(length));
+    }
+
+    /// Request the whole object (the default)
+    pub fn clear_range(&mut self) {
+        self.range = None;
+    }
+
+    /// Only fetch the object if it matches `e_tag` (`If-Match`), failing with
+    /// a `Precondition` error otherwise
+    pub fn set_if_match(&mut self, e_tag: Option<String>) {
+        self.if_match = e_tag;
+    }
+
+    /// Only fetch the object if it doesn't match `e_tag` (`If-None-Match`),
+    /// failing with a `NotModified` error otherwise
+    pub fn set_if_none_match(&mut self, e_tag: Option<String>) {
+        self.if_none_match = e_tag;
+    }
+
+    /// Only fetch the object's metadata, not its body
+    pub fn set_head(&mut self, head: bool) {
+        self.head = head;
+    }
+}
+
 impl From<WasmGetOptions> for GetOptions {
     fn from(value: WasmGetOptions) -> Self {
         GetOptions {
@@ -34,8 +96,78 @@ impl From<WasmGetOptions> for GetOptions {
     }
 }
 
+/// Options for [`WasmObjectStore::put`]
+#[derive(Debug, Default)]
+#[wasm_bindgen]
+pub struct WasmPutOptions {
+    mode: PutMode,
+    content_type: Option<String>,
+}
+
+#[wasm_bindgen]
+impl WasmPutOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace any existing object (the default)
+    pub fn set_mode_overwrite(&mut self) {
+        self.mode = PutMode::Overwrite;
+    }
+
+    /// Only create the object, failing should it already exist
+    pub fn set_mode_create(&mut self) {
+        self.mode = PutMode::Create;
+    }
+
+    /// Only replace the object if it still matches `e_tag` (and `version`,
+    /// where the store is versioned), e.g. as returned by a previous `put`
+    pub fn set_mode_update(&mut self, e_tag: Option<String>, version: Option<String>) {
+        self.mode = PutMode::Update(UpdateVersion { e_tag, version });
+    }
+
+    /// The `Content-Type` the object is served with
+    pub fn set_content_type(&mut self, content_type: Option<String>) {
+        self.content_type = content_type;
+    }
+}
+
+impl From<WasmPutOptions> for PutOptions {
+    fn from(value: WasmPutOptions) -> Self {
+        let mut attributes = Attributes::new();
+        if let Some(content_type) = value.content_type {
+            attributes.insert(Attribute::ContentType, content_type.into());
+        }
+        PutOptions {
+            mode: value.mode,
+            attributes,
+            ..Default::default()
+        }
+    }
+}
+
+/// The outcome of [`WasmObjectStore::put`]
 #[derive(Debug)]
 #[wasm_bindgen(getter_with_clone, inspectable)]
+pub struct WasmPutResult {
+    /// The ETag of the written object, where the store reports one
+    pub e_tag: Option<String>,
+    /// The version of the written object, for versioned stores
+    pub version: Option<String>,
+}
+
+impl From<PutResult> for WasmPutResult {
+    fn from(value: PutResult) -> Self {
+        Self {
+            e_tag: value.e_tag,
+            version: value.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone, inspectable)]
 pub struct WasmObjectMeta {
     /// The full path to the object
     pub location: String,
@@ -63,10 +195,486 @@ impl From<object_store::ObjectMeta> for WasmObjectMeta {
     }
 }
 
+/// A streamed object, see [`WasmObjectStore::get_with_meta`]
+#[wasm_bindgen(getter_with_clone)]
+pub struct WasmGetResult {
+    /// The body, a `ReadableStream` of `Uint8Array` chunks
+    pub stream: wasm_streams::readable::sys::ReadableStream,
+    /// The metadata of the object as a whole, e.g. its total size
+    pub meta: WasmObjectMeta,
+    /// The first byte of the object being streamed
+    pub range_start: usize,
+    /// The end (exclusive) of the bytes being streamed
+    pub range_end: usize,
+}
+
+/// A line of [`WasmObjectStore::list_ndjson`] output
+#[derive(serde::Serialize)]
+struct NdjsonObjectMeta<'a> {
+    location: &'a str,
+    /// RFC 3339
+    last_modified: String,
+    size: usize,
+    e_tag: Option<&'a str>,
+    version: Option<&'a str>,
+}
+
+impl<'a> From<&'a object_store::ObjectMeta> for NdjsonObjectMeta<'a> {
+    fn from(value: &'a object_store::ObjectMeta) -> Self {
+        Self {
+            location: value.location.as_ref(),
+            last_modified: value.last_modified.to_rfc3339(),
+            size: value.size,
+            e_tag: value.e_tag.as_deref(),
+            version: value.version.as_deref(),
+        }
+    }
+}
+
+/// The default cap on the number of objects [`WasmObjectStore::list_all`] collects
+const DEFAULT_LIST_ALL_MAX: usize = 10_000;
+
+fn ndjson_chunk(
+    batch: Vec<object_store::Result<object_store::ObjectMeta>>,
+) -> Result<JsValue, JsValue> {
+    let mut chunk = String::new();
+    for meta in batch {
+        let meta = meta.map_err(JsError::from)?;
+        let line = serde_json::to_string(&NdjsonObjectMeta::from(&meta)).map_err(JsError::from)?;
+        chunk.push_str(&line);
+        chunk.push('\n');
+    }
+    Ok(chunk.into())
+}
+
+/// A single page of a listing, see [`WasmObjectStore::list_page`]
+#[cfg(feature = "aws")]
+#[derive(Debug)]
+#[wasm_bindgen(getter_with_clone, inspectable)]
+pub struct WasmListPage {
+    /// The [`WasmObjectMeta`] of the objects listed in this page
+    pub objects: js_sys::Array,
+    /// The number of keys returned in this page
+    pub key_count: usize,
+    /// Whether further pages follow
+    pub is_truncated: bool,
+    /// The token to pass to `list_page` for the next page, if any
+    pub next_continuation_token: Option<String>,
+    /// The [`WasmObjectOwner`] of each object, by location, for stores built
+    /// with `fetch_owner` (`aws_fetch_owner` in URL options)
+    pub owners: js_sys::Map,
+}
+
+/// The owner of a listed object, see [`WasmListPage`]
+#[cfg(feature = "aws")]
+#[derive(Debug)]
+#[wasm_bindgen(getter_with_clone, inspectable)]
+pub struct WasmObjectOwner {
+    /// The canonical ID of the owning account
+    pub id: Option<String>,
+    /// The owner's display name, which S3 only reports in some regions
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug)]
+#[wasm_bindgen(getter_with_clone, inspectable)]
+pub struct WasmRequestEvent {
+    /// The kind of operation, e.g. `get`, `list`
+    pub operation: String,
+    /// The number of payload bytes uploaded or downloaded, where known
+    pub bytes: usize,
+    /// Time taken, in milliseconds
+    pub duration: f64,
+}
+
+impl From<RequestEvent> for WasmRequestEvent {
+    fn from(value: RequestEvent) -> Self {
+        Self {
+            operation: value.operation.to_string(),
+            bytes: value.bytes,
+            duration: value.duration.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+fn notify(
+    observer: Option<&js_sys::Function>,
+    operation: &'static str,
+    bytes: usize,
+    started: DateTime<Utc>,
+) {
+    if let Some(observer) = observer {
+        let event = RequestEvent {
+            operation,
+            bytes,
+            duration: (Utc::now() - started).to_std().unwrap_or_default(),
+        };
+        let _ = observer.call1(&JsValue::NULL, &WasmRequestEvent::from(event).into());
+    }
+}
+
+/// Notifies its observer of a `list` request once dropped, i.e. once the
+/// listing it's part of ends or is abandoned
+struct ListNotification<'a> {
+    observer: Option<&'a js_sys::Function>,
+    started: DateTime<Utc>,
+}
+
+impl Drop for ListNotification<'_> {
+    fn drop(&mut self) {
+        notify(self.observer, "list", 0, self.started);
+    }
+}
+
+/// What listings need of a [`WasmObjectStore`], owned so that they can be
+/// driven from local tasks
+struct Lister {
+    inner: Arc<dyn ObjectStore>,
+    observer: Option<js_sys::Function>,
+    #[cfg(feature = "aws")]
+    s3: Option<Arc<crate::aws::AmazonS3>>,
+}
+
+impl Lister {
+    /// The objects under `prefix`, notifying the observer of each request:
+    /// every page of S3 listings (each requested once the previous has been
+    /// consumed), or the listings of other stores as a whole
+    fn list<'a>(
+        &'a self,
+        prefix: Option<&'a Path>,
+    ) -> LocalBoxStream<'a, object_store::Result<object_store::ObjectMeta>> {
+        #[cfg(feature = "aws")]
+        if let Some(s3) = &self.s3 {
+            // `None` once the last page has been listed, otherwise the
+            // continuation token of the next page (`None` for the first)
+            let pages = futures::stream::try_unfold(
+                Some(None),
+                move |token: Option<Option<String>>| async move {
+                    let token = match token {
+                        Some(token) => token,
+                        None => return Ok::<_, object_store::Error>(None),
+                    };
+                    let started = Utc::now();
+                    let page = s3.list_page(prefix, token).await;
+                    notify(self.observer.as_ref(), "list", 0, started);
+                    let page = page?;
+                    let next = match page.is_truncated {
+                        true => page.next_continuation_token.map(Some),
+                        false => None,
+                    };
+                    let objects = page.objects.into_iter().map(Ok);
+                    Ok(Some((futures::stream::iter(objects), next)))
+                },
+            );
+            return pages.try_flatten().boxed_local();
+        }
+        let notification = ListNotification {
+            observer: self.observer.as_ref(),
+            started: Utc::now(),
+        };
+        self.inner
+            .list(prefix)
+            .map(move |meta| {
+                let _ = &notification;
+                meta
+            })
+            .boxed_local()
+    }
+}
+
+/// The body of `res` as a `ReadableStream` of `Uint8Array` chunks
+///
+/// A body failing part way (e.g. the connection dropping) errors the stream,
+/// rejecting the reader's pending `read()`.
+fn readable_stream(res: GetResult) -> wasm_streams::readable::sys::ReadableStream {
+    let intermediate_stream = res.into_stream().map(|chunk| {
+        let inner_chunk = chunk.map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(js_sys::Uint8Array::from(inner_chunk.as_ref()).into())
+    });
+    wasm_streams::ReadableStream::from_stream(intermediate_stream).into_raw()
+}
+
+/// Fail with a descriptive error where the `ReadableStream` global is missing
+/// (e.g. some worker runtimes), rather than panicking during stream construction
+fn ensure_readable_stream() -> Result<(), JsError> {
+    match js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("ReadableStream")) {
+        Ok(true) => Ok(()),
+        _ => Err(JsError::new(
+            "ReadableStream is not available in this environment, streaming results require the WHATWG Streams API",
+        )),
+    }
+}
+
+/// A streaming multipart upload, created by [`WasmObjectStore::put_multipart`]
+///
+/// Callers should always finish with either `close` or `abort`: parts of an
+/// incomplete upload are retained (and billed) by the store. As a fallback,
+/// an upload that is freed (explicitly via `free`, or by a finalizer where
+/// wasm-bindgen weak references are enabled) before completing is aborted.
+#[wasm_bindgen]
+pub struct WasmMultipartUpload {
+    inner: Option<Box<dyn MultipartUpload>>,
+    buffer: Vec<u8>,
+    part_size: usize,
+}
+
+impl WasmMultipartUpload {
+    fn upload(&mut self) -> Result<&mut Box<dyn MultipartUpload>, JsError> {
+        self.inner
+            .as_mut()
+            .ok_or_else(|| JsError::new("multipart upload has already been closed or aborted"))
+    }
+}
+
+#[wasm_bindgen]
+impl WasmMultipartUpload {
+    /// Upload the next part, parts are assembled in the order they were added
+    #[wasm_bindgen]
+    pub async fn put_part(&mut self, data: js_sys::Uint8Array) -> Result<(), JsError> {
+        let payload = bytes::Bytes::from(data.to_vec()).into();
+        self.upload()?.put_part(payload).await?;
+        Ok(())
+    }
+    /// Buffer `data`, uploading a part each time the buffer reaches the part size
+    ///
+    /// Unlike `put_part`, chunks of any size may be written, the remainder is
+    /// uploaded as the final part on `close`.
+    #[wasm_bindgen]
+    pub async fn write(&mut self, data: js_sys::Uint8Array) -> Result<(), JsError> {
+        self.buffer.extend(data.to_vec());
+        while self.buffer.len() >= self.part_size {
+            let rest = self.buffer.split_off(self.part_size);
+            let part = std::mem::replace(&mut self.buffer, rest);
+            self.upload()?.put_part(part.into()).await?;
+        }
+        Ok(())
+    }
+    /// Complete the upload, making the object visible
+    #[wasm_bindgen]
+    pub async fn close(&mut self) -> Result<(), JsError> {
+        if !self.buffer.is_empty() {
+            let part = std::mem::take(&mut self.buffer);
+            self.upload()?.put_part(part.into()).await?;
+        }
+        self.upload()?.complete().await?;
+        self.inner = None;
+        Ok(())
+    }
+    /// Abort the upload, discarding any uploaded parts
+    #[wasm_bindgen]
+    pub async fn abort(&mut self) -> Result<(), JsError> {
+        self.upload()?.abort().await?;
+        self.inner = None;
+        Ok(())
+    }
+}
+
+impl Drop for WasmMultipartUpload {
+    fn drop(&mut self) {
+        if let Some(mut upload) = self.inner.take() {
+            spawn_local(async move {
+                let _ = upload.abort().await;
+            });
+        }
+    }
+}
+
+/// S3 configuration for [`WasmObjectStore::from_s3_config`]
+#[cfg(feature = "aws")]
+#[derive(Debug, Default)]
+#[wasm_bindgen(getter_with_clone, inspectable)]
+pub struct WasmS3BuilderOptions {
+    pub bucket: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    /// e.g. `http://localhost:9000` for MinIO
+    pub endpoint: Option<String>,
+    /// Report the owner of each object listed by `list_page`, see
+    /// [`WasmListPage::owners`]
+    pub fetch_owner: bool,
+}
+
+#[cfg(feature = "aws")]
+#[wasm_bindgen]
+impl WasmS3BuilderOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
 #[wasm_bindgen]
 pub struct WasmObjectStore {
     inner: Arc<dyn ObjectStore>,
     base_path: Option<object_store::path::Path>,
+    observer: Option<js_sys::Function>,
+    relative_locations: bool,
+    cache: Option<ResponseCache>,
+    #[cfg(feature = "aws")]
+    s3: Option<Arc<crate::aws::AmazonS3>>,
+}
+
+/// Bodies of whole objects, keyed by location, along with the ETag they were
+/// fetched with
+type ResponseCache = Mutex<HashMap<Path, (String, Bytes)>>;
+
+/// Whether `options` request the whole of the current version of an object,
+/// unconditionally, i.e. a response the response cache can stand in for
+fn is_whole_object(options: &GetOptions) -> bool {
+    options.if_match.is_none()
+        && options.if_none_match.is_none()
+        && options.if_modified_since.is_none()
+        && options.if_unmodified_since.is_none()
+        && options.range.is_none()
+        && options.version.is_none()
+        && !options.head
+}
+
+impl WasmObjectStore {
+    fn lister(&self) -> Lister {
+        Lister {
+            inner: self.inner.clone(),
+            observer: self.observer.clone(),
+            #[cfg(feature = "aws")]
+            s3: self.s3.clone(),
+        }
+    }
+
+    fn resolve(&self, location: &str) -> Result<Path, wasm_bindgen::JsError> {
+        Ok(self.resolve_path(location)?)
+    }
+
+    fn resolve_path(&self, location: &str) -> object_store::Result<Path> {
+        // query parameters will be interpreted as literal parts of the path,
+        // and url encoded
+        let converted_path = Path::from_url_path(location)?;
+        Ok(match &self.base_path {
+            Some(path) => Path::from_url_path(format!("{}/{}", path, converted_path))?,
+            None => converted_path,
+        })
+    }
+
+    /// The underlying store, for Rust code compiled alongside this crate
+    ///
+    /// Locations passed to it are full keys, unlike those of the JS methods,
+    /// which are resolved against the base path.
+    pub fn inner(&self) -> Arc<dyn ObjectStore> {
+        self.inner.clone()
+    }
+
+    /// Fetch `location`, resolved against the base path as by `get`, as a
+    /// stream of `Bytes` rather than of JS `Uint8Array`s
+    pub async fn bytes_stream(
+        &self,
+        location: &str,
+        options: GetOptions,
+    ) -> object_store::Result<BoxStream<'static, object_store::Result<Bytes>>> {
+        let location = self.resolve_path(location)?;
+        Ok(self.inner.get_opts(&location, options).await?.into_stream())
+    }
+
+    /// Fetch the whole of `location` through `cache`, revalidating a cached
+    /// body with `If-None-Match` and serving it should the store answer
+    /// `304 Not Modified`
+    async fn get_cached(
+        &self,
+        cache: &ResponseCache,
+        location: &Path,
+    ) -> object_store::Result<Bytes> {
+        let cached = cache.lock().unwrap().get(location).cloned();
+        let options = GetOptions {
+            if_none_match: cached.as_ref().map(|(e_tag, _)| e_tag.clone()),
+            ..Default::default()
+        };
+        let started = Utc::now();
+        let res = self.inner.get_opts(location, options).await;
+        let bytes = res.as_ref().map(|res| res.range.len()).unwrap_or(0);
+        notify(self.observer.as_ref(), "get", bytes, started);
+        let res = match (res, cached) {
+            (Err(object_store::Error::NotModified { .. }), Some((_, body))) => return Ok(body),
+            (Err(err), _) => {
+                cache.lock().unwrap().remove(location);
+                return Err(err);
+            }
+            (Ok(res), _) => res,
+        };
+        let e_tag = res.meta.e_tag.clone();
+        let body = res.bytes().await?;
+        let mut cache = cache.lock().unwrap();
+        match e_tag {
+            Some(e_tag) => cache.insert(location.clone(), (e_tag, body.clone())),
+            None => cache.remove(location),
+        };
+        Ok(body)
+    }
+
+    /// The prefix to list, and the base path to strip from listed locations
+    /// when [`relative_locations`](WasmObjectStore::set_relative_locations) is set
+    fn list_prefix(
+        &self,
+        prefix: Option<String>,
+    ) -> Result<(Option<Path>, Option<Path>), wasm_bindgen::JsError> {
+        if !self.relative_locations {
+            let prefix = match prefix {
+                Some(_prefix) => Some(Path::parse(_prefix)?),
+                None => None,
+            };
+            return Ok((prefix, None));
+        }
+        let prefix = match prefix {
+            Some(prefix) => Some(self.resolve(&prefix)?),
+            None => self.base_path.clone(),
+        };
+        Ok((prefix, self.base_path.clone()))
+    }
+}
+
+/// Strip `base` from the location of `meta`, if set
+fn relative_to(
+    base: Option<&Path>,
+    mut meta: object_store::ObjectMeta,
+) -> object_store::ObjectMeta {
+    if let Some(location) = base
+        .and_then(|base| meta.location.prefix_match(base))
+        .map(|parts| parts.collect())
+    {
+        meta.location = location;
+    }
+    meta
+}
+
+/// User metadata (`x-amz-meta-*` on S3), from a JS object of string values
+fn metadata_attributes(metadata: Option<Object>) -> Result<Attributes, JsError> {
+    let mut attributes = Attributes::new();
+    if let Some(metadata) = metadata {
+        let metadata: HashMap<String, String> = serde_wasm_bindgen::from_value(metadata.into())?;
+        for (key, value) in metadata {
+            attributes.insert(Attribute::Metadata(key.into()), value.into());
+        }
+    }
+    Ok(attributes)
+}
+
+/// The user metadata of `attributes` as a JS object, other attributes (e.g.
+/// `Content-Type`), and those the store reports as metadata (e.g. `expires`,
+/// `x-amz-restore`), being omitted
+fn metadata_object(attributes: &Attributes) -> Object {
+    let metadata = Object::new();
+    for (attribute, value) in attributes {
+        if let Attribute::Metadata(key) = attribute {
+            if key.starts_with("x-amz-") || key == EXPIRES_ATTRIBUTE {
+                continue;
+            }
+            let _ = js_sys::Reflect::set(
+                &metadata,
+                &JsValue::from_str(key),
+                &JsValue::from_str(value),
+            );
+        }
+    }
+    metadata
 }
 
 #[wasm_bindgen]
@@ -77,57 +685,1212 @@ impl WasmObjectStore {
         options: Option<Object>,
     ) -> Result<WasmObjectStore, wasm_bindgen::JsError> {
         let parsed_url = Url::parse(&url)?;
-        let (storage_container, path) = match options {
-            Some(options) => {
-                let deserialized_options: HashMap<String, String> =
-                    serde_wasm_bindgen::from_value(options.into())?;
-                _parse_url_opts(&parsed_url, deserialized_options.iter())?
-            }
-            None => _parse_url_opts(&parsed_url, std::iter::empty::<(String, String)>())?,
+        let deserialized_options: HashMap<String, String> = match options {
+            Some(options) => serde_wasm_bindgen::from_value(options.into())?,
+            None => HashMap::new(),
         };
+        // S3 stores are kept as such, for `check` and `list_page`
+        #[cfg(feature = "aws")]
+        if let Some((store, path)) =
+            crate::parse::parse_s3_url_opts(&parsed_url, deserialized_options.iter())?
+        {
+            let store = Arc::new(store);
+            return Ok(Self {
+                inner: store.clone(),
+                base_path: Some(path),
+                observer: None,
+                relative_locations: false,
+                cache: None,
+                s3: Some(store),
+            });
+        }
+        let (storage_container, path) = _parse_url_opts(&parsed_url, deserialized_options.iter())?;
         Ok(Self {
             inner: storage_container.into(),
             base_path: Some(path),
+            observer: None,
+            relative_locations: false,
+            cache: None,
+            #[cfg(feature = "aws")]
+            s3: None,
+        })
+    }
+    /// Build an S3 store directly from `config`, rather than parsing a URL
+    #[cfg(feature = "aws")]
+    #[wasm_bindgen]
+    pub fn from_s3_config(
+        config: WasmS3BuilderOptions,
+    ) -> Result<WasmObjectStore, wasm_bindgen::JsError> {
+        use crate::aws::builder::{AmazonS3Builder, AmazonS3ConfigKey};
+
+        let builder = [
+            (AmazonS3ConfigKey::Bucket, config.bucket),
+            (AmazonS3ConfigKey::Region, config.region),
+            (AmazonS3ConfigKey::AccessKeyId, config.access_key_id),
+            (AmazonS3ConfigKey::SecretAccessKey, config.secret_access_key),
+            (AmazonS3ConfigKey::SessionToken, config.session_token),
+            (AmazonS3ConfigKey::Endpoint, config.endpoint),
+        ]
+        .into_iter()
+        .fold(
+            AmazonS3Builder::new(),
+            |builder, (key, value)| match value {
+                Some(value) => builder.with_config(key, value),
+                None => builder,
+            },
+        )
+        .with_fetch_owner(config.fetch_owner);
+        let store = Arc::new(builder.build()?);
+        Ok(Self {
+            inner: store.clone(),
+            base_path: None,
+            observer: None,
+            relative_locations: false,
+            cache: None,
+            s3: Some(store),
         })
     }
+    /// Verify the store is reachable and accessible, e.g. at startup
+    ///
+    /// S3 stores, whether built with `from_s3_config` or from a URL, check
+    /// access to the bucket itself, others issue a `head` of the base path, for
+    /// which `NotFound` succeeds.
+    #[wasm_bindgen]
+    pub async fn check(&self) -> Result<(), wasm_bindgen::JsError> {
+        #[cfg(feature = "aws")]
+        if let Some(s3) = &self.s3 {
+            return Ok(s3.check_access().await?);
+        }
+        let location = self.base_path.clone().unwrap_or_default();
+        match self.inner.head(&location).await {
+            Ok(_) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+    /// Invoke `observer` with a [`WasmRequestEvent`] once per `get`/`head`/`put`
+    /// call, and per page of S3 listings (other stores' listings being one
+    /// request)
+    #[wasm_bindgen]
+    pub fn set_observer(&mut self, observer: Option<js_sys::Function>) {
+        self.observer = observer;
+    }
+    /// Keep the bodies of objects fetched whole by `get`/`get_buffered` in
+    /// memory, keyed by location and ETag
+    ///
+    /// Subsequent reads of a cached object are conditional (`If-None-Match`),
+    /// and served from the cache when the store answers `304 Not Modified`,
+    /// skipping the download. Objects without an ETag aren't cached. Bodies are
+    /// held until the object changes or is found to be missing, or the cache is
+    /// disabled, which discards it.
+    #[wasm_bindgen]
+    pub fn set_response_cache(&mut self, enabled: bool) {
+        match enabled {
+            true => {
+                self.cache.get_or_insert_with(Default::default);
+            }
+            false => self.cache = None,
+        }
+    }
+    /// Resolve `list`/`list_ndjson` prefixes against, and report listed (and
+    /// `head`) locations relative to, the store's base path
+    ///
+    /// Listed locations can then be passed straight back to `get`/`put`, which
+    /// resolve locations against the base path. Off by default, in which case
+    /// prefixes and listed locations are full keys.
+    #[wasm_bindgen]
+    pub fn set_relative_locations(&mut self, relative: bool) {
+        self.relative_locations = relative;
+    }
+    /// Fetch `location` as a `ReadableStream` of `Uint8Array` chunks
+    ///
+    /// Errors where `ReadableStream` is unavailable, use [`get_buffered`](Self::get_buffered) there.
+    ///
+    /// A range in `options` is passed through to the store's `get_opts`. HTTP
+    /// sources must answer ranged requests with `206 Partial Content`, unless
+    /// built with [`with_range_fallback`](crate::http::HttpStore::with_range_fallback).
     #[wasm_bindgen]
     pub async fn get(
         &self,
         location: &str,
         options: Option<WasmGetOptions>,
     ) -> Result<wasm_streams::readable::sys::ReadableStream, wasm_bindgen::JsError> {
+        ensure_readable_stream()?;
         let options = options.unwrap_or_default().into();
-        // query parameters will be interpreted as literal parts of the path,
-        // and url encoded
-        let converted_path = Path::from_url_path(location)?;
-        let synthesised_location = match &self.base_path {
-            Some(path) => Path::from_url_path(format!("{}/{}", path, converted_path))?,
-            None => converted_path,
+        let synthesised_location = self.resolve(location)?;
+        if let Some(cache) = self.cache.as_ref().filter(|_| is_whole_object(&options)) {
+            let body = self.get_cached(cache, &synthesised_location).await?;
+            let chunk = js_sys::Uint8Array::from(body.as_ref());
+            let stream = futures::stream::once(async move { Ok(chunk.into()) });
+            return Ok(wasm_streams::ReadableStream::from_stream(stream).into_raw());
+        }
+        let started = Utc::now();
+        let res = self.inner.get_opts(&synthesised_location, options).await;
+        let bytes = res.as_ref().map(|res| res.range.len()).unwrap_or(0);
+        notify(self.observer.as_ref(), "get", bytes, started);
+        Ok(readable_stream(res?))
+    }
+    /// Fetch `location` as by [`get`](Self::get), along with the
+    /// [`WasmObjectMeta`] of the object and the byte range being streamed
+    ///
+    /// Lets callers learn the total size (e.g. for progress) and ETag (e.g. for
+    /// later revalidation) of what they're streaming. Always fetched from the
+    /// store, bypassing the response cache.
+    #[wasm_bindgen]
+    pub async fn get_with_meta(
+        &self,
+        location: &str,
+        options: Option<WasmGetOptions>,
+    ) -> Result<WasmGetResult, wasm_bindgen::JsError> {
+        ensure_readable_stream()?;
+        let options = options.unwrap_or_default().into();
+        let synthesised_location = self.resolve(location)?;
+        let started = Utc::now();
+        let res = self.inner.get_opts(&synthesised_location, options).await;
+        let bytes = res.as_ref().map(|res| res.range.len()).unwrap_or(0);
+        notify(self.observer.as_ref(), "get", bytes, started);
+        let res = res?;
+        let range = res.range.clone();
+        let base = self.base_path.as_ref().filter(|_| self.relative_locations);
+        let meta = relative_to(base, res.meta.clone()).into();
+        Ok(WasmGetResult {
+            stream: readable_stream(res),
+            meta,
+            range_start: range.start,
+            range_end: range.end,
+        })
+    }
+    /// Fetch `location` into a single `Uint8Array`, for environments lacking `ReadableStream`
+    #[wasm_bindgen]
+    pub async fn get_buffered(
+        &self,
+        location: &str,
+        options: Option<WasmGetOptions>,
+    ) -> Result<js_sys::Uint8Array, wasm_bindgen::JsError> {
+        let options = options.unwrap_or_default().into();
+        let synthesised_location = self.resolve(location)?;
+        if let Some(cache) = self.cache.as_ref().filter(|_| is_whole_object(&options)) {
+            let body = self.get_cached(cache, &synthesised_location).await?;
+            return Ok(js_sys::Uint8Array::from(body.as_ref()));
+        }
+        let started = Utc::now();
+        let res = self.inner.get_opts(&synthesised_location, options).await;
+        let bytes = res.as_ref().map(|res| res.range.len()).unwrap_or(0);
+        notify(self.observer.as_ref(), "get", bytes, started);
+        let bytes = res?.bytes().await?;
+        Ok(js_sys::Uint8Array::from(bytes.as_ref()))
+    }
+    /// Download `location` into `writable` (e.g. a File System Access API
+    /// `FileSystemWritableFileStream`), closing it once every chunk is written
+    ///
+    /// Each chunk is only fetched once the previous write has completed, so a
+    /// slow sink throttles the download. Should the download fail, `writable`
+    /// is aborted with the error.
+    #[wasm_bindgen]
+    pub async fn get_to(
+        &self,
+        location: &str,
+        writable: wasm_streams::writable::sys::WritableStream,
+        options: Option<WasmGetOptions>,
+    ) -> Result<(), JsValue> {
+        let mut writable = wasm_streams::WritableStream::from_raw(writable);
+        let mut writer = writable.try_get_writer()?;
+        let result = async {
+            let options = options.unwrap_or_default().into();
+            let synthesised_location = self.resolve(location)?;
+            let started = Utc::now();
+            let res = self.inner.get_opts(&synthesised_location, options).await;
+            let bytes = res.as_ref().map(|res| res.range.len()).unwrap_or(0);
+            notify(self.observer.as_ref(), "get", bytes, started);
+            let mut stream = res.map_err(JsError::from)?.into_stream();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(JsError::from)?;
+                writer
+                    .write(js_sys::Uint8Array::from(chunk.as_ref()).into())
+                    .await?;
+            }
+            Ok(())
+        }
+        .await;
+        match result {
+            Ok(()) => writer.close().await,
+            Err(err) => {
+                let _ = writer.abort_with_reason(&err).await;
+                Err(err)
+            }
+        }
+    }
+    /// The [`WasmObjectMeta`] of `location` (its size, last modified date, ETag
+    /// and version), without fetching its body
+    ///
+    /// The reported location is relative to the base path where
+    /// [`relative_locations`](Self::set_relative_locations) is set.
+    #[wasm_bindgen]
+    pub async fn head(&self, location: &str) -> Result<WasmObjectMeta, wasm_bindgen::JsError> {
+        let synthesised_location = self.resolve(location)?;
+        let started = Utc::now();
+        let res = self.inner.head(&synthesised_location).await;
+        notify(self.observer.as_ref(), "head", 0, started);
+        let base = self.base_path.as_ref().filter(|_| self.relative_locations);
+        Ok(relative_to(base, res?).into())
+    }
+    /// The user metadata of `location` (`x-amz-meta-*` on S3), as an object of
+    /// strings keyed without the `x-amz-meta-` prefix
+    ///
+    /// Only the object's headers are fetched. Tags are separate from metadata,
+    /// and not included.
+    #[wasm_bindgen]
+    pub async fn get_metadata(&self, location: &str) -> Result<Object, wasm_bindgen::JsError> {
+        let synthesised_location = self.resolve(location)?;
+        let options = GetOptions {
+            head: true,
+            ..Default::default()
         };
-        let res = self.inner.get_opts(&synthesised_location, options).await?;
-        let intermediate_stream = res.into_stream().map(|chunk| {
-            let inner_chunk = chunk.unwrap();
-            let return_vec =
-                js_sys::Uint8Array::new_with_length(inner_chunk.len().try_into().unwrap());
-            return_vec.copy_from(&inner_chunk);
-            Ok(return_vec.into())
-        });
-        Ok(wasm_streams::ReadableStream::from_stream(intermediate_stream).into_raw())
+        let started = Utc::now();
+        let res = self.inner.get_opts(&synthesised_location, options).await;
+        notify(self.observer.as_ref(), "head", 0, started);
+        Ok(metadata_object(&res?.attributes))
+    }
+    /// Put `bytes` to `location` in a single request, resolving to the ETag
+    /// and version of the written object
+    ///
+    /// A failed create (see `WasmPutOptions.set_mode_create`) rejects with an
+    /// `AlreadyExists` error, a failed update with a `Precondition` error.
+    #[wasm_bindgen]
+    pub async fn put(
+        &self,
+        location: &str,
+        bytes: js_sys::Uint8Array,
+        options: Option<WasmPutOptions>,
+    ) -> Result<WasmPutResult, wasm_bindgen::JsError> {
+        let synthesised_location = self.resolve(location)?;
+        let payload = Bytes::from(bytes.to_vec());
+        let size = payload.len();
+        let started = Utc::now();
+        let res = self
+            .inner
+            .put_opts(
+                &synthesised_location,
+                payload.into(),
+                options.unwrap_or_default().into(),
+            )
+            .await;
+        notify(self.observer.as_ref(), "put", size, started);
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().remove(&synthesised_location);
+        }
+        Ok(res?.into())
+    }
+    /// Append `bytes` to the object at `location`, whose size must be `offset`,
+    /// resolving to the object's new size
+    ///
+    /// Offsets and sizes are numbers rather than `usize`, which is 32 bits in
+    /// wasm, so that objects beyond 4GiB can be appended to. An `offset` that
+    /// isn't a whole number of bytes is rejected. An `offset` of 0 creates the
+    /// object. Only supported by S3 Express One
+    /// Zone directory buckets. A stale `offset` (e.g. after a concurrent
+    /// append) rejects with a `Precondition` error.
+    #[cfg(feature = "aws")]
+    #[wasm_bindgen]
+    pub async fn append(
+        &self,
+        location: &str,
+        offset: f64,
+        bytes: js_sys::Uint8Array,
+    ) -> Result<f64, wasm_bindgen::JsError> {
+        let s3 = self
+            .s3
+            .as_ref()
+            .ok_or_else(|| JsError::new("append is only supported by S3 stores"))?;
+        let offset = byte_offset(offset)
+            .ok_or_else(|| JsError::new(&format!("invalid append offset {offset}")))?;
+        let synthesised_location = self.resolve(location)?;
+        let payload = Bytes::from(bytes.to_vec());
+        let size = payload.len();
+        let started = Utc::now();
+        let res = s3
+            .append(&synthesised_location, offset, payload.into())
+            .await;
+        notify(self.observer.as_ref(), "put", size, started);
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().remove(&synthesised_location);
+        }
+        // exact, sizes being far below Number.MAX_SAFE_INTEGER
+        Ok(res? as f64)
+    }
+    /// Start a multipart upload to `location`
+    ///
+    /// `total_size`, where known, sizes the parts uploaded by `write` so that
+    /// even very large objects fit within the store's part count limit.
+    /// `metadata`, an object of strings, is stored as the object's user
+    /// metadata (see [`get_metadata`](Self::get_metadata)).
+    #[wasm_bindgen]
+    pub async fn put_multipart(
+        &self,
+        location: &str,
+        total_size: Option<usize>,
+        metadata: Option<Object>,
+    ) -> Result<WasmMultipartUpload, wasm_bindgen::JsError> {
+        let synthesised_location = self.resolve(location)?;
+        let opts = PutMultipartOpts {
+            attributes: metadata_attributes(metadata)?,
+            ..Default::default()
+        };
+        let upload = self
+            .inner
+            .put_multipart_opts(&synthesised_location, opts)
+            .await?;
+        Ok(WasmMultipartUpload {
+            inner: Some(upload),
+            buffer: Vec::new(),
+            part_size: multipart_part_size(total_size),
+        })
+    }
+    /// Upload the `Uint8Array` chunks of `readable` to `location`
+    ///
+    /// `total_size`, where known, picks how: a stream of at most one part
+    /// (5MiB) is buffered and put in a single request, larger streams, and
+    /// those of unknown size, are uploaded in parts sized by `total_size` (see
+    /// [`put_multipart`](Self::put_multipart)), so never exceed the single
+    /// request size limit. The stream must yield exactly `total_size` bytes,
+    /// otherwise the upload is abandoned. `metadata` is stored as the object's
+    /// user metadata, as by [`put_multipart`](Self::put_multipart).
+    #[wasm_bindgen]
+    pub async fn put_stream(
+        &self,
+        location: &str,
+        readable: wasm_streams::readable::sys::ReadableStream,
+        total_size: Option<usize>,
+        metadata: Option<Object>,
+    ) -> Result<(), JsValue> {
+        let mut chunks = wasm_streams::ReadableStream::from_raw(readable).into_stream();
+        let mismatch = |size: usize| match total_size {
+            Some(total_size) if total_size != size => Err(JsError::new(&format!(
+                "stream yielded {size} bytes, expected {total_size}"
+            ))),
+            _ => Ok(()),
+        };
+        if let Some(total_size) = total_size.filter(|size| *size <= MIN_PART_SIZE) {
+            let mut buffer = Vec::with_capacity(total_size);
+            while let Some(chunk) = chunks.next().await {
+                buffer.extend(js_sys::Uint8Array::new(&chunk?).to_vec());
+            }
+            mismatch(buffer.len())?;
+            let synthesised_location = self.resolve(location)?;
+            let opts = PutOptions {
+                attributes: metadata_attributes(metadata)?,
+                ..Default::default()
+            };
+            self.inner
+                .put_opts(&synthesised_location, Bytes::from(buffer).into(), opts)
+                .await
+                .map_err(JsError::from)?;
+            return Ok(());
+        }
+        let mut upload = self.put_multipart(location, total_size, metadata).await?;
+        let result = async {
+            let mut size = 0;
+            while let Some(chunk) = chunks.next().await {
+                let chunk = js_sys::Uint8Array::new(&chunk?);
+                size += chunk.length() as usize;
+                upload.write(chunk).await?;
+            }
+            mismatch(size)?;
+            Ok::<_, JsValue>(())
+        }
+        .await;
+        match result {
+            Ok(()) => Ok(upload.close().await?),
+            Err(err) => {
+                let _ = upload.abort().await;
+                Err(err)
+            }
+        }
+    }
+    /// Move `from` to `to`, both resolved against the base path, resolving to
+    /// whether the object was moved
+    ///
+    /// With `overwrite` unset, an object at `to` is left as is, resolving to
+    /// `false` (via `rename_if_not_exists`, which not every store supports).
+    /// Otherwise it's replaced, the source being copied then deleted.
+    #[wasm_bindgen]
+    pub async fn rename(
+        &self,
+        from: &str,
+        to: &str,
+        overwrite: bool,
+    ) -> Result<bool, wasm_bindgen::JsError> {
+        let from = self.resolve(from)?;
+        let to = self.resolve(to)?;
+        let res = match overwrite {
+            true => self.inner.rename(&from, &to).await,
+            false => self.inner.rename_if_not_exists(&from, &to).await,
+        };
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().unwrap();
+            cache.remove(&from);
+            cache.remove(&to);
+        }
+        match res {
+            Ok(()) => Ok(true),
+            Err(object_store::Error::AlreadyExists { .. }) if !overwrite => Ok(false),
+            Err(err) => Err(err.into()),
+        }
     }
     #[wasm_bindgen]
     pub async fn list(
         &self,
         prefix: Option<String>,
     ) -> Result<wasm_streams::readable::sys::ReadableStream, wasm_bindgen::JsError> {
-        let prefix = match prefix {
-            Some(_prefix) => Some(Path::parse(_prefix)?),
-            None => None,
+        ensure_readable_stream()?;
+        let (prefix, base) = self.list_prefix(prefix)?;
+        // drive the lazy listing from a local task, so that only a bounded
+        // number of entries are buffered ahead of the JS reader
+        let lister = self.lister();
+        let (tx, rx) = futures::channel::mpsc::channel(1);
+        spawn_local(async move {
+            let _ = lister
+                .list(prefix.as_ref())
+                .map(|element| {
+                    Ok(element
+                        .map(|meta| WasmObjectMeta::from(relative_to(base.as_ref(), meta)).into())
+                        .map_err(|err| JsError::from(err).into()))
+                })
+                .forward(tx)
+                .await;
+        });
+        Ok(wasm_streams::ReadableStream::from_stream(rx).into_raw())
+    }
+    /// List objects into an `Array` of [`WasmObjectMeta`], for small listings
+    ///
+    /// Errors, rather than truncating, if more than `max` (default 10000)
+    /// objects are listed.
+    #[wasm_bindgen]
+    pub async fn list_all(
+        &self,
+        prefix: Option<String>,
+        max: Option<usize>,
+    ) -> Result<js_sys::Array, wasm_bindgen::JsError> {
+        let (prefix, base) = self.list_prefix(prefix)?;
+        let max = max.unwrap_or(DEFAULT_LIST_ALL_MAX);
+        let listed = self
+            .lister()
+            .list(prefix.as_ref())
+            .take(max + 1)
+            .try_collect::<Vec<_>>()
+            .await?;
+        if listed.len() > max {
+            return Err(JsError::new(&format!(
+                "listing exceeds {max} objects, use list or list_ndjson to stream it"
+            )));
+        }
+        Ok(listed
+            .into_iter()
+            .map(|meta| JsValue::from(WasmObjectMeta::from(relative_to(base.as_ref(), meta))))
+            .collect())
+    }
+    /// List a single page of objects, continuing from `continuation_token`
+    ///
+    /// Reports whether further pages follow and the page's key count, e.g. for
+    /// progress UIs. Only supported by S3 stores.
+    #[cfg(feature = "aws")]
+    #[wasm_bindgen]
+    pub async fn list_page(
+        &self,
+        prefix: Option<String>,
+        continuation_token: Option<String>,
+    ) -> Result<WasmListPage, wasm_bindgen::JsError> {
+        let s3 = self
+            .s3
+            .as_ref()
+            .ok_or_else(|| JsError::new("list_page is only supported by S3 stores"))?;
+        let (prefix, base) = self.list_prefix(prefix)?;
+        let started = Utc::now();
+        let page = s3.list_page(prefix.as_ref(), continuation_token).await;
+        notify(self.observer.as_ref(), "list", 0, started);
+        let mut page = page?;
+        let objects = js_sys::Array::new();
+        let owners = js_sys::Map::new();
+        for meta in page.objects {
+            let owner = page.owners.remove(&meta.location);
+            let meta = WasmObjectMeta::from(relative_to(base.as_ref(), meta));
+            if let Some(owner) = owner {
+                let owner = WasmObjectOwner {
+                    id: owner.id,
+                    display_name: owner.display_name,
+                };
+                owners.set(&JsValue::from_str(&meta.location), &owner.into());
+            }
+            objects.push(&meta.into());
+        }
+        Ok(WasmListPage {
+            objects,
+            key_count: page.key_count,
+            is_truncated: page.is_truncated,
+            next_continuation_token: page.next_continuation_token,
+            owners,
+        })
+    }
+    /// List objects as a `ReadableStream` of newline-delimited JSON strings
+    ///
+    /// Each line is an object with the fields of [`WasmObjectMeta`],
+    /// `last_modified` being an RFC 3339 string. Lines are batched into chunks
+    /// of up to `batch_size` (default 1000) entries, avoiding the per-object
+    /// overhead of `list` for very large listings.
+    #[wasm_bindgen]
+    pub async fn list_ndjson(
+        &self,
+        prefix: Option<String>,
+        batch_size: Option<usize>,
+    ) -> Result<wasm_streams::readable::sys::ReadableStream, wasm_bindgen::JsError> {
+        ensure_readable_stream()?;
+        let (prefix, base) = self.list_prefix(prefix)?;
+        let batch_size = batch_size.unwrap_or(1000).max(1);
+        let lister = self.lister();
+        let (tx, rx) = futures::channel::mpsc::channel(1);
+        spawn_local(async move {
+            let _ = lister
+                .list(prefix.as_ref())
+                .map_ok(|meta| relative_to(base.as_ref(), meta))
+                .ready_chunks(batch_size)
+                .map(|batch| Ok(ndjson_chunk(batch)))
+                .forward(tx)
+                .await;
+        });
+        Ok(wasm_streams::ReadableStream::from_stream(rx).into_raw())
+    }
+}
+
+/// The byte offset a JS number denotes: a whole, non-negative number no
+/// greater than `Number.MAX_SAFE_INTEGER`
+#[cfg(feature = "aws")]
+fn byte_offset(offset: f64) -> Option<u64> {
+    const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+    ((0.0..=MAX_SAFE_INTEGER).contains(&offset) && offset.fract() == 0.0).then_some(offset as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    /// A JS object of string values, as passed to the constructor
+    fn js_options(pairs: &[(&str, &str)]) -> Object {
+        let options = Object::new();
+        for (key, value) in pairs {
+            js_sys::Reflect::set(&options, &(*key).into(), &(*value).into()).unwrap();
+        }
+        options
+    }
+
+    /// Options selecting the mocked S3 client
+    #[cfg(feature = "aws")]
+    fn mock_s3_options() -> Object {
+        js_options(&[
+            ("aws_access_key_id", "access_key"),
+            ("aws_secret_access_key", "secret_key"),
+            ("aws_region", "us-east-1"),
+        ])
+    }
+
+    #[wasm_bindgen_test]
+    #[cfg(feature = "aws")]
+    async fn test_check_s3_url() {
+        let store =
+            WasmObjectStore::new("s3://bucket/data".into(), Some(mock_s3_options())).unwrap();
+        assert!(store.s3.is_some());
+        store.check().await.unwrap();
+
+        let store = WasmObjectStore::new(
+            "s3://mock-malformed-error/data".into(),
+            Some(mock_s3_options()),
+        )
+        .unwrap();
+        assert!(store.check().await.is_err());
+    }
+
+    #[cfg(all(feature = "aws", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_list_is_lazy() {
+        let pages = Arc::new(Mutex::new(0));
+        let counted = pages.clone();
+        let s3 = crate::aws::builder::AmazonS3Builder::new()
+            .access_key_id("access_key")
+            .secret_access_key("secret_key")
+            .region("us-east-1")
+            .bucket("mock-listing")
+            .with_observer(crate::telemetry::RequestObserver::new(move |_| {
+                *counted.lock().unwrap() += 1
+            }))
+            .build()
+            .unwrap();
+        let s3 = Arc::new(s3);
+        let lister = Lister {
+            inner: s3.clone(),
+            observer: None,
+            s3: Some(s3),
         };
-        let initial_stream = self.inner.list_with_delimiter(prefix.as_ref()).await?;
-        let intermediate_stream = futures::stream::iter(initial_stream.objects).map(|element| {
-            let inner: WasmObjectMeta = element.into();
-            Ok(inner.into())
+
+        // pages (of two keys) are only requested as the listing is consumed, so
+        // at most a page is held at once, however many objects are listed
+        let mut listed = lister.list(None);
+        for consumed in 1..=5 {
+            listed.next().await.unwrap().unwrap();
+            assert_eq!(*pages.lock().unwrap(), (consumed + 1) / 2, "{consumed}");
+        }
+        assert!(listed.next().await.is_none());
+        assert_eq!(*pages.lock().unwrap(), 3);
+    }
+
+    #[wasm_bindgen_test]
+    #[cfg(feature = "aws")]
+    async fn test_from_s3_config() {
+        let mut config = WasmS3BuilderOptions::new();
+        config.bucket = Some("mock-listing".into());
+        config.region = Some("us-east-1".into());
+        config.access_key_id = Some("access_key".into());
+        config.secret_access_key = Some("secret_key".into());
+        config.session_token = Some("session_token".into());
+        config.endpoint = Some("http://localhost:9000".into());
+        let store = WasmObjectStore::from_s3_config(config).unwrap();
+        assert!(store.s3.is_some());
+        store.check().await.unwrap();
+        assert_eq!(store.list_all(None, None).await.unwrap().length(), 5);
+    }
+
+    #[wasm_bindgen_test]
+    #[cfg(feature = "aws")]
+    async fn test_append() {
+        let store = WasmObjectStore::new(
+            "s3://logs--usw2-az1--x-s3/data".into(),
+            Some(mock_s3_options()),
+        )
+        .unwrap();
+        let bytes = |data: &[u8]| js_sys::Uint8Array::from(data);
+        assert_eq!(
+            store.append("log.txt", 0.0, bytes(b"abc")).await.unwrap(),
+            3.0
+        );
+        assert_eq!(
+            store.append("log.txt", 3.0, bytes(b"de")).await.unwrap(),
+            5.0
+        );
+        assert!(store.append("log.txt", 3.0, bytes(b"f")).await.is_err());
+        assert!(store.append("log.txt", 5.5, bytes(b"f")).await.is_err());
+
+        let store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        assert!(store.append("log.txt", 0.0, bytes(b"abc")).await.is_err());
+    }
+
+    #[test]
+    fn test_range_options() {
+        let range = |options: WasmGetOptions| GetOptions::from(options).range;
+        let mut options = WasmGetOptions::new();
+        options.set_range_bounded(2, 5).unwrap();
+        assert_eq!(range(options), Some(GetRange::Bounded(2..5)));
+        let mut options = WasmGetOptions::new();
+        options.set_range_offset(5);
+        assert_eq!(range(options), Some(GetRange::Offset(5)));
+        let mut options = WasmGetOptions::new();
+        options.set_range_suffix(10);
+        options.clear_range();
+        assert_eq!(range(options), None);
+        let mut options = WasmGetOptions::new();
+        options.set_range_suffix(10);
+        assert_eq!(range(options), Some(GetRange::Suffix(10)));
+    }
+
+    #[test]
+    #[cfg(feature = "aws")]
+    fn test_byte_offset() {
+        assert_eq!(byte_offset(0.0), Some(0));
+        // beyond the 4GiB a wasm usize holds
+        assert_eq!(byte_offset(5e12), Some(5_000_000_000_000));
+        assert_eq!(
+            byte_offset(9_007_199_254_740_991.0),
+            Some(9_007_199_254_740_991)
+        );
+        for invalid in [-1.0, 1.5, f64::NAN, f64::INFINITY, 1e16] {
+            assert_eq!(byte_offset(invalid), None, "{invalid}");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    #[cfg(feature = "aws")]
+    async fn test_list_observer() {
+        let events = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counted = events.clone();
+        let observer = Closure::<dyn Fn(JsValue)>::new(move |_| counted.set(counted.get() + 1));
+        let observer: &js_sys::Function = observer.as_ref().unchecked_ref();
+        let mut store =
+            WasmObjectStore::new("s3://mock-listing".into(), Some(mock_s3_options())).unwrap();
+        store.set_observer(Some(observer.clone()));
+
+        // five keys, listed two to a page
+        assert_eq!(store.list_all(None, None).await.unwrap().length(), 5);
+        assert_eq!(events.get(), 3);
+        store.list_page(None, None).await.unwrap();
+        assert_eq!(events.get(), 4);
+        // reads and listings of other stores are one request each
+        let mut store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        store.set_observer(Some(observer.clone()));
+        store.list_all(None, None).await.unwrap();
+        assert!(store.head("a.txt").await.is_err());
+        assert_eq!(events.get(), 6);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_response_cache() {
+        // the bytes read by each request
+        let reads = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = reads.clone();
+        let observer = Closure::<dyn Fn(JsValue)>::new(move |event| {
+            let bytes = js_sys::Reflect::get(&event, &"bytes".into()).unwrap();
+            recorded.borrow_mut().push(bytes.as_f64().unwrap());
         });
-        Ok(wasm_streams::ReadableStream::from_stream(intermediate_stream).into_raw())
+        let mut store = WasmObjectStore::new("memory:///".into(), None).unwrap();
+        store.set_observer(Some(
+            observer
+                .as_ref()
+                .unchecked_ref::<js_sys::Function>()
+                .clone(),
+        ));
+        store.set_response_cache(true);
+        let location = Path::from("a.txt");
+        store.inner.put(&location, "abc".into()).await.unwrap();
+
+        let read = store.get_buffered("a.txt", None).await.unwrap();
+        assert_eq!(read.to_vec(), b"abc");
+        // unchanged, so served from the cache after a conditional request
+        let read = store.get_buffered("a.txt", None).await.unwrap();
+        assert_eq!(read.to_vec(), b"abc");
+        assert_eq!(*reads.borrow(), [3.0, 0.0]);
+        // changed, so read again
+        store.inner.put(&location, "abcd".into()).await.unwrap();
+        let read = store.get_buffered("a.txt", None).await.unwrap();
+        assert_eq!(read.to_vec(), b"abcd");
+        assert_eq!(*reads.borrow(), [3.0, 0.0, 4.0]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_rename() {
+        let store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        let read = |location: &str| {
+            let location = Path::from(location);
+            let inner = store.inner();
+            async move { inner.get(&location).await?.bytes().await }
+        };
+        for (location, data) in [("data/a.txt", "a"), ("data/b.txt", "b")] {
+            store
+                .inner
+                .put(&Path::from(location), data.into())
+                .await
+                .unwrap();
+        }
+
+        // a collision without overwrite leaves both objects as they were
+        assert!(!store.rename("a.txt", "b.txt", false).await.unwrap());
+        assert_eq!(read("data/a.txt").await.unwrap().as_ref(), b"a");
+        assert_eq!(read("data/b.txt").await.unwrap().as_ref(), b"b");
+        // with overwrite, the destination is replaced
+        assert!(store.rename("a.txt", "b.txt", true).await.unwrap());
+        assert!(read("data/a.txt").await.is_err());
+        assert_eq!(read("data/b.txt").await.unwrap().as_ref(), b"a");
+        // without a collision, moved regardless
+        assert!(store.rename("b.txt", "c/d.txt", false).await.unwrap());
+        assert!(read("data/b.txt").await.is_err());
+        assert_eq!(read("data/c/d.txt").await.unwrap().as_ref(), b"a");
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_head() {
+        let store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        store
+            .inner
+            .put(&Path::from("data/a.txt"), "abc".into())
+            .await
+            .unwrap();
+
+        let meta = store.head("a.txt").await.unwrap();
+        assert_eq!(meta.location, "data/a.txt");
+        assert_eq!(meta.size, 3);
+        assert!(meta.e_tag.is_some());
+        assert!(meta.last_modified.get_time() > 0.0);
+        assert!(store.head("b.txt").await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_get_range() {
+        let store = WasmObjectStore::new("memory:///".into(), None).unwrap();
+        let data: Vec<u8> = (0..=255).collect();
+        store
+            .inner
+            .put(&Path::from("a.bin"), data.clone().into())
+            .await
+            .unwrap();
+
+        let mut options = WasmGetOptions::new();
+        options.set_range_bounded(100, 200).unwrap();
+        let read = store.get_buffered("a.bin", Some(options)).await.unwrap();
+        assert_eq!(read.length(), 100);
+        assert_eq!(read.to_vec(), &data[100..200]);
+        let mut options = WasmGetOptions::new();
+        options.set_range_suffix(6);
+        let read = store.get_buffered("a.bin", Some(options)).await.unwrap();
+        assert_eq!(read.to_vec(), &data[250..]);
+        assert!(WasmGetOptions::new().set_range_bounded(200, 100).is_err());
+
+        // preconditions on the current ETag
+        let e_tag = store.head("a.bin").await.unwrap().e_tag;
+        let mut options = WasmGetOptions::new();
+        options.set_if_none_match(e_tag.clone());
+        assert!(store.get_buffered("a.bin", Some(options)).await.is_err());
+        let mut options = WasmGetOptions::new();
+        options.set_if_match(e_tag);
+        let read = store.get_buffered("a.bin", Some(options)).await.unwrap();
+        assert_eq!(read.length(), 256);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_get_with_meta() {
+        let store = WasmObjectStore::new("memory:///".into(), None).unwrap();
+        store
+            .inner
+            .put(&Path::from("a.bin"), "0123456789".into())
+            .await
+            .unwrap();
+
+        let mut options = WasmGetOptions::new();
+        options.set_range_offset(4);
+        let result = store.get_with_meta("a.bin", Some(options)).await.unwrap();
+        // the object as a whole, and the part of it being streamed
+        assert_eq!(result.meta.size, 10);
+        assert!(result.meta.e_tag.is_some());
+        assert_eq!((result.range_start, result.range_end), (4, 10));
+        let chunks: Vec<JsValue> = wasm_streams::ReadableStream::from_raw(result.stream)
+            .into_stream()
+            .try_collect()
+            .await
+            .unwrap();
+        let streamed: Vec<u8> = chunks
+            .iter()
+            .flat_map(|chunk| js_sys::Uint8Array::new(chunk).to_vec())
+            .collect();
+        assert_eq!(streamed, b"456789");
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_put() {
+        let store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        let bytes = |data: &[u8]| js_sys::Uint8Array::from(data);
+        let create = || {
+            let mut options = WasmPutOptions::new();
+            options.set_mode_create();
+            options.set_content_type(Some("application/json".into()));
+            Some(options)
+        };
+
+        let created = store
+            .put("a.json", bytes(b"{\"a\": 1}"), create())
+            .await
+            .unwrap();
+        assert!(created.e_tag.is_some());
+        // created under the base path, with the content type
+        let path = Path::from("data/a.json");
+        let result = store.inner.get(&path).await.unwrap();
+        assert_eq!(
+            result.attributes.get(&Attribute::ContentType),
+            Some(&"application/json".into())
+        );
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"{\"a\": 1}");
+        assert!(store.put("a.json", bytes(b"{}"), create()).await.is_err());
+
+        // updates only replace the object they were made against
+        let mut update = WasmPutOptions::new();
+        update.set_mode_update(created.e_tag.clone(), None);
+        let updated = store
+            .put("a.json", bytes(b"{\"a\": 2}"), Some(update))
+            .await
+            .unwrap();
+        assert_ne!(updated.e_tag, created.e_tag);
+        let mut stale = WasmPutOptions::new();
+        stale.set_mode_update(created.e_tag, None);
+        assert!(store
+            .put("a.json", bytes(b"{}"), Some(stale))
+            .await
+            .is_err());
+        let result = store.inner.get(&path).await.unwrap();
+        // each put replaces the attributes, here with none
+        assert_eq!(result.attributes.get(&Attribute::ContentType), None);
+        assert_eq!(result.bytes().await.unwrap().as_ref(), b"{\"a\": 2}");
+    }
+
+    #[test]
+    fn test_ndjson_line() {
+        use chrono::TimeZone;
+
+        let meta = object_store::ObjectMeta {
+            location: Path::from("a/b.txt"),
+            last_modified: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            size: 3,
+            e_tag: Some("\"e\"".into()),
+            version: None,
+        };
+        let line = serde_json::to_string(&NdjsonObjectMeta::from(&meta)).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!({
+                "location": "a/b.txt",
+                "last_modified": "2024-01-01T00:00:00+00:00",
+                "size": 3,
+                "e_tag": "\"e\"",
+                "version": null,
+            })
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_list_ndjson() {
+        let store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        for location in ["a.txt", "b/c.txt", "d.txt"] {
+            let path = Path::from(format!("data/{location}"));
+            store.inner.put(&path, "abc".into()).await.unwrap();
+        }
+
+        let stream = store.list_ndjson(None, Some(2)).await.unwrap();
+        let chunks: Vec<_> = wasm_streams::ReadableStream::from_raw(stream)
+            .into_stream()
+            .try_collect()
+            .await
+            .unwrap();
+        // batches of up to two lines
+        assert_eq!(chunks.len(), 2);
+        let ndjson: String = chunks
+            .iter()
+            .map(|chunk| chunk.as_string().unwrap())
+            .collect();
+        let mut locations: Vec<_> = ndjson
+            .lines()
+            .map(|line| {
+                let parsed: serde_json::Value = serde_json::from_str(line).unwrap();
+                assert_eq!(parsed["size"], 3);
+                parsed["location"].as_str().unwrap().to_string()
+            })
+            .collect();
+        locations.sort();
+        // full keys, as with `list`
+        assert_eq!(locations, ["data/a.txt", "data/b/c.txt", "data/d.txt"]);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_list_all() {
+        let store = WasmObjectStore::new("memory:///".into(), None).unwrap();
+        for (location, data) in [("a.txt", "a"), ("b/c.txt", "bc"), ("d.txt", "def")] {
+            store
+                .inner
+                .put(&Path::from(location), data.into())
+                .await
+                .unwrap();
+        }
+
+        let listing = store.list_all(None, None).await.unwrap();
+        assert_eq!(listing.length(), 3);
+        let mut objects: Vec<_> = listing
+            .iter()
+            .map(|meta| {
+                let location = js_sys::Reflect::get(&meta, &"location".into()).unwrap();
+                let size = js_sys::Reflect::get(&meta, &"size".into()).unwrap();
+                (location.as_string().unwrap(), size.as_f64().unwrap())
+            })
+            .collect();
+        objects.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            objects,
+            [
+                ("a.txt".to_string(), 1.0),
+                ("b/c.txt".to_string(), 2.0),
+                ("d.txt".to_string(), 3.0)
+            ]
+        );
+        assert_eq!(listed(&store, Some("b")).await, ["b/c.txt"]);
+        // at the cap is fine, beyond it errors rather than truncating
+        assert_eq!(store.list_all(None, Some(3)).await.unwrap().length(), 3);
+        assert!(store.list_all(None, Some(2)).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_bytes_stream() {
+        let store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        // the inner store takes full keys
+        store
+            .inner()
+            .put(&Path::from("data/a.bin"), "0123456789".into())
+            .await
+            .unwrap();
+
+        let options = GetOptions {
+            range: Some(GetRange::Bounded(2..5)),
+            ..Default::default()
+        };
+        let chunks: Vec<Bytes> = store
+            .bytes_stream("a.bin", options)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), b"234");
+        let err = store
+            .bytes_stream("b.bin", GetOptions::default())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, object_store::Error::NotFound { .. }), "{err}");
+    }
+
+    /// The locations `list_all` reports under `prefix`
+    async fn listed(store: &WasmObjectStore, prefix: Option<&str>) -> Vec<String> {
+        let listing = store
+            .list_all(prefix.map(String::from), None)
+            .await
+            .unwrap();
+        listing
+            .iter()
+            .map(|meta| {
+                let location = js_sys::Reflect::get(&meta, &"location".into()).unwrap();
+                location.as_string().unwrap()
+            })
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_relative_locations() {
+        let mut store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        for location in ["data/a/b.txt", "other.txt"] {
+            store
+                .inner
+                .put(&Path::from(location), "abc".into())
+                .await
+                .unwrap();
+        }
+
+        // full keys, of the whole store, by default
+        assert_eq!(listed(&store, None).await, ["data/a/b.txt", "other.txt"]);
+        store.set_relative_locations(true);
+        assert_eq!(listed(&store, None).await, ["a/b.txt"]);
+        assert_eq!(listed(&store, Some("a")).await, ["a/b.txt"]);
+        // listed locations round-trip through the base path
+        let meta = store.head("a/b.txt").await.unwrap();
+        assert_eq!(meta.location, "a/b.txt");
+        assert_eq!(meta.size, 3);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_get_to() {
+        use futures::SinkExt;
+
+        let store = WasmObjectStore::new("memory:///".into(), None).unwrap();
+        store
+            .inner
+            .put(&Path::from("a.bin"), "0123456789".into())
+            .await
+            .unwrap();
+        let collected = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink =
+            futures::sink::unfold(collected.clone(), |collected, chunk: JsValue| async move {
+                let chunk = js_sys::Uint8Array::new(&chunk);
+                collected.borrow_mut().extend(chunk.to_vec());
+                Ok::<_, JsValue>(collected)
+            });
+        let writable = wasm_streams::WritableStream::from_sink(Box::pin(sink));
+
+        let mut options = WasmGetOptions::new();
+        options.set_range_offset(2);
+        store
+            .get_to("a.bin", writable.into_raw(), Some(options))
+            .await
+            .unwrap();
+        assert_eq!(collected.borrow().as_slice(), b"23456789");
+
+        // a failed download aborts the writable
+        let writable = wasm_streams::WritableStream::from_sink(
+            futures::sink::drain().sink_map_err(|never| match never {}),
+        );
+        let raw = writable.into_raw();
+        assert!(store
+            .get_to("missing.bin", raw.clone(), None)
+            .await
+            .is_err());
+        let mut writable = wasm_streams::WritableStream::from_raw(raw);
+        let mut writer = writable.get_writer();
+        assert!(writer.close().await.is_err());
+    }
+
+    /// An upload counting the times it was aborted
+    #[derive(Debug)]
+    struct CountedAborts(Arc<std::sync::atomic::AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl MultipartUpload for CountedAborts {
+        fn put_part(&mut self, _data: object_store::PutPayload) -> object_store::UploadPart {
+            Box::pin(futures::future::ready(Ok(())))
+        }
+        async fn complete(&mut self) -> object_store::Result<PutResult> {
+            Ok(PutResult {
+                e_tag: None,
+                version: None,
+            })
+        }
+        async fn abort(&mut self) -> object_store::Result<()> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_multipart_abort() {
+        let aborts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let aborted = || aborts.load(std::sync::atomic::Ordering::SeqCst);
+        let upload = || WasmMultipartUpload {
+            inner: Some(Box::new(CountedAborts(aborts.clone()))),
+            buffer: Vec::new(),
+            part_size: MIN_PART_SIZE,
+        };
+        // the abort of a dropped upload runs once the current task yields
+        let yield_now =
+            || wasm_bindgen_futures::JsFuture::from(js_sys::Promise::resolve(&JsValue::NULL));
+
+        let mut explicit = upload();
+        explicit.abort().await.unwrap();
+        assert_eq!(aborted(), 1);
+        assert!(explicit.abort().await.is_err());
+        drop(explicit);
+        yield_now().await.unwrap();
+        assert_eq!(aborted(), 1);
+
+        let mut dropped = upload();
+        dropped
+            .write(js_sys::Uint8Array::from(&b"abc"[..]))
+            .await
+            .unwrap();
+        drop(dropped);
+        yield_now().await.unwrap();
+        assert_eq!(aborted(), 2);
+
+        // completed uploads are left be
+        let mut closed = upload();
+        closed.close().await.unwrap();
+        drop(closed);
+        yield_now().await.unwrap();
+        assert_eq!(aborted(), 2);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_check_memory_url() {
+        let store = WasmObjectStore::new("memory:///data".into(), None).unwrap();
+        #[cfg(feature = "aws")]
+        assert!(store.s3.is_none());
+        // a missing base path is reachable
+        store.check().await.unwrap();
     }
 }
@@ -2,7 +2,9 @@
 pub mod http;
 #[cfg(all(target_arch = "wasm32", feature = "js_binding"))]
 pub mod js_binding;
+pub mod list;
 pub mod parse;
+pub mod telemetry;
 pub mod utils;
 #[cfg(feature = "http")]
 pub use http::HttpStore;
@@ -10,3 +12,7 @@ pub use http::HttpStore;
 pub mod aws;
 #[cfg(feature = "aws")]
 pub use aws::AmazonS3;
+#[cfg(feature = "azure")]
+pub mod azure;
+#[cfg(feature = "azure")]
+pub use azure::MicrosoftAzure;
@@ -0,0 +1,193 @@
+use futures::future::join_all;
+use futures::stream::{self, BoxStream, StreamExt};
+use object_store::path::Path;
+use object_store::{ObjectMeta, ObjectStore, Result};
+
+type Listing<'a> = BoxStream<'a, Result<ObjectMeta>>;
+
+/// List several prefixes of `store` concurrently, merged into a single stream
+/// in key order
+///
+/// Objects falling under more than one of the (overlapping) prefixes are
+/// yielded once. Relies on each listing being in key order, as S3 listings are.
+pub fn list_many<'a>(store: &'a dyn ObjectStore, prefixes: &[Path]) -> Listing<'a> {
+    let streams: Vec<Option<Listing<'a>>> = prefixes
+        .iter()
+        .map(|prefix| Some(store.list(Some(prefix))))
+        .collect();
+    let heads: Vec<Option<Result<ObjectMeta>>> = streams.iter().map(|_| None).collect();
+    let state = (streams, heads, None::<Path>);
+    stream::unfold(state, |(mut streams, mut heads, mut last)| async move {
+        loop {
+            // pull the next entry of every listing without one buffered
+            let refills = streams
+                .iter_mut()
+                .zip(heads.iter_mut())
+                .filter(|(stream, head)| stream.is_some() && head.is_none())
+                .map(|(stream, head)| async move {
+                    match stream.as_mut()?.next().await {
+                        Some(item) => *head = Some(item),
+                        None => *stream = None,
+                    }
+                    Some(())
+                });
+            join_all(refills).await;
+            if let Some(err) = heads.iter_mut().find(|head| matches!(head, Some(Err(_)))) {
+                let err = err.take()?;
+                return Some((err, (streams, heads, last)));
+            }
+            let next = heads
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, head)| match head {
+                    Some(Ok(meta)) => Some((idx, meta.location.as_ref())),
+                    _ => None,
+                })
+                .min_by(|(_, a), (_, b)| a.cmp(b))
+                .map(|(idx, _)| idx)?;
+            let meta = match heads[next].take()? {
+                Ok(meta) => meta,
+                Err(err) => return Some((Err(err), (streams, heads, last))),
+            };
+            if last.as_ref() == Some(&meta.location) {
+                continue;
+            }
+            last = Some(meta.location.clone());
+            return Some((Ok(meta), (streams, heads, last)));
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+    use futures::TryStreamExt;
+    use object_store::memory::InMemory;
+    use object_store::{
+        GetOptions, GetResult, ListResult, MultipartUpload, PutMultipartOpts, PutOptions,
+        PutPayload, PutResult,
+    };
+
+    async fn store(keys: &[&str]) -> InMemory {
+        let store = InMemory::new();
+        for key in keys {
+            store
+                .put(&Path::from(*key), PutPayload::from_static(b"data"))
+                .await
+                .unwrap();
+        }
+        store
+    }
+
+    fn locations(metas: Vec<ObjectMeta>) -> Vec<String> {
+        metas.into_iter().map(|meta| meta.location.into()).collect()
+    }
+
+    #[test]
+    fn test_list_many_merges_in_key_order() {
+        block_on(async {
+            let store = store(&["c/1", "a/2", "b/1", "a/1", "c/0", "d/1"]).await;
+            let prefixes = [Path::from("c"), Path::from("a"), Path::from("b")];
+            let listed = list_many(&store, &prefixes).try_collect().await.unwrap();
+            assert_eq!(locations(listed), ["a/1", "a/2", "b/1", "c/0", "c/1"]);
+        })
+    }
+
+    #[test]
+    fn test_list_many_deduplicates_overlapping_prefixes() {
+        block_on(async {
+            let store = store(&["a/1", "a/b/1", "a/b/2", "b/1"]).await;
+            let prefixes = [Path::from("a/b"), Path::from("a"), Path::from("a")];
+            let listed = list_many(&store, &prefixes).try_collect().await.unwrap();
+            assert_eq!(locations(listed), ["a/1", "a/b/1", "a/b/2"]);
+        })
+    }
+
+    #[test]
+    fn test_list_many_without_prefixes() {
+        block_on(async {
+            let store = store(&["a/1"]).await;
+            let listed: Vec<_> = list_many(&store, &[]).collect().await;
+            assert!(listed.is_empty());
+        })
+    }
+
+    /// An [`InMemory`] store whose listings of `fail` fail
+    #[derive(Debug)]
+    struct FailingList(InMemory);
+
+    impl std::fmt::Display for FailingList {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "FailingList")
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStore for FailingList {
+        async fn put_opts(
+            &self,
+            location: &Path,
+            payload: PutPayload,
+            opts: PutOptions,
+        ) -> Result<PutResult> {
+            self.0.put_opts(location, payload, opts).await
+        }
+
+        async fn put_multipart_opts(
+            &self,
+            location: &Path,
+            opts: PutMultipartOpts,
+        ) -> Result<Box<dyn MultipartUpload>> {
+            self.0.put_multipart_opts(location, opts).await
+        }
+
+        async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+            self.0.get_opts(location, options).await
+        }
+
+        async fn delete(&self, location: &Path) -> Result<()> {
+            self.0.delete(location).await
+        }
+
+        fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, Result<ObjectMeta>> {
+            match prefix {
+                Some(prefix) if prefix.as_ref() == "fail" => {
+                    stream::once(async { Err(object_store::Error::NotImplemented) }).boxed()
+                }
+                prefix => self.0.list(prefix),
+            }
+        }
+
+        async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+            self.0.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+            self.0.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+            self.0.copy_if_not_exists(from, to).await
+        }
+    }
+
+    #[test]
+    fn test_list_many_forwards_errors() {
+        block_on(async {
+            let store = FailingList(store(&["a/1", "a/2"]).await);
+            let prefixes = [Path::from("a"), Path::from("fail")];
+            let listed: Vec<_> = list_many(&store, &prefixes).collect().await;
+            assert_eq!(listed.len(), 3);
+            assert!(matches!(
+                listed[0],
+                Err(object_store::Error::NotImplemented)
+            ));
+            // the other listing continues past the failure
+            let rest = listed.into_iter().skip(1).collect::<Result<Vec<_>>>();
+            assert_eq!(locations(rest.unwrap()), ["a/1", "a/2"]);
+        })
+    }
+}
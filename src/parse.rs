@@ -24,9 +24,6 @@ use url::Url;
 
 #[derive(Debug, Snafu)]
 enum Error {
-    #[snafu(display("Unable to convert URL \"{}\" to filesystem path", url))]
-    InvalidUrl { url: Url },
-
     #[snafu(display("Unable to recognise URL \"{}\"", url))]
     Unrecognised { url: Url },
 
@@ -51,14 +48,14 @@ impl From<Error> for object_store::Error {
 enum ObjectStoreScheme {
     // /// Url corresponding to [`LocalFileSystem`]
     // Local,
-    // /// Url corresponding to [`InMemory`]
-    // Memory,
+    /// Url corresponding to [`InMemory`](object_store::memory::InMemory)
+    Memory,
     /// Url corresponding to [`AmazonS3`](crate::aws::AmazonS3)
     AmazonS3,
     // /// Url corresponding to [`GoogleCloudStorage`](crate::gcp::GoogleCloudStorage)
     // GoogleCloudStorage,
-    // /// Url corresponding to [`MicrosoftAzure`](crate::azure::MicrosoftAzure)
-    // MicrosoftAzure,
+    /// Url corresponding to [`MicrosoftAzure`](crate::azure::MicrosoftAzure)
+    MicrosoftAzure,
     /// Url corresponding to [`HttpStore`](crate::http::HttpStore)
     Http,
 }
@@ -72,26 +69,37 @@ impl ObjectStoreScheme {
 
         let (scheme, path) = match (url.scheme(), url.host_str()) {
             // ("file", None) => (Self::Local, url.path()),
-            // ("memory", None) => (Self::Memory, url.path()),
+            ("memory", None) => (Self::Memory, url.path()),
             ("s3" | "s3a", Some(_)) => (Self::AmazonS3, url.path()),
             // ("gs", Some(_)) => (Self::GoogleCloudStorage, url.path()),
-            // ("az" | "adl" | "azure" | "abfs" | "abfss", Some(_)) => {
-            //     (Self::MicrosoftAzure, url.path())
-            // }
+            ("az" | "adl" | "azure" | "abfs" | "abfss", Some(_)) => {
+                (Self::MicrosoftAzure, url.path())
+            }
             ("http", Some(_)) => (Self::Http, url.path()),
             ("https", Some(host)) => {
-                // if host.ends_with("dfs.core.windows.net")
-                //     || host.ends_with("blob.core.windows.net")
-                //     || host.ends_with("dfs.fabric.microsoft.com")
-                //     || host.ends_with("blob.fabric.microsoft.com")
-                // {
-                //     (Self::MicrosoftAzure, url.path())
-                // } else
-                if host.ends_with("amazonaws.com") {
-                    match host.starts_with("s3") {
-                        true => (Self::AmazonS3, strip_bucket().unwrap_or_default()),
-                        false => (Self::AmazonS3, url.path()),
+                if host.ends_with("dfs.core.windows.net") || host.ends_with("blob.core.windows.net")
+                {
+                    // the container is the first segment of the path
+                    (Self::MicrosoftAzure, strip_bucket().unwrap_or_default())
+                } else if host.ends_with("amazonaws.com") {
+                    // virtual-hosted URLs (`<bucket>.s3.<region>.amazonaws.com`) carry
+                    // the bucket in the host, path-style URLs as the first segment.
+                    // as do S3 Express zonal endpoints (`<bucket>.s3express-<az>...`).
+                    // NB: bucket names may themselves begin with "s3"
+                    let virtual_hosted = matches!(
+                        host.split_once('.'),
+                        Some((_, rest)) if rest.starts_with("s3.")
+                            || rest.starts_with("s3-")
+                            || rest.starts_with("s3express-")
+                    );
+                    match virtual_hosted {
+                        false => (Self::AmazonS3, strip_bucket().unwrap_or_default()),
+                        true => (Self::AmazonS3, url.path()),
                     }
+                } else if host.ends_with(".r2.dev") {
+                    // public R2 buckets (`pub-<id>.r2.dev`) serve their objects over
+                    // plain, unsigned HTTP, rather than the S3 API
+                    (Self::Http, url.path())
                 } else if host.ends_with("r2.cloudflarestorage.com") {
                     (Self::AmazonS3, strip_bucket().unwrap_or_default())
                 } else {
@@ -105,6 +113,7 @@ impl ObjectStoreScheme {
     }
 }
 
+#[allow(unused_macros)]
 macro_rules! builder_opts {
     ($builder:ty, $url:expr, $options:expr) => {{
         let builder = $options.into_iter().fold(
@@ -146,6 +155,8 @@ where
     let path = Path::parse(path)?;
 
     let store: Box<dyn ObjectStore> = match scheme {
+        // each URL gets a fresh, empty store
+        ObjectStoreScheme::Memory => Box::new(object_store::memory::InMemory::new()),
         #[cfg(feature = "aws")]
         ObjectStoreScheme::AmazonS3 => {
             builder_opts!(crate::aws::builder::AmazonS3Builder, url, _options)
@@ -153,18 +164,261 @@ where
         #[cfg(feature = "http")]
         ObjectStoreScheme::Http => {
             let url = &url[..url::Position::BeforePath];
-            let parsed_url = Url::parse(url).unwrap();
-            Box::new(crate::http::HttpStore::new(parsed_url))
-            // builder_opts!(crate::http::HttpBuilder, url, _options)
+            builder_opts!(crate::http::HttpBuilder, url, _options)
         }
-        #[cfg(not(all(feature = "aws", feature = "http")))]
-        s => {
-            return Err(object_store::Error::Generic {
-                store: "parse_url",
-                source: format!("feature for {s:?} not enabled").into(),
-            })
+        #[cfg(feature = "azure")]
+        ObjectStoreScheme::MicrosoftAzure => {
+            builder_opts!(crate::azure::MicrosoftAzureBuilder, url, _options)
         }
+        #[cfg(not(all(feature = "aws", feature = "http", feature = "azure")))]
+        s => return Err(Error::NotEnabled { scheme: s }.into()),
     };
 
     Ok((store, path))
 }
+
+/// Create an [`AmazonS3`](crate::aws::AmazonS3) store from `url` and options,
+/// as [`parse_url_opts`] would, keeping its concrete type for the S3-specific
+/// APIs (e.g. [`list_page`](crate::aws::AmazonS3::list_page))
+///
+/// Returns `None` for URLs that don't address S3.
+#[cfg(feature = "aws")]
+pub(crate) fn parse_s3_url_opts<I, K, V>(
+    url: &Url,
+    options: I,
+) -> Result<Option<(crate::aws::AmazonS3, Path)>, object_store::Error>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: Into<String>,
+{
+    let (scheme, path) = ObjectStoreScheme::parse(url)?;
+    if scheme != ObjectStoreScheme::AmazonS3 {
+        return Ok(None);
+    }
+    let path = Path::parse(path)?;
+    let builder = options.into_iter().fold(
+        crate::aws::builder::AmazonS3Builder::new().with_url(url.to_string()),
+        |builder, (key, value)| match key.as_ref().parse() {
+            Ok(k) => builder.with_config(k, value),
+            Err(_) => builder,
+        },
+    );
+    Ok(Some((builder.build()?, path)))
+}
+
+/// Create an [`ObjectStore`] based on the provided `url` and options, first
+/// offering them to `factory`
+///
+/// Should `factory` return a store, that store is used along with the path of
+/// `url`, whatever its scheme (e.g. `memory:///data/file.parquet`). Otherwise this
+/// behaves as [`parse_url_opts`]. Lets tests substitute an in-memory or mock
+/// store for the one `url` would otherwise resolve to.
+pub fn parse_url_opts_with_factory<I, K, V, F>(
+    url: &Url,
+    options: I,
+    factory: F,
+) -> Result<(Box<dyn ObjectStore>, Path), object_store::Error>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: Into<String>,
+    F: FnOnce(&Url, &[(String, String)]) -> Option<object_store::Result<Box<dyn ObjectStore>>>,
+{
+    let options = options
+        .into_iter()
+        .map(|(key, value)| (key.as_ref().to_string(), value.into()))
+        .collect::<Vec<_>>();
+    match factory(url, &options) {
+        Some(store) => Ok((store?, Path::from_url_path(url.path())?)),
+        None => parse_url_opts(url, options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The scheme and path `url` parses to
+    fn parse(url: &str) -> (ObjectStoreScheme, String) {
+        let (scheme, path) = ObjectStoreScheme::parse(&Url::parse(url).unwrap()).unwrap();
+        (scheme, path.to_string())
+    }
+
+    #[test]
+    fn test_parse_s3() {
+        use ObjectStoreScheme::AmazonS3;
+
+        for (url, expected) in [
+            ("s3://bucket/path/to/file", "path/to/file"),
+            ("s3a://bucket/path/to/file", "path/to/file"),
+            // path-style URLs name the bucket in their first segment, only
+            // that segment is stripped from keys beginning with the bucket name
+            ("https://s3.eu-west-1.amazonaws.com/bucket/file", "file"),
+            (
+                "https://s3.eu-west-1.amazonaws.com/bucket/bucket/file",
+                "bucket/file",
+            ),
+            // virtual-hosted URLs name it in their host
+            ("https://bucket.s3.eu-west-1.amazonaws.com/file", "file"),
+            (
+                "https://bucket.s3.eu-west-1.amazonaws.com/bucket/file",
+                "bucket/file",
+            ),
+            (
+                "https://s3data.s3.eu-west-1.amazonaws.com/path/file",
+                "path/file",
+            ),
+            (
+                "https://s3data.s3-eu-west-1.amazonaws.com/path/file",
+                "path/file",
+            ),
+            (
+                "https://logs--usw2-az1--x-s3.s3express-usw2-az1.us-west-2.amazonaws.com/a/b",
+                "a/b",
+            ),
+        ] {
+            assert_eq!(parse(url), (AmazonS3, expected.to_string()), "{url}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "aws")]
+    fn test_parse_s3_url_opts() {
+        let options = [
+            ("aws_region", "eu-west-1"),
+            ("aws_access_key_id", "key"),
+            ("aws_secret_access_key", "secret"),
+        ];
+        let url = Url::parse("s3://bucket/path/to/file.parquet").unwrap();
+        let (_, path) = parse_s3_url_opts(&url, options).unwrap().unwrap();
+        assert_eq!(path.as_ref(), "path/to/file.parquet");
+
+        for url in [
+            "memory:///path",
+            "https://example.com/path",
+            "az://container/path",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(parse_s3_url_opts(&url, options).unwrap().is_none(), "{url}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn test_parse_http_url_opts() {
+        let url = Url::parse("https://example.com/data/file.parquet").unwrap();
+        let options = [
+            ("http_headers", "x-api-key: secret"),
+            ("retry_max_attempts", "5"),
+            ("http_retry_initial_backoff_ms", "200"),
+        ];
+        let (store, path) = parse_url_opts(&url, options).unwrap();
+        assert_eq!(path.as_ref(), "data/file.parquet");
+        let store = store.to_string();
+        assert!(store.contains("x-api-key"), "{store}");
+        assert!(!store.contains("secret"), "{store}");
+
+        let invalid = parse_url_opts(&url, [("http_headers", "no colon")]);
+        assert!(invalid.is_err());
+        // as with the other stores, unknown keys are ignored
+        assert!(parse_url_opts(&url, [("http_not_a_key", "1")]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_azure() {
+        use ObjectStoreScheme::MicrosoftAzure;
+
+        for (url, expected) in [
+            ("az://container/path/file", "path/file"),
+            (
+                "abfss://container@account.dfs.core.windows.net/path/file",
+                "path/file",
+            ),
+            // the container is the first segment of https URLs
+            (
+                "https://account.blob.core.windows.net/container/path/file",
+                "path/file",
+            ),
+            (
+                "https://account.dfs.core.windows.net/container/path/file",
+                "path/file",
+            ),
+        ] {
+            assert_eq!(parse(url), (MicrosoftAzure, expected.to_string()), "{url}");
+        }
+        // other hosts under windows.net aren't Blob storage
+        assert_eq!(
+            parse("https://account.file.core.windows.net/share/file").0,
+            ObjectStoreScheme::Http
+        );
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(
+            parse("memory:///path/to/file"),
+            (ObjectStoreScheme::Memory, "path/to/file".to_string())
+        );
+        // memory URLs have no host
+        let url = Url::parse("memory://host/path").unwrap();
+        assert!(ObjectStoreScheme::parse(&url).is_err());
+        let url = Url::parse("ftp://host/path").unwrap();
+        assert!(ObjectStoreScheme::parse(&url).is_err());
+    }
+
+    #[test]
+    fn test_parse_url_opts_with_factory() {
+        let factory = |url: &Url, options: &[(String, String)]| {
+            assert_eq!(options, [("region".to_string(), "test".to_string())]);
+            (url.scheme() == "mock").then(|| {
+                let store = object_store::memory::InMemory::new();
+                Ok(Box::new(store) as Box<dyn ObjectStore>)
+            })
+        };
+        let options = [("region", "test")];
+
+        // schemes parse_url doesn't recognise can be handled by the factory
+        let url = Url::parse("mock://bucket/path/to/file").unwrap();
+        let (store, path) = parse_url_opts_with_factory(&url, options, factory).unwrap();
+        assert_eq!(path.as_ref(), "path/to/file");
+        assert!(store.to_string().contains("InMemory"), "{store}");
+
+        // others fall back to parse_url_opts
+        let url = Url::parse("memory:///path/to/file").unwrap();
+        let (_, path) = parse_url_opts_with_factory(&url, options, factory).unwrap();
+        assert_eq!(path.as_ref(), "path/to/file");
+        let url = Url::parse("ftp://host/path").unwrap();
+        assert!(parse_url_opts_with_factory(&url, options, factory).is_err());
+
+        // the factory's errors are returned
+        let url = Url::parse("mock://bucket/path").unwrap();
+        let failing =
+            |_: &Url, _: &[(String, String)]| Some(Err(object_store::Error::NotImplemented));
+        assert!(parse_url_opts_with_factory(&url, options, failing).is_err());
+    }
+
+    #[test]
+    fn test_parse_r2() {
+        // public buckets are served over plain HTTP, the S3 API needs signing
+        assert_eq!(
+            parse("https://pub-0123456789abcdef.r2.dev/path/file"),
+            (ObjectStoreScheme::Http, "path/file".to_string())
+        );
+        assert_eq!(
+            parse("https://account.r2.cloudflarestorage.com/bucket/path/file"),
+            (ObjectStoreScheme::AmazonS3, "path/file".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn test_parse_r2_public_url() {
+        let url = Url::parse("https://pub-0123456789abcdef.r2.dev/path/file").unwrap();
+        let (store, path) = parse_url(&url).unwrap();
+        assert_eq!(path.as_ref(), "path/file");
+        let store = store.to_string();
+        assert!(store.starts_with("InnerClient"), "{store}");
+        assert!(store.contains("pub-0123456789abcdef.r2.dev"), "{store}");
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+/// A single request made against the backing store
+#[derive(Debug, Clone)]
+pub struct RequestEvent {
+    /// The kind of request, e.g. `get`, `head`, `put`, `list`
+    pub operation: &'static str,
+    /// The number of payload bytes uploaded or downloaded, where known
+    pub bytes: usize,
+    /// Time until the response (or error) was received
+    pub duration: Duration,
+}
+
+/// Callback invoked once per request, e.g. for tallying API calls for cost estimation
+///
+/// Each attempt at a request counts, retries, redirects and resumptions of
+/// interrupted downloads included, as each is billed.
+#[derive(Clone)]
+pub struct RequestObserver(Arc<dyn Fn(RequestEvent) + Send + Sync>);
+
+impl RequestObserver {
+    pub fn new(observer: impl Fn(RequestEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(observer))
+    }
+}
+
+impl Debug for RequestObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RequestObserver")
+    }
+}
+
+/// Report a request started at `started` to `observer`, if any
+///
+/// NB: `std::time::Instant` panics on wasm32-unknown-unknown, so durations,
+/// here and in the S3 store's retry deadlines, are measured with the wall clock
+pub(crate) fn observe(
+    observer: Option<&RequestObserver>,
+    operation: &'static str,
+    bytes: usize,
+    started: DateTime<Utc>,
+) {
+    if let Some(RequestObserver(observer)) = observer {
+        observer(RequestEvent {
+            operation,
+            bytes,
+            duration: (Utc::now() - started).to_std().unwrap_or_default(),
+        });
+    }
+}
@@ -24,3 +24,221 @@ macro_rules! log {
         println!("LOG - {}", format!( $( $t )* ));
     }
 }
+
+/// Interpret a configuration value as a boolean flag
+pub(crate) fn str_is_truthy(value: &str) -> bool {
+    ["true", "1", "yes", "on"]
+        .iter()
+        .any(|truthy| value.eq_ignore_ascii_case(truthy))
+}
+
+/// The smallest size S3 accepts for any but the last part of a multipart upload (5MiB)
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+/// The most parts S3 accepts in a single multipart upload
+pub const MAX_PARTS: usize = 10_000;
+
+/// Choose a part size for a multipart upload of `total_size` bytes, where known,
+/// that keeps the part count within [`MAX_PARTS`] without dropping below
+/// [`MIN_PART_SIZE`]
+///
+/// Uploads of unknown size use [`MIN_PART_SIZE`], limiting them to ~48GiB.
+pub fn multipart_part_size(total_size: Option<usize>) -> usize {
+    match total_size {
+        // round up without adding `MAX_PARTS - 1` first, which overflows near usize::MAX
+        Some(total_size) => {
+            (total_size / MAX_PARTS + usize::from(total_size % MAX_PARTS != 0)).max(MIN_PART_SIZE)
+        }
+        None => MIN_PART_SIZE,
+    }
+}
+
+/// The [`Attribute::Metadata`](object_store::Attribute::Metadata) key of the
+/// `Expires` header, as an RFC 3339 UTC timestamp
+///
+/// Set on `put` (accepting any format [`parse_http_date`] does, other values
+/// are ignored), and reported on `get`/`head` where the header is a valid date.
+pub const EXPIRES_ATTRIBUTE: &str = "expires";
+
+/// Parse an HTTP date, as found in `Expires`, in any of the formats servers
+/// send in practice
+///
+/// Accepts the preferred IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) along
+/// with other RFC 2822 dates, including numeric or named zone offsets, the
+/// obsolete RFC 850 (`Sunday, 06-Nov-94 08:49:37 GMT`) and asctime
+/// (`Sun Nov  6 08:49:37 1994`) formats, and RFC 3339. The latter two
+/// formats without an offset are interpreted as UTC.
+pub fn parse_http_date(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+
+    let value = value.trim();
+    if let Ok(date) =
+        DateTime::parse_from_rfc2822(value).or_else(|_| DateTime::parse_from_rfc3339(value))
+    {
+        return Some(date.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(value, "%A, %d-%b-%y %H:%M:%S GMT")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%a %b %e %H:%M:%S %Y"))
+        .map(|date| Utc.from_utc_datetime(&date))
+        .ok()
+}
+
+/// The `strftime` format of HTTP dates (IMF-fixdate), for UTC dates
+pub(crate) const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// The validator of an `If-Range` request, which is answered with the requested
+/// range if the object still matches it, or with the whole object otherwise
+#[derive(Debug, Clone)]
+pub enum IfRange {
+    /// A strong ETag, weak ETags never match
+    ETag(String),
+    /// The object's `Last-Modified` date
+    LastModified(chrono::DateTime<chrono::Utc>),
+}
+
+impl IfRange {
+    /// The value of the `If-Range` header
+    pub fn header_value(&self) -> String {
+        match self {
+            Self::ETag(e_tag) => e_tag.clone(),
+            Self::LastModified(date) => date.format(HTTP_DATE_FORMAT).to_string(),
+        }
+    }
+}
+
+/// Format `date` as the value of an [`EXPIRES_ATTRIBUTE`]
+pub fn expires_attribute_value(date: chrono::DateTime<chrono::Utc>) -> String {
+    date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// Replace the predefined and numeric XML character references in `text`
+#[cfg(feature = "http")]
+pub(crate) fn unescape_xml(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let reference = &rest[1..end];
+        let character = match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match reference.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => reference.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_parse_http_date() {
+        let expected = Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 37).unwrap();
+        let cases = [
+            // IMF-fixdate
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            // RFC 850
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            // asctime, the day padded with a space
+            "Sun Nov  6 08:49:37 1994",
+            "1994-11-06T08:49:37Z",
+            "1994-11-06T10:49:37+02:00",
+            "Sun, 06 Nov 1994 03:49:37 -0500",
+            "  Sun, 06 Nov 1994 08:49:37 GMT  ",
+        ];
+        for case in cases {
+            assert_eq!(parse_http_date(case), Some(expected), "{case}");
+        }
+
+        for case in [
+            "",
+            "0",
+            "-1",
+            "tomorrow",
+            "Sun, 06 Nov 1994",
+            "1994-13-06T08:49:37Z",
+        ] {
+            assert_eq!(parse_http_date(case), None, "{case}");
+        }
+    }
+    #[test]
+    fn test_if_range_header_value() {
+        let e_tag = IfRange::ETag("\"abc\"".to_string());
+        assert_eq!(e_tag.header_value(), "\"abc\"");
+        let date = Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 37).unwrap();
+        let last_modified = IfRange::LastModified(date);
+        assert_eq!(
+            last_modified.header_value(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(parse_http_date(&last_modified.header_value()), Some(date));
+    }
+    #[test]
+    // totals beyond MIN_PART_SIZE * MAX_PARTS overflow a 32-bit usize
+    #[cfg(target_pointer_width = "64")]
+    fn test_multipart_part_size() {
+        assert_eq!(multipart_part_size(None), MIN_PART_SIZE);
+        assert_eq!(multipart_part_size(Some(0)), MIN_PART_SIZE);
+        assert_eq!(multipart_part_size(Some(1024)), MIN_PART_SIZE);
+        assert_eq!(
+            multipart_part_size(Some(MIN_PART_SIZE * MAX_PARTS)),
+            MIN_PART_SIZE
+        );
+
+        for total in [
+            MIN_PART_SIZE * MAX_PARTS + 1,
+            MIN_PART_SIZE * MAX_PARTS * 3 + 7,
+            5 * 1024 * 1024 * 1024 * 1024,
+            usize::MAX - 1,
+            usize::MAX,
+        ] {
+            let part_size = multipart_part_size(Some(total));
+            assert!(part_size > MIN_PART_SIZE, "{total}");
+            let parts = total / part_size + usize::from(total % part_size != 0);
+            assert!(parts <= MAX_PARTS, "{total} in {parts} parts");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn test_unescape_xml() {
+        assert_eq!(unescape_xml("plain"), "plain");
+        assert_eq!(
+            unescape_xml("&lt;a&gt; &amp; &quot;b&quot; &apos;c&apos;"),
+            "<a> & \"b\" 'c'"
+        );
+        assert_eq!(unescape_xml("&#x41;&#66;&#x1F600;"), "AB\u{1F600}");
+        // unknown and invalid references are left as is
+        assert_eq!(
+            unescape_xml("&nbsp; &#xZZ; &#1114112;"),
+            "&nbsp; &#xZZ; &#1114112;"
+        );
+        // as is a dangling `&`
+        assert_eq!(unescape_xml("a & b"), "a & b");
+        assert_eq!(unescape_xml("a &amp; b &"), "a & b &");
+    }
+}